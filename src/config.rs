@@ -0,0 +1,619 @@
+//! Deployment configuration loaded from an optional `fuma.toml`.
+//!
+//! Every field has a default that reproduces the historical behavior, so a
+//! missing or partial config file never changes the generated output.
+
+use crate::constants::{
+    SemesterMapping, DEFAULT_BADGE_HOST, DEFAULT_DATE_FORMAT, DEFAULT_DOWNLOAD_HOST,
+    DEFAULT_README_PATH, GITHUB_ORG,
+};
+use crate::error::FumaError;
+use crate::fetcher::Source;
+use chrono::FixedOffset;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Top-level configuration, one table per pipeline stage.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub generator: GeneratorConfig,
+    pub tree: TreeConfig,
+    pub format: FormatConfig,
+    pub cache: CacheConfig,
+    pub fetch: FetchConfig,
+    pub report: ReportConfig,
+}
+
+/// Options controlling page and index generation.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GeneratorConfig {
+    /// Prefix prepended to every generated `href`, e.g. `/wiki` when the
+    /// site is hosted under a subpath. Empty means the domain root.
+    pub base_href: String,
+    /// Render the grading scheme as a markdown table below `<CourseInfo />`.
+    pub grade_table: bool,
+    /// Fetch each repo's `tag.txt` and emit its lines as frontmatter `tags`.
+    pub tag_txt_tags: bool,
+    /// Rebuild only `index.mdx` and `meta.json`, listing course pages that
+    /// already exist on disk without rewriting them. Set by `--index-only`.
+    #[serde(skip)]
+    pub index_only: bool,
+    /// Only rewrite the pages of these repos, plus the indexes and metadata
+    /// that list them; other pages are left as they are. Set by `--changed`.
+    #[serde(skip)]
+    pub changed_repo_ids: Option<HashSet<String>>,
+    /// Write a `meta.json` in each semester and category folder whose `pages`
+    /// list `index` first, then the courses in `folder_meta_order`.
+    pub folder_meta: bool,
+    pub folder_meta_order: CourseOrder,
+    /// Append links to the previous and next course of the same semester,
+    /// following the order courses appear in the training plan.
+    pub course_nav: bool,
+    /// Banners inserted below `<CourseInfo />` on every course page in scope.
+    pub notices: Vec<Notice>,
+    /// How a bare number in `recommended_semester` (e.g. `3`) is mapped to
+    /// a semester folder, for plans that count semesters sequentially.
+    pub numeric_semesters: SemesterNumbering,
+    /// Parse every generated frontmatter block back and fail the run if it
+    /// isn't valid YAML or its `title` doesn't round-trip.
+    pub strict_frontmatter: bool,
+    /// Majors to leave out entirely, as a major code (`CS`, every year) or
+    /// `year/major` (`2023/CS`).
+    pub major_blocklist: Vec<String>,
+    /// Emit the major's total credits as `totalCredits` in its `meta.json`
+    /// and on its index page.
+    pub major_credits: bool,
+    /// Add a schema.org `Course` JSON-LD script to each course page.
+    pub json_ld: bool,
+    /// `provider` organization name in the JSON-LD; omitted when empty.
+    pub json_ld_provider: String,
+    /// Template for semester titles, e.g. `Year {year_index} · {season_en}`.
+    /// Empty keeps the built-in `{year}·{season}` (大一·秋).
+    pub semester_title_template: String,
+    /// Semester folders in the order they're listed, e.g. to put a summer
+    /// term before the next autumn. Empty follows the semester mapping;
+    /// folders left out come last, alphabetically.
+    pub semester_order: Vec<String>,
+    /// Semester names, folders and titles; the built-in table unless the
+    /// data directory has a `semesters.toml`.
+    #[serde(skip)]
+    pub semesters: SemesterMapping,
+    /// Plan TOML course fields the crate doesn't model, e.g. `department`,
+    /// to copy into the frontmatter `course` block when present.
+    pub course_extra_fields: Vec<String>,
+    /// Add the normalized assessment method (考试 / 考查 / 合格制) to `tags`.
+    pub assessment_tag: bool,
+    /// Write a `search.mdx` listing every course once, sorted by title.
+    pub search_page: bool,
+    /// How a zero or unknown course credit appears in frontmatter.
+    pub zero_credit: ZeroCredit,
+    /// Insert a table of contents of the README's level 2–3 headings after
+    /// `<CourseInfo />` when it has at least this many. Unset disables it.
+    pub toc_min_headings: Option<usize>,
+}
+
+/// Frontmatter rendering of a zero or unknown credit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZeroCredit {
+    /// `credit: 0`
+    #[default]
+    Zero,
+    /// `credit: null`, for the component to render as "—"
+    Null,
+    /// No `credit` key at all
+    Omit,
+}
+
+impl GeneratorConfig {
+    /// Whether this run writes the course page of `repo_id`
+    pub fn should_regenerate(&self, repo_id: &str) -> bool {
+        !self.index_only
+            && self
+                .changed_repo_ids
+                .as_ref()
+                .is_none_or(|changed| changed.contains(repo_id))
+    }
+
+    /// Whether the major `major_code` of `year` is in `major_blocklist`
+    pub fn is_major_blocked(&self, year: &str, major_code: &str) -> bool {
+        self.major_blocklist
+            .iter()
+            .any(|entry| match entry.split_once('/') {
+                Some((y, code)) => y == year && code == major_code,
+                None => entry == major_code,
+            })
+    }
+}
+
+/// Interpretation of numeric `recommended_semester` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SemesterNumbering {
+    /// Numbers are not recognized
+    #[default]
+    Off,
+    /// Autumn and spring only: 1 → 大一·秋, 2 → 大一·春, 3 → 大二·秋
+    Sequential,
+    /// Summer terms count too: 1 → 大一·秋, 2 → 大一·春, 3 → 大一·夏
+    SequentialWithSummer,
+}
+
+/// A markdown banner shown on the course pages of a year, a major, or all.
+///
+/// The markdown goes through the formatter along with the rest of the page.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Notice {
+    pub scope: NoticeScope,
+    /// Year (e.g. `2023`) for `year`; major code (`CS`) or `year/major`
+    /// (`2023/CS`) for `major`; ignored for `all`.
+    #[serde(default)]
+    pub target: String,
+    pub markdown: String,
+}
+
+/// Which pages a [`Notice`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoticeScope {
+    Year,
+    Major,
+    All,
+}
+
+impl Notice {
+    /// Whether the notice applies to pages under `year/major_code`
+    pub fn applies_to(&self, year: &str, major_code: &str) -> bool {
+        match self.scope {
+            NoticeScope::All => true,
+            NoticeScope::Year => self.target == year,
+            NoticeScope::Major => {
+                self.target == major_code || self.target == format!("{}/{}", year, major_code)
+            }
+        }
+    }
+}
+
+/// Course ordering used inside folder `meta.json` files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CourseOrder {
+    /// Order of appearance in the training plan
+    #[default]
+    Plan,
+    /// Highest credit first; ties and unknown credits keep plan order
+    Credit,
+}
+
+/// Order of entries within each folder of the `<Files>` tree. Folders always
+/// come before files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TreeSort {
+    /// Alphabetical, case-insensitive
+    #[default]
+    AlphaFoldersFirst,
+    /// Newest first; a folder's date is that of its newest file
+    DateDescending,
+    /// Oldest first
+    DateAscending,
+}
+
+/// Extra file tree exclusions on top of the built-in ones in `constants`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExclusionRules {
+    /// Exact file names to exclude, e.g. `Thumbs.db`.
+    pub patterns: Vec<String>,
+    /// File name suffixes to exclude, e.g. `.zip`.
+    pub extensions: Vec<String>,
+    /// Path prefixes to exclude, e.g. `drafts/`.
+    pub prefixes: Vec<String>,
+    /// Built-in patterns, extensions or prefixes to stop excluding,
+    /// e.g. `README.md`.
+    pub keep: Vec<String>,
+}
+
+/// Options controlling the `<Files>` download tree.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TreeConfig {
+    /// Ordered proxy hosts used to build download URLs. The first entry is
+    /// the primary link; the rest are failover candidates.
+    pub download_hosts: Vec<String>,
+    /// GitHub organization owning the course repositories in download URLs.
+    pub download_org: String,
+    /// Branch for download URLs, replacing the default branch recorded by
+    /// `--fetch` (or `main`) for every repo.
+    pub download_branch: Option<String>,
+    /// Emit the remaining hosts as a `data-fallback` attribute on each file.
+    pub emit_fallback_urls: bool,
+    /// Exact worktree paths to hide, keyed by repo ID. Applied after the
+    /// pattern-based exclusions, e.g. an accidentally committed answer key.
+    pub hidden_files: HashMap<String, Vec<String>>,
+    /// Exclusion rules applied to every repo, merged with the built-ins.
+    pub exclude: ExclusionRules,
+    /// chrono format string for file dates, e.g. `%Y年%m月%d日`.
+    pub date_format: String,
+    /// Time zone file dates are shown in, written as a UTC offset like
+    /// `+08:00`. Anything else is rejected when the config is parsed.
+    #[serde(deserialize_with = "deserialize_utc_offset")]
+    pub utc_offset: FixedOffset,
+    /// Show file dates relative to now ("3 天前") instead of formatted.
+    pub relative_dates: bool,
+    /// Order of entries within each folder.
+    pub sort: TreeSort,
+    /// Merge chains of folders that each hold a single subfolder into one
+    /// node named by the joined path, e.g. `slides/2023/week1`.
+    pub collapse_folders: bool,
+    /// Deepest folder level listed, 0 being the top level; deeper contents
+    /// are replaced by a "... (N more items)" entry. Unlimited when unset.
+    pub max_depth: Option<usize>,
+    /// Reference Unix time for relative dates; the current time when unset.
+    #[serde(skip)]
+    pub date_reference: Option<i64>,
+}
+
+/// Parse `utc_offset` from a string like `+08:00`
+fn deserialize_utc_offset<'de, D>(deserializer: D) -> Result<FixedOffset, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(|_| {
+        serde::de::Error::custom(format!(
+            "invalid utc_offset {:?}, expected an offset like \"+08:00\"",
+            value
+        ))
+    })
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            download_hosts: vec![DEFAULT_DOWNLOAD_HOST.to_string()],
+            download_org: GITHUB_ORG.to_string(),
+            download_branch: None,
+            emit_fallback_urls: false,
+            hidden_files: HashMap::new(),
+            exclude: ExclusionRules::default(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            utc_offset: FixedOffset::east_opt(0).expect("zero offset is valid"),
+            relative_dates: false,
+            sort: TreeSort::AlphaFoldersFirst,
+            collapse_folders: false,
+            max_depth: None,
+            date_reference: None,
+        }
+    }
+}
+
+/// Options controlling MDX formatting.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    /// Tag names to self-close in addition to the built-in HTML void elements.
+    pub extra_void_elements: Vec<String>,
+    /// Substrings of image URLs treated as badges, e.g. `badgen.net`.
+    pub badge_hosts: Vec<String>,
+    /// When non-empty, only badges whose alt text matches one of these labels
+    /// (case-insensitive) are removed; other badges are kept. Empty removes
+    /// every line containing a badge host.
+    pub badge_alt_denylist: Vec<String>,
+    /// Maximum number of consecutive blank lines kept in a document.
+    pub max_blank_lines: usize,
+    /// Remove blank lines at the start and end of each document.
+    pub trim_blank_lines: bool,
+    /// Code fence language renames, matched case-insensitively, e.g.
+    /// `py = "python"`. Empty by default, so fence tags are kept verbatim.
+    pub fence_language_aliases: HashMap<String, String>,
+    /// Write `\r\n` line endings back to files that used them. Formatting
+    /// always works on `\n`; by default the output keeps `\n`.
+    pub preserve_crlf: bool,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            extra_void_elements: Vec::new(),
+            badge_hosts: vec![DEFAULT_BADGE_HOST.to_string()],
+            badge_alt_denylist: Vec::new(),
+            max_blank_lines: 1,
+            trim_blank_lines: false,
+            fence_language_aliases: HashMap::new(),
+            preserve_crlf: false,
+        }
+    }
+}
+
+/// Options controlling the local `repos/` cache shared by fetcher and generator.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// File layout of the cache. Also set to `sharded` by `--sharded-repos`.
+    pub layout: ReposLayout,
+    /// Where to re-fetch a cached `{repo}.json` from, once, when it fails to
+    /// parse (e.g. after an interrupted write). Set by `--refetch-corrupt`.
+    #[serde(skip)]
+    pub refetch_source: Option<Arc<Source>>,
+}
+
+/// How cached `{repo}.mdx` / `{repo}.json` files are laid out in `repos_dir`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReposLayout {
+    /// `repos_dir/REPO.*`
+    #[default]
+    Flat,
+    /// `repos_dir/<first 2 chars of REPO>/REPO.*`, to keep directories small
+    Sharded,
+}
+
+impl ReposLayout {
+    /// Path of the cached file `{repo}.{ext}` under `repos_dir`
+    pub fn repo_file(self, repos_dir: &Path, repo: &str, ext: &str) -> PathBuf {
+        let file_name = format!("{}.{}", repo, ext);
+        match self {
+            ReposLayout::Flat => repos_dir.join(file_name),
+            ReposLayout::Sharded => {
+                let shard: String = repo.chars().take(2).collect();
+                repos_dir.join(shard).join(file_name)
+            }
+        }
+    }
+}
+
+/// Options controlling what `--fetch` downloads from each repo.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FetchConfig {
+    /// GitHub API endpoint, e.g. `https://github.example.com/api/v3` for
+    /// GitHub Enterprise; the public API when unset.
+    pub base_url: Option<String>,
+    /// README paths tried in order until one exists, e.g. `readme.md`.
+    pub readme_paths: Vec<String>,
+    /// Branch to read the README from; the repo's default branch when unset.
+    pub readme_branch: Option<String>,
+    /// Download files from raw.githubusercontent.com instead of the contents
+    /// API, falling back to the API for files the raw host can't serve.
+    pub raw_content: bool,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            base_url: None,
+            readme_paths: vec![DEFAULT_README_PATH.to_string()],
+            readme_branch: None,
+            raw_content: false,
+        }
+    }
+}
+
+/// Options controlling the end-of-run issue report.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReportConfig {
+    /// Where to write `report.json`; no report is written when unset.
+    /// Overridden by `--report <path>`.
+    pub path: Option<PathBuf>,
+}
+
+/// Load fuma.toml from the repository root if present.
+///
+/// Returns the default config if the file doesn't exist. A file that exists
+/// but can't be read or parsed is an error rather than a silent fallback,
+/// since defaults for e.g. `base_href` would break every link on the site.
+pub fn load_config(repo_root: &Path) -> crate::error::Result<Config> {
+    let path = repo_root.join("fuma.toml");
+
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|source| FumaError::ConfigParse { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config_missing_file() {
+        let temp_dir = std::env::temp_dir().join("test_config_missing");
+        let _ = fs::create_dir_all(&temp_dir);
+
+        let config = load_config(&temp_dir).unwrap();
+        assert_eq!(config.generator.base_href, "");
+        assert_eq!(
+            config.tree.download_hosts,
+            vec!["https://gh.hoa.moe/github.com".to_string()]
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_load_config_base_href() {
+        let temp_dir = std::env::temp_dir().join("test_config_base_href");
+        let _ = fs::create_dir_all(&temp_dir);
+        fs::write(
+            temp_dir.join("fuma.toml"),
+            "[generator]\nbase_href = \"/wiki\"\n",
+        )
+        .unwrap();
+
+        let config = load_config(&temp_dir).unwrap();
+        assert_eq!(config.generator.base_href, "/wiki");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_load_config_malformed_file() {
+        let temp_dir = std::env::temp_dir().join("test_config_malformed");
+        let _ = fs::create_dir_all(&temp_dir);
+        fs::write(
+            temp_dir.join("fuma.toml"),
+            "[generator]\nbase_href = \"/wiki\"\n[tree\n",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            load_config(&temp_dir),
+            Err(FumaError::ConfigParse { .. })
+        ));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_load_config_bad_utc_offset() {
+        let temp_dir = std::env::temp_dir().join("test_config_bad_utc_offset");
+        let _ = fs::create_dir_all(&temp_dir);
+        let write = |offset: &str| {
+            fs::write(
+                temp_dir.join("fuma.toml"),
+                format!(
+                    "[generator]\nbase_href = \"/wiki\"\n[tree]\nutc_offset = \"{}\"\n",
+                    offset
+                ),
+            )
+            .unwrap()
+        };
+
+        write("UTC+8");
+        let error = load_config(&temp_dir).unwrap_err();
+        assert!(matches!(error, FumaError::ConfigParse { .. }));
+        assert!(
+            error.to_string().contains("invalid utc_offset"),
+            "{}",
+            error
+        );
+
+        // Fixing the offset keeps the rest of the file
+        write("+08:00");
+        let config = load_config(&temp_dir).unwrap();
+        assert_eq!(config.generator.base_href, "/wiki");
+        assert_eq!(config.tree.utc_offset.local_minus_utc(), 8 * 3600);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_load_config_folder_meta_order() {
+        let temp_dir = std::env::temp_dir().join("test_config_folder_meta_order");
+        let _ = fs::create_dir_all(&temp_dir);
+        fs::write(
+            temp_dir.join("fuma.toml"),
+            "[generator]\nfolder_meta = true\nfolder_meta_order = \"credit\"\n",
+        )
+        .unwrap();
+
+        let config = load_config(&temp_dir).unwrap();
+        assert!(config.generator.folder_meta);
+        assert_eq!(config.generator.folder_meta_order, CourseOrder::Credit);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_load_config_fetch_readme() {
+        let temp_dir = std::env::temp_dir().join("test_config_fetch_readme");
+        let _ = fs::create_dir_all(&temp_dir);
+        fs::write(
+            temp_dir.join("fuma.toml"),
+            "[fetch]\nreadme_paths = [\"readme.md\", \"README.md\"]\nreadme_branch = \"docs\"\n",
+        )
+        .unwrap();
+
+        let config = load_config(&temp_dir).unwrap();
+        assert_eq!(config.fetch.readme_paths, vec!["readme.md", "README.md"]);
+        assert_eq!(config.fetch.readme_branch.as_deref(), Some("docs"));
+        assert_eq!(Config::default().fetch.readme_paths, vec!["README.md"]);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        let config: Config = toml::from_str("[tree]\nutc_offset = \"+08:00\"\n").unwrap();
+        assert_eq!(config.tree.utc_offset.local_minus_utc(), 8 * 3600);
+
+        let error = toml::from_str::<Config>("[tree]\nutc_offset = \"Asia/Shanghai\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("invalid utc_offset \"Asia/Shanghai\""),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_repos_layout_repo_file() {
+        let dir = Path::new("repos");
+        assert_eq!(
+            ReposLayout::Flat.repo_file(dir, "COMP1001", "mdx"),
+            PathBuf::from("repos/COMP1001.mdx")
+        );
+        assert_eq!(
+            ReposLayout::Sharded.repo_file(dir, "COMP1001", "tag.txt"),
+            PathBuf::from("repos/CO/COMP1001.tag.txt")
+        );
+        assert_eq!(
+            ReposLayout::Sharded.repo_file(dir, "X", "json"),
+            PathBuf::from("repos/X/X.json")
+        );
+    }
+
+    #[test]
+    fn test_notice_applies_to() {
+        let config: Config = toml::from_str(
+            r#"
+[[generator.notices]]
+scope = "major"
+target = "2023/CS"
+markdown = "考试周"
+
+[[generator.notices]]
+scope = "year"
+target = "2024"
+markdown = "新方案"
+
+[[generator.notices]]
+scope = "all"
+markdown = "全站公告"
+"#,
+        )
+        .unwrap();
+        let [major, year, all] = &config.generator.notices[..] else {
+            panic!("expected three notices");
+        };
+
+        assert!(major.applies_to("2023", "CS"));
+        assert!(!major.applies_to("2024", "CS"));
+        assert!(!major.applies_to("2023", "EE"));
+        assert!(year.applies_to("2024", "EE"));
+        assert!(!year.applies_to("2023", "EE"));
+        assert!(all.applies_to("2020", "ME"));
+    }
+
+    #[test]
+    fn test_is_major_blocked() {
+        let config = GeneratorConfig {
+            major_blocklist: vec!["EE".to_string(), "2023/CS".to_string()],
+            ..GeneratorConfig::default()
+        };
+        assert!(config.is_major_blocked("2022", "EE"));
+        assert!(config.is_major_blocked("2023", "CS"));
+        assert!(!config.is_major_blocked("2024", "CS"));
+        assert!(!config.is_major_blocked("2023", "ME"));
+    }
+}
@@ -1,5 +1,10 @@
+use crate::config::{ExclusionRules, SemesterNumbering};
+
+/// A semester's Chinese name, folder name and display title
+pub type SemesterEntry = (&'static str, &'static str, &'static str);
+
 /// Semester mapping from Chinese names to folder names and display titles
-pub const SEMESTER_MAPPING: &[(&str, &str, &str)] = &[
+pub const SEMESTER_MAPPING: &[SemesterEntry] = &[
     ("第一学年秋季", "fresh-autumn", "大一·秋"),
     ("第一学年春季", "fresh-spring", "大一·春"),
     ("第一学年夏季", "fresh-summer", "大一·夏"),
@@ -17,20 +22,173 @@ pub const SEMESTER_MAPPING: &[(&str, &str, &str)] = &[
     ("第五学年夏季", "fifth-summer", "大五·夏"),
 ];
 
+/// The semester table in effect for a run: the built-in
+/// [`SEMESTER_MAPPING`], or a custom one loaded from `semesters.toml`.
+/// Entries are in calendar order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SemesterMapping {
+    custom: Option<Vec<(String, String, String)>>,
+}
+
+impl SemesterMapping {
+    /// A mapping of `(name, folder, title)` entries replacing the built-in one
+    pub fn custom(entries: Vec<(String, String, String)>) -> Self {
+        Self {
+            custom: Some(entries),
+        }
+    }
+
+    /// Whether this mapping replaces [`SEMESTER_MAPPING`]
+    pub fn is_custom(&self) -> bool {
+        self.custom.is_some()
+    }
+
+    /// Every semester's Chinese name, folder and title, in order
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        let custom = self
+            .custom
+            .iter()
+            .flatten()
+            .map(|(name, folder, title)| (name.as_str(), folder.as_str(), title.as_str()));
+        let builtin = SEMESTER_MAPPING
+            .iter()
+            .filter(move |_| self.custom.is_none())
+            .copied();
+        custom.chain(builtin)
+    }
+}
+
 /// Get semester folder and title from Chinese semester name
-pub fn get_semester_folder(recommended: &str) -> Option<(&'static str, &'static str)> {
-    SEMESTER_MAPPING
-        .iter()
-        .find(|&&(key, _, _)| key == recommended)
-        .map(|&(_, folder, title)| (folder, title))
+pub fn get_semester_folder<'a>(
+    semesters: &'a SemesterMapping,
+    recommended: &str,
+) -> Option<(&'a str, &'a str)> {
+    semesters
+        .entries()
+        .find(|&(key, _, _)| key == recommended)
+        .map(|(_, folder, title)| (folder, title))
+}
+
+/// Get the folder and title of the `n`-th semester (1-based) under a
+/// sequential numbering scheme.
+pub fn get_numbered_semester_folder(
+    semesters: &SemesterMapping,
+    n: usize,
+    numbering: SemesterNumbering,
+) -> Option<(&str, &str)> {
+    let include_summer = match numbering {
+        SemesterNumbering::Off => return None,
+        SemesterNumbering::Sequential => false,
+        SemesterNumbering::SequentialWithSummer => true,
+    };
+    semesters
+        .entries()
+        .filter(|(_, folder, _)| include_summer || !folder.ends_with("-summer"))
+        .nth(n.checked_sub(1)?)
+        .map(|(_, folder, title)| (folder, title))
+}
+
+/// Display labels for each academic year, indexed by `year_index - 1`
+const YEAR_LABELS: &[&str] = &["大一", "大二", "大三", "大四", "大五"];
+
+/// Folder suffix, Chinese label and English label of each season
+const SEASON_LABELS: &[(&str, &str, &str)] = &[
+    ("autumn", "秋", "Autumn"),
+    ("spring", "春", "Spring"),
+    ("summer", "夏", "Summer"),
+];
+
+/// Template reproducing the titles in `SEMESTER_MAPPING`
+pub const DEFAULT_SEMESTER_TITLE_TEMPLATE: &str = "{year}·{season}";
+
+/// Format a semester folder's display title from its year and season.
+///
+/// `template` may use `{year}` (大一), `{year_index}` (1), `{season}` (秋)
+/// and `{season_en}` (Autumn); an empty template means
+/// [`DEFAULT_SEMESTER_TITLE_TEMPLATE`]. Returns `None` for unknown folders.
+///
+/// With a custom mapping the template is ignored and the mapping's own
+/// titles are used, since its folders needn't follow the built-in years and
+/// seasons.
+pub fn format_semester_title(
+    semesters: &SemesterMapping,
+    folder: &str,
+    template: &str,
+) -> Option<String> {
+    if semesters.is_custom() {
+        return get_semester_title_by_folder(semesters, folder).map(str::to_string);
+    }
+    let position = SEMESTER_MAPPING.iter().position(|(_, f, _)| *f == folder)?;
+    let year_index = position / SEASON_LABELS.len();
+    let (suffix, season, season_en) = SEASON_LABELS[position % SEASON_LABELS.len()];
+    debug_assert!(folder.ends_with(suffix));
+
+    let template = if template.is_empty() {
+        DEFAULT_SEMESTER_TITLE_TEMPLATE
+    } else {
+        template
+    };
+    Some(
+        template
+            .replace("{year_index}", &(year_index + 1).to_string())
+            .replace("{year}", YEAR_LABELS[year_index])
+            .replace("{season_en}", season_en)
+            .replace("{season}", season),
+    )
 }
 
 /// Get semester title from folder name.
-pub fn get_semester_title_by_folder(folder: &str) -> Option<&'static str> {
-    SEMESTER_MAPPING
+pub fn get_semester_title_by_folder<'a>(
+    semesters: &'a SemesterMapping,
+    folder: &str,
+) -> Option<&'a str> {
+    semesters
+        .entries()
+        .find(|&(_, f, _)| f == folder)
+        .map(|(_, _, title)| title)
+}
+
+/// Order a set of semester folders by academic calendar.
+///
+/// Folders follow `order`, or the semester mapping's order (not
+/// alphabetical) when `order` is empty. Folders missing from that order are
+/// appended afterwards in alphabetical order, so nothing is silently lost and
+/// the output stays deterministic.
+pub fn order_semester_folders(
+    semesters: &SemesterMapping,
+    folders: &std::collections::HashSet<String>,
+    order: &[String],
+) -> Vec<String> {
+    let order: Vec<&str> = if order.is_empty() {
+        semesters.entries().map(|(_, folder, _)| folder).collect()
+    } else {
+        order.iter().map(String::as_str).collect()
+    };
+
+    let mut ordered: Vec<String> = order
+        .iter()
+        .filter(|folder| folders.contains(**folder))
+        .map(|folder| folder.to_string())
+        .collect();
+
+    let mut unknown: Vec<String> = folders
         .iter()
-        .find(|&&(_, f, _)| f == folder)
-        .map(|&(_, _, title)| title)
+        .filter(|folder| !order.contains(&folder.as_str()))
+        .cloned()
+        .collect();
+    unknown.sort();
+    ordered.extend(unknown);
+
+    ordered
+}
+
+/// Split a semester field on the ASCII comma, fullwidth comma or 、,
+/// yielding the non-empty trimmed values
+pub fn split_semester_tokens(recommended: &str) -> impl Iterator<Item = &str> {
+    recommended
+        .split(|c| [',', '，', '、'].contains(&c))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
 }
 
 /// Parse semester field that may contain multiple semester values.
@@ -39,17 +197,23 @@ pub fn get_semester_title_by_folder(folder: &str) -> Option<&'static str> {
 /// - "第三学年秋季"
 /// - "第三学年秋季,第四学年秋季"
 /// - "第三学年秋季，第四学年秋季"
-pub fn parse_semester_folders(recommended: &str) -> Vec<(&'static str, &'static str)> {
+/// - "3", when `numbering` maps bare semester numbers
+pub fn parse_semester_folders<'a>(
+    semesters: &'a SemesterMapping,
+    recommended: &str,
+    numbering: SemesterNumbering,
+) -> Vec<(&'a str, &'a str)> {
     let mut folders = Vec::new();
     let mut seen = std::collections::HashSet::new();
 
-    for token in recommended.split(|c| [',', '，', '、'].contains(&c)) {
-        let semester = token.trim();
-        if semester.is_empty() {
-            continue;
-        }
-
-        if let Some((folder, title)) = get_semester_folder(semester) {
+    for semester in split_semester_tokens(recommended) {
+        let mapped = get_semester_folder(semesters, semester).or_else(|| {
+            semester
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| get_numbered_semester_folder(semesters, n, numbering))
+        });
+        if let Some((folder, title)) = mapped {
             if seen.insert(folder) {
                 folders.push((folder, title));
             }
@@ -59,6 +223,39 @@ pub fn parse_semester_folders(recommended: &str) -> Vec<(&'static str, &'static
     folders
 }
 
+/// GitHub organization that hosts the course repositories
+pub const GITHUB_ORG: &str = "HITSZ-OpenAuto";
+
+/// GitHub REST API endpoint used by the fetcher
+pub const GITHUB_API_BASE: &str = "https://api.github.com";
+
+/// Branch assumed for download links when a repo's default branch is unknown
+pub const DEFAULT_BRANCH: &str = "main";
+
+/// Branch holding each repo's generated worktree.json
+pub const WORKTREE_BRANCH: &str = "worktree";
+
+/// Host serving raw file contents of public GitHub repos
+pub const GITHUB_RAW_BASE: &str = "https://raw.githubusercontent.com";
+
+/// README fetched for each repo when no candidates are configured
+pub const DEFAULT_README_PATH: &str = "README.md";
+
+/// Image host whose badges the formatter strips unless configured otherwise
+pub const DEFAULT_BADGE_HOST: &str = "img.shields.io";
+
+/// chrono format used for file dates unless configured otherwise
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Proxy host used for resource download links when none is configured
+pub const DEFAULT_DOWNLOAD_HOST: &str = "https://gh.hoa.moe/github.com";
+
+/// HTML void elements, which MDX only accepts in self-closed form
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
 /// Files to exclude from the file tree
 pub const EXCLUDED_PATTERNS: &[&str] = &[".gitkeep", "README.md", "LICENSE", "tag.txt"];
 
@@ -68,28 +265,39 @@ pub const EXCLUDED_EXTENSIONS: &[&str] = &[".toml"];
 /// Directory prefixes to exclude
 pub const EXCLUDED_PREFIXES: &[&str] = &[".github/"];
 
-/// Check if a file path should be included in the file tree
-pub fn should_include_file(path: &str) -> bool {
+/// Check if a file path should be included in the file tree.
+///
+/// The built-in exclusions apply together with `rules`' additions, except
+/// for built-in entries listed in `rules.keep`.
+pub fn should_include_file(path: &str, rules: &ExclusionRules) -> bool {
     let filename = path.split('/').next_back().unwrap_or("");
+    let excluded = |builtin: &[&'static str], extra: &[String], matches: &dyn Fn(&str) -> bool| {
+        builtin
+            .iter()
+            .copied()
+            .filter(|rule| !rules.keep.iter().any(|kept| kept == rule))
+            .chain(extra.iter().map(String::as_str))
+            .any(matches)
+    };
 
     // Check exact matches
-    if EXCLUDED_PATTERNS.contains(&filename) {
+    if excluded(EXCLUDED_PATTERNS, &rules.patterns, &|pattern| {
+        pattern == filename
+    }) {
         return false;
     }
 
     // Check extensions
-    if EXCLUDED_EXTENSIONS
-        .iter()
-        .any(|ext| filename.ends_with(ext))
-    {
+    if excluded(EXCLUDED_EXTENSIONS, &rules.extensions, &|ext| {
+        filename.ends_with(ext)
+    }) {
         return false;
     }
 
     // Check prefixes
-    if EXCLUDED_PREFIXES
-        .iter()
-        .any(|prefix| path.starts_with(prefix))
-    {
+    if excluded(EXCLUDED_PREFIXES, &rules.prefixes, &|prefix| {
+        path.starts_with(prefix)
+    }) {
         return false;
     }
 
@@ -102,40 +310,48 @@ mod tests {
 
     #[test]
     fn test_get_semester_folder_valid() {
-        let result = get_semester_folder("第一学年秋季");
+        let semesters = SemesterMapping::default();
+        let result = get_semester_folder(&semesters, "第一学年秋季");
         assert_eq!(result, Some(("fresh-autumn", "大一·秋")));
 
-        let result = get_semester_folder("第二学年春季");
+        let result = get_semester_folder(&semesters, "第二学年春季");
         assert_eq!(result, Some(("sophomore-spring", "大二·春")));
 
-        let result = get_semester_folder("第四学年夏季");
+        let result = get_semester_folder(&semesters, "第四学年夏季");
         assert_eq!(result, Some(("senior-summer", "大四·夏")));
 
-        let result = get_semester_folder("第五学年春季");
+        let result = get_semester_folder(&semesters, "第五学年春季");
         assert_eq!(result, Some(("fifth-spring", "大五·春")));
     }
 
     #[test]
     fn test_get_semester_folder_invalid() {
-        let result = get_semester_folder("第六学年秋季");
+        let semesters = SemesterMapping::default();
+        let result = get_semester_folder(&semesters, "第六学年秋季");
         assert_eq!(result, None);
 
-        let result = get_semester_folder("invalid");
+        let result = get_semester_folder(&semesters, "invalid");
         assert_eq!(result, None);
 
-        let result = get_semester_folder("");
+        let result = get_semester_folder(&semesters, "");
         assert_eq!(result, None);
     }
 
     #[test]
     fn test_parse_semester_folders_single() {
-        let result = parse_semester_folders("第二学年夏季");
+        let semesters = SemesterMapping::default();
+        let result = parse_semester_folders(&semesters, "第二学年夏季", SemesterNumbering::Off);
         assert_eq!(result, vec![("sophomore-summer", "大二·夏")]);
     }
 
     #[test]
     fn test_parse_semester_folders_multiple() {
-        let result = parse_semester_folders("第三学年秋季,第四学年秋季");
+        let semesters = SemesterMapping::default();
+        let result = parse_semester_folders(
+            &semesters,
+            "第三学年秋季,第四学年秋季",
+            SemesterNumbering::Off,
+        );
         assert_eq!(
             result,
             vec![("junior-autumn", "大三·秋"), ("senior-autumn", "大四·秋")]
@@ -144,62 +360,130 @@ mod tests {
 
     #[test]
     fn test_parse_semester_folders_dedup_and_invalid() {
-        let result = parse_semester_folders("第三学年秋季，第三学年秋季，未知学期");
+        let semesters = SemesterMapping::default();
+        let result = parse_semester_folders(
+            &semesters,
+            "第三学年秋季，第三学年秋季，未知学期",
+            SemesterNumbering::Off,
+        );
         assert_eq!(result, vec![("junior-autumn", "大三·秋")]);
     }
 
     #[test]
     fn test_get_semester_title_by_folder() {
+        let semesters = SemesterMapping::default();
         assert_eq!(
-            get_semester_title_by_folder("fresh-summer"),
+            get_semester_title_by_folder(&semesters, "fresh-summer"),
             Some("大一·夏")
         );
         assert_eq!(
-            get_semester_title_by_folder("fifth-autumn"),
+            get_semester_title_by_folder(&semesters, "fifth-autumn"),
             Some("大五·秋")
         );
-        assert_eq!(get_semester_title_by_folder("unknown"), None);
+        assert_eq!(get_semester_title_by_folder(&semesters, "unknown"), None);
+    }
+
+    #[test]
+    fn test_order_semester_folders_semantic_order() {
+        let semesters = SemesterMapping::default();
+        let folders: std::collections::HashSet<String> = [
+            "senior-autumn",
+            "fresh-spring",
+            "fresh-autumn",
+            "junior-summer",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(
+            order_semester_folders(&semesters, &folders, &[]),
+            vec![
+                "fresh-autumn",
+                "fresh-spring",
+                "junior-summer",
+                "senior-autumn"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_semester_folders_unknown_appended() {
+        let semesters = SemesterMapping::default();
+        let folders: std::collections::HashSet<String> = ["zeta", "sophomore-autumn", "alpha"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(
+            order_semester_folders(&semesters, &folders, &[]),
+            vec!["sophomore-autumn", "alpha", "zeta"]
+        );
+    }
+
+    #[test]
+    fn test_order_semester_folders_custom_order() {
+        let semesters = SemesterMapping::default();
+        let folders: std::collections::HashSet<String> =
+            ["fresh-summer", "fresh-spring", "fresh-autumn", "extra"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+        let order: Vec<String> = ["fresh-spring", "fresh-summer", "fresh-autumn"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(
+            order_semester_folders(&semesters, &folders, &order),
+            vec!["fresh-spring", "fresh-summer", "fresh-autumn", "extra"]
+        );
     }
 
     #[test]
     fn test_should_include_file_excluded_patterns() {
-        assert!(!should_include_file(".gitkeep"));
-        assert!(!should_include_file("README.md"));
-        assert!(!should_include_file("LICENSE"));
-        assert!(!should_include_file("tag.txt"));
-        assert!(!should_include_file("folder/.gitkeep"));
-        assert!(!should_include_file("docs/README.md"));
+        let rules = ExclusionRules::default();
+        assert!(!should_include_file(".gitkeep", &rules));
+        assert!(!should_include_file("README.md", &rules));
+        assert!(!should_include_file("LICENSE", &rules));
+        assert!(!should_include_file("tag.txt", &rules));
+        assert!(!should_include_file("folder/.gitkeep", &rules));
+        assert!(!should_include_file("docs/README.md", &rules));
     }
 
     #[test]
     fn test_should_include_file_excluded_extensions() {
-        assert!(!should_include_file("config.toml"));
-        assert!(!should_include_file("folder/settings.toml"));
-        assert!(!should_include_file("path/to/file.toml"));
+        let rules = ExclusionRules::default();
+        assert!(!should_include_file("config.toml", &rules));
+        assert!(!should_include_file("folder/settings.toml", &rules));
+        assert!(!should_include_file("path/to/file.toml", &rules));
     }
 
     #[test]
     fn test_should_include_file_excluded_prefixes() {
-        assert!(!should_include_file(".github/workflows/ci.yml"));
-        assert!(!should_include_file(".github/ISSUE_TEMPLATE.md"));
+        let rules = ExclusionRules::default();
+        assert!(!should_include_file(".github/workflows/ci.yml", &rules));
+        assert!(!should_include_file(".github/ISSUE_TEMPLATE.md", &rules));
     }
 
     #[test]
     fn test_should_include_file_valid_files() {
-        assert!(should_include_file("notes.pdf"));
-        assert!(should_include_file("lecture.pptx"));
-        assert!(should_include_file("folder/document.docx"));
-        assert!(should_include_file("path/to/file.txt"));
-        assert!(should_include_file("code.py"));
-        assert!(should_include_file("assignment.md"));
+        let rules = ExclusionRules::default();
+        assert!(should_include_file("notes.pdf", &rules));
+        assert!(should_include_file("lecture.pptx", &rules));
+        assert!(should_include_file("folder/document.docx", &rules));
+        assert!(should_include_file("path/to/file.txt", &rules));
+        assert!(should_include_file("code.py", &rules));
+        assert!(should_include_file("assignment.md", &rules));
     }
 
     #[test]
     fn test_should_include_file_edge_cases() {
-        assert!(should_include_file("readme.txt")); // Not exactly README.md
-        assert!(should_include_file("my.toml.txt")); // Doesn't end with .toml
-        assert!(should_include_file("github/file.txt")); // Not .github prefix
-        assert!(!should_include_file(".github/file.txt")); // Is .github prefix
+        let rules = ExclusionRules::default();
+        assert!(should_include_file("readme.txt", &rules)); // Not exactly README.md
+        assert!(should_include_file("my.toml.txt", &rules)); // Doesn't end with .toml
+        assert!(should_include_file("github/file.txt", &rules)); // Not .github prefix
+        assert!(!should_include_file(".github/file.txt", &rules)); // Is .github prefix
     }
 
     #[test]
@@ -219,4 +503,117 @@ mod tests {
             assert!(titles.insert(title), "Duplicate title: {}", title);
         }
     }
+
+    #[test]
+    fn test_parse_semester_folders_numeric() {
+        let semesters = SemesterMapping::default();
+        assert_eq!(
+            parse_semester_folders(&semesters, "3", SemesterNumbering::Sequential),
+            vec![("sophomore-autumn", "大二·秋")]
+        );
+        assert_eq!(
+            parse_semester_folders(&semesters, "3", SemesterNumbering::SequentialWithSummer),
+            vec![("fresh-summer", "大一·夏")]
+        );
+        assert_eq!(
+            parse_semester_folders(&semesters, "1, 第一学年春季", SemesterNumbering::Sequential),
+            vec![("fresh-autumn", "大一·秋"), ("fresh-spring", "大一·春")]
+        );
+        assert!(parse_semester_folders(&semesters, "0", SemesterNumbering::Sequential).is_empty());
+        assert!(parse_semester_folders(&semesters, "11", SemesterNumbering::Sequential).is_empty());
+        assert!(parse_semester_folders(&semesters, "3", SemesterNumbering::Off).is_empty());
+    }
+
+    #[test]
+    fn test_format_semester_title_default_matches_mapping() {
+        let semesters = SemesterMapping::default();
+        for (_, folder, title) in SEMESTER_MAPPING {
+            assert_eq!(
+                format_semester_title(&semesters, folder, "").as_deref(),
+                Some(*title)
+            );
+            assert_eq!(
+                format_semester_title(&semesters, folder, DEFAULT_SEMESTER_TITLE_TEMPLATE)
+                    .as_deref(),
+                Some(*title)
+            );
+        }
+        assert_eq!(format_semester_title(&semesters, "unknown", ""), None);
+    }
+
+    #[test]
+    fn test_custom_semester_mapping() {
+        let semesters = SemesterMapping::custom(vec![
+            (
+                "第一学期".to_string(),
+                "term-1".to_string(),
+                "Term 1".to_string(),
+            ),
+            (
+                "夏季学期".to_string(),
+                "term-1-summer".to_string(),
+                "Summer".to_string(),
+            ),
+            (
+                "第二学期".to_string(),
+                "term-2".to_string(),
+                "Term 2".to_string(),
+            ),
+        ]);
+        assert_eq!(get_semester_folder(&semesters, "第一学年秋季"), None);
+        assert_eq!(
+            parse_semester_folders(&semesters, "第二学期, 2", SemesterNumbering::Sequential),
+            vec![("term-2", "Term 2")]
+        );
+        assert_eq!(
+            format_semester_title(&semesters, "term-1", "{year}").as_deref(),
+            Some("Term 1")
+        );
+        let folders: std::collections::HashSet<String> = ["term-2", "term-1", "fresh-autumn"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            order_semester_folders(&semesters, &folders, &[]),
+            vec!["term-1", "term-2", "fresh-autumn"]
+        );
+    }
+
+    #[test]
+    fn test_format_semester_title_custom_template() {
+        let semesters = SemesterMapping::default();
+        assert_eq!(
+            format_semester_title(&semesters, "sophomore-spring", "{year} {season}").as_deref(),
+            Some("大二 春")
+        );
+        assert_eq!(
+            format_semester_title(
+                &semesters,
+                "fresh-autumn",
+                "Year {year_index} · {season_en}"
+            )
+            .as_deref(),
+            Some("Year 1 · Autumn")
+        );
+    }
+
+    #[test]
+    fn test_should_include_file_custom_rules() {
+        let rules = ExclusionRules {
+            extensions: vec![".zip".to_string()],
+            patterns: vec!["Thumbs.db".to_string()],
+            prefixes: vec!["drafts/".to_string()],
+            keep: vec!["README.md".to_string()],
+        };
+        assert!(!should_include_file("archive/old.zip", &rules));
+        assert!(!should_include_file("slides/Thumbs.db", &rules));
+        assert!(!should_include_file("drafts/notes.pdf", &rules));
+
+        // README.md is kept while the other built-ins still apply
+        assert!(should_include_file("docs/README.md", &rules));
+        assert!(!should_include_file("LICENSE", &rules));
+        assert!(!should_include_file("config.toml", &rules));
+        assert!(!should_include_file(".github/ci.yml", &rules));
+        assert!(should_include_file("notes.pdf", &rules));
+    }
 }
@@ -0,0 +1,113 @@
+//! Compare two generated docs trees file-by-file.
+//!
+//! Used to review what a data change does to the output before committing it:
+//! generate into a scratch directory, then diff it against the current docs.
+
+use crate::error::{FumaError, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Files that differ between two docs trees, as paths relative to the roots.
+#[derive(Debug, Default, PartialEq)]
+pub struct DocsDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+}
+
+impl DocsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Hash every file under `dir`, keyed by its path relative to `dir`
+fn hash_tree(dir: &Path) -> Result<BTreeMap<PathBuf, u64>> {
+    if !dir.is_dir() {
+        return Err(FumaError::MissingDirectory(dir.to_path_buf()));
+    }
+
+    let mut hashes = BTreeMap::new();
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let mut hasher = DefaultHasher::new();
+        fs::read(entry.path())?.hash(&mut hasher);
+        let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        hashes.insert(relative.to_path_buf(), hasher.finish());
+    }
+    Ok(hashes)
+}
+
+/// Compare `old_dir` against `new_dir` by content hash. Paths in each list
+/// are sorted.
+pub fn diff_docs(old_dir: &Path, new_dir: &Path) -> Result<DocsDiff> {
+    let old = hash_tree(old_dir)?;
+    let new = hash_tree(new_dir)?;
+    let mut diff = DocsDiff::default();
+
+    for (path, hash) in &new {
+        match old.get(path) {
+            None => diff.added.push(path.clone()),
+            Some(old_hash) if old_hash != hash => diff.changed.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .keys()
+        .filter(|path| !new.contains_key(*path))
+        .cloned()
+        .collect();
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_docs() {
+        let root = std::env::temp_dir().join("test_diff_docs");
+        let _ = fs::remove_dir_all(&root);
+        let old_dir = root.join("old");
+        let new_dir = root.join("new");
+        for dir in [&old_dir, &new_dir] {
+            fs::create_dir_all(dir.join("2023/CS")).unwrap();
+            fs::write(dir.join("2023/index.mdx"), "same").unwrap();
+        }
+        fs::write(old_dir.join("2023/CS/COMP1001.mdx"), "old body").unwrap();
+        fs::write(new_dir.join("2023/CS/COMP1001.mdx"), "new body").unwrap();
+        fs::write(old_dir.join("2023/CS/MATH1001.mdx"), "gone").unwrap();
+        fs::write(new_dir.join("2023/CS/PHYS1001.mdx"), "fresh").unwrap();
+
+        let diff = diff_docs(&old_dir, &new_dir).unwrap();
+        assert_eq!(
+            diff,
+            DocsDiff {
+                added: vec![PathBuf::from("2023/CS/PHYS1001.mdx")],
+                removed: vec![PathBuf::from("2023/CS/MATH1001.mdx")],
+                changed: vec![PathBuf::from("2023/CS/COMP1001.mdx")],
+            }
+        );
+        assert!(diff_docs(&old_dir, &old_dir).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_diff_docs_missing_dir() {
+        let missing = std::env::temp_dir().join("test_diff_docs_missing");
+        let _ = fs::remove_dir_all(&missing);
+        assert!(matches!(
+            diff_docs(&missing, &missing),
+            Err(FumaError::MissingDirectory(_))
+        ));
+    }
+}
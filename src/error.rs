@@ -12,8 +12,52 @@ pub enum FumaError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("Failed to parse plan {}: {source}", .path.display())]
+    PlanParse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Failed to parse config {}: {source}", .path.display())]
+    ConfigParse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Plan {plan_id} cannot extend {base}: {reason}")]
+    PlanExtends {
+        plan_id: String,
+        base: String,
+        reason: String,
+    },
+
     #[error("Missing required directory: {0}")]
     MissingDirectory(PathBuf),
+
+    #[error(
+        "Missing required inputs: {}",
+        .0.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    MissingInputs(Vec<PathBuf>),
+
+    #[error("Invalid frontmatter for {page}: {reason}")]
+    InvalidFrontmatter { page: String, reason: String },
+
+    /// A failed HTTP request; `status` is set when the server responded
+    #[error("HTTP error: {message}")]
+    Http {
+        status: Option<u16>,
+        message: String,
+    },
+}
+
+impl From<reqwest::Error> for FumaError {
+    fn from(e: reqwest::Error) -> Self {
+        FumaError::Http {
+            status: e.status().map(|s| s.as_u16()),
+            message: e.to_string(),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, FumaError>;
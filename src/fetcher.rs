@@ -3,23 +3,137 @@
 //! This module provides functionality to fetch README.md and worktree.json files
 //! from GitHub repositories, replacing the Python-based fetching logic.
 
+use crate::config::ReposLayout;
+use crate::constants::{DEFAULT_README_PATH, GITHUB_API_BASE, GITHUB_RAW_BASE, WORKTREE_BRANCH};
 use crate::error::{FumaError, Result};
 use base64::prelude::*;
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, ETAG, IF_NONE_MATCH, USER_AGENT,
+};
+use reqwest::StatusCode;
 use serde::Deserialize;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::fs;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 /// GitHub API response for file content
 #[derive(Debug, Deserialize)]
 struct GitHubContent {
+    /// Empty, with encoding `none`, for files over 1MB
+    #[serde(default)]
     content: String,
     encoding: String,
+    #[serde(default)]
+    size: u64,
+    download_url: Option<String>,
 }
 
+/// GitHub API response for repository metadata
+#[derive(Debug, Deserialize)]
+struct GitHubRepo {
+    default_branch: String,
+}
+
+/// A file downloaded from GitHub, with the ETag it was served with
+#[derive(Debug)]
+pub struct FetchedFile {
+    pub content: String,
+    pub etag: Option<String>,
+}
+
+/// Error for a response that isn't a success, keeping its status code
+fn status_error(response: &reqwest::Response, host: &str) -> FumaError {
+    FumaError::Http {
+        status: Some(response.status().as_u16()),
+        message: format!("{} returned status: {}", host, response.status()),
+    }
+}
+
+/// Decode base64 file content to UTF-8 text, ignoring the line wrapping and
+/// any other ASCII whitespace in it
+fn decode_base64_text(encoded: &str) -> Result<String> {
+    let compact: String = encoded
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let decoded = BASE64_STANDARD
+        .decode(compact)
+        .map_err(|e| FumaError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+
+    String::from_utf8(decoded)
+        .map_err(|e| FumaError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+}
+
+/// Sidecar file holding the ETag of a downloaded file: `{path}.etag`
+fn etag_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".etag");
+    path.with_file_name(name)
+}
+
+/// Limits in-flight requests per URL host, independent of the global limit.
+///
+/// One semaphore is created lazily for each host seen, so traffic to the API
+/// and to a raw-content fallback host is throttled separately.
+#[derive(Debug)]
+pub struct HostLimiter {
+    per_host: usize,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+    /// Create a limiter allowing `per_host` concurrent requests to each host
+    pub fn new(per_host: usize) -> Self {
+        Self {
+            per_host: per_host.max(1),
+            semaphores: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait for a request slot on the host of `url`.
+    ///
+    /// URLs without a parsable host share a single bucket.
+    pub async fn acquire(&self, url: &str) -> OwnedSemaphorePermit {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+
+        let semaphore = {
+            let mut semaphores = self.semaphores.lock().unwrap();
+            Arc::clone(
+                semaphores
+                    .entry(host)
+                    .or_insert_with(|| Arc::new(Semaphore::new(self.per_host))),
+            )
+        };
+
+        // The semaphore is never closed, so acquiring cannot fail
+        semaphore.acquire_owned().await.unwrap()
+    }
+}
+
+/// Attempts per request, including the first, unless overridden
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled for each further attempt
+const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
 /// GitHub API client for fetching repository data
+#[derive(Debug)]
 pub struct GitHubFetcher {
     client: reqwest::Client,
+    base_url: String,
+    raw_base: String,
+    use_raw: bool,
+    host_limiter: Option<HostLimiter>,
+    max_attempts: u32,
+    retry_base_delay: Duration,
 }
 
 impl GitHubFetcher {
@@ -42,10 +156,46 @@ impl GitHubFetcher {
 
         let client = reqwest::Client::builder()
             .default_headers(headers)
-            .build()
-            .map_err(|e| FumaError::Io(std::io::Error::other(e)))?;
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: GITHUB_API_BASE.to_string(),
+            raw_base: GITHUB_RAW_BASE.to_string(),
+            use_raw: false,
+            host_limiter: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_base_delay: DEFAULT_RETRY_BASE_DELAY,
+        })
+    }
+
+    /// Try each request up to `attempts` times (at least once) before giving
+    /// up on network errors, 5xx and 429 responses
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Read files from raw.githubusercontent.com first, which skips the
+    /// base64 round trip and doesn't count against the API rate limit.
+    /// Files the raw host can't serve (e.g. in private repos) still come
+    /// from the contents API.
+    pub fn with_raw_content(mut self, use_raw: bool) -> Self {
+        self.use_raw = use_raw;
+        self
+    }
 
-        Ok(Self { client })
+    /// Send API requests to `base_url` instead of the public GitHub API, e.g.
+    /// `https://github.example.com/api/v3` for GitHub Enterprise
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Cap concurrent requests to each individual host
+    pub fn with_max_concurrency_per_host(mut self, per_host: usize) -> Self {
+        self.host_limiter = Some(HostLimiter::new(per_host));
+        self
     }
 
     /// Fetch a file from GitHub repository
@@ -56,158 +206,578 @@ impl GitHubFetcher {
         path: &str,
         branch: Option<&str>,
     ) -> Result<String> {
-        let mut url = format!(
-            "https://api.github.com/repos/{}/{}/contents/{}",
-            org, repo, path
-        );
+        match self
+            .fetch_file_if_changed(org, repo, path, branch, None)
+            .await?
+        {
+            Some(file) => Ok(file.content),
+            None => Err(FumaError::Http {
+                status: Some(StatusCode::NOT_MODIFIED.as_u16()),
+                message: "GitHub API returned 304 for an unconditional request".to_string(),
+            }),
+        }
+    }
+
+    /// Fetch a file from GitHub repository unless it still matches `etag`.
+    ///
+    /// Returns `None` when GitHub answers 304 Not Modified.
+    pub async fn fetch_file_if_changed(
+        &self,
+        org: &str,
+        repo: &str,
+        path: &str,
+        branch: Option<&str>,
+        etag: Option<&str>,
+    ) -> Result<Option<FetchedFile>> {
+        if self.use_raw {
+            let url = format!(
+                "{}/{}/{}/{}/{}",
+                self.raw_base,
+                org,
+                repo,
+                branch.unwrap_or("HEAD"),
+                path
+            );
+            if let Ok(file) = self.fetch_raw(&url, etag).await {
+                return Ok(file);
+            }
+        }
+
+        let mut url = format!("{}/repos/{}/{}/contents/{}", self.base_url, org, repo, path);
 
         if let Some(ref_name) = branch {
             url.push_str(&format!("?ref={}", ref_name));
         }
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| FumaError::Io(std::io::Error::other(e)))?;
+        self.fetch_contents(&url, etag).await
+    }
 
-        if !response.status().is_success() {
-            return Err(FumaError::Io(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("GitHub API returned status: {}", response.status()),
-            )));
+    /// GET `url`, retrying transient failures with exponential backoff.
+    ///
+    /// Network errors, 5xx and 429 are retried; any other response, including
+    /// 404, is returned as is. The last response or error is returned once
+    /// the attempts run out. With an `etag` the request is conditional.
+    async fn get(&self, url: &str, etag: Option<&str>) -> Result<reqwest::Response> {
+        let _host_permit = match self.host_limiter {
+            Some(ref limiter) => Some(limiter.acquire(url).await),
+            None => None,
+        };
+
+        let mut attempt = 1;
+        loop {
+            let mut request = self.client.get(url);
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            let result = request.send().await;
+            let transient = match result {
+                Ok(ref response) => {
+                    response.status().is_server_error()
+                        || response.status() == StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(_) => true,
+            };
+            if !transient || attempt >= self.max_attempts {
+                return result.map_err(FumaError::from);
+            }
+
+            tokio::time::sleep(self.retry_delay(attempt)).await;
+            attempt += 1;
         }
+    }
 
-        let content: GitHubContent = response
-            .json()
-            .await
-            .map_err(|e| FumaError::Io(std::io::Error::other(e)))?;
+    /// Backoff before retry number `attempt`: the base delay doubled per
+    /// attempt, plus up to half of that again as jitter
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let delay = self.retry_base_delay * 2u32.pow(attempt.saturating_sub(1).min(16));
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let max_jitter = (delay.as_millis() / 2) as u64 + 1;
+        delay + Duration::from_millis(u64::from(nanos) % max_jitter)
+    }
 
-        // Decode base64 content
-        if content.encoding == "base64" {
-            let decoded = BASE64_STANDARD
-                .decode(content.content.replace('\n', ""))
-                .map_err(|e| {
-                    FumaError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
-                })?;
+    /// Download a raw file URL as text.
+    ///
+    /// Returns `None` when `etag` is given and the file is unchanged.
+    async fn fetch_raw(&self, url: &str, etag: Option<&str>) -> Result<Option<FetchedFile>> {
+        let response = self.get(url, etag).await?;
 
-            String::from_utf8(decoded)
-                .map_err(|e| FumaError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
-        } else {
-            Ok(content.content)
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(status_error(&response, "Raw content host"));
         }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let content = response.text().await?;
+
+        Ok(Some(FetchedFile { content, etag }))
     }
 
-    /// Fetch README.md for a repository
-    pub async fn fetch_readme(&self, org: &str, repo: &str) -> Result<String> {
-        self.fetch_file(org, repo, "README.md", None).await
+    /// Download a contents API URL and decode the file it describes.
+    ///
+    /// Returns `None` when `etag` is given and the file is unchanged.
+    async fn fetch_contents(&self, url: &str, etag: Option<&str>) -> Result<Option<FetchedFile>> {
+        let response = self.get(url, etag).await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(status_error(&response, "GitHub API"));
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let content: GitHubContent = response.json().await?;
+
+        // Files over 1MB come without content; download them directly instead
+        if content.content.is_empty() && content.size > 0 {
+            let Some(ref download_url) = content.download_url else {
+                return Err(FumaError::Http {
+                    status: None,
+                    message: format!("{} is too large for the contents API", url),
+                });
+            };
+            let file = self.fetch_raw(download_url, None).await?;
+            return Ok(file.map(|file| FetchedFile {
+                content: file.content,
+                etag,
+            }));
+        }
+
+        let text = if content.encoding == "base64" {
+            decode_base64_text(&content.content)?
+        } else {
+            content.content
+        };
+
+        Ok(Some(FetchedFile {
+            content: text,
+            etag,
+        }))
     }
 
     /// Fetch worktree.json from worktree branch
     pub async fn fetch_worktree_json(&self, org: &str, repo: &str) -> Result<String> {
-        self.fetch_file(org, repo, "worktree.json", Some("worktree"))
+        self.fetch_file(org, repo, "worktree.json", Some(WORKTREE_BRANCH))
             .await
     }
 
-    /// Fetch repository data and save to local files
-    pub async fn fetch_repo_data(&self, org: &str, repo: &str, repos_dir: &Path) -> Result<()> {
-        let mdx_path = repos_dir.join(format!("{}.mdx", repo));
-        let json_path = repos_dir.join(format!("{}.json", repo));
+    /// Look up the default branch of a repository, e.g. `main` or `master`
+    pub async fn fetch_default_branch(&self, org: &str, repo: &str) -> Result<String> {
+        let url = format!("{}/repos/{}/{}", self.base_url, org, repo);
+        let response = self.get(&url, None).await?;
 
-        // Fetch README if not exists
-        if !mdx_path.exists() {
-            match self.fetch_readme(org, repo).await {
-                Ok(content) => {
-                    fs::write(&mdx_path, content).await?;
+        if !response.status().is_success() {
+            return Err(status_error(&response, "GitHub API"));
+        }
+
+        let metadata: GitHubRepo = response.json().await?;
+        Ok(metadata.default_branch)
+    }
+}
+
+/// What happened to one file of a repo during a fetch
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileStatus {
+    /// Downloaded and written to `repos_dir`
+    Fetched,
+    /// Kept as is: already present locally, or unchanged upstream
+    Skipped,
+    /// Could not be fetched; holds the error message
+    Failed(String),
+}
+
+/// Fetch outcome of one repo
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoFetch {
+    pub repo: String,
+    pub readme: FileStatus,
+    pub worktree: FileStatus,
+}
+
+impl RepoFetch {
+    /// Whether neither file failed
+    pub fn is_ok(&self) -> bool {
+        !matches!(self.readme, FileStatus::Failed(_))
+            && !matches!(self.worktree, FileStatus::Failed(_))
+    }
+}
+
+/// Per-repo outcomes of [`fetch_all_repos`], in `repos_list` order
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    pub repos: Vec<RepoFetch>,
+}
+
+impl FetchReport {
+    /// Repos with at least one failed file
+    pub fn failed(&self) -> impl Iterator<Item = &RepoFetch> {
+        self.repos.iter().filter(|r| !r.is_ok())
+    }
+}
+
+/// Progress callback for [`fetch_all_repos`]: `(completed, total, repo)`
+pub type FetchProgress<'a> = dyn FnMut(usize, usize, &str) + 'a;
+
+/// Per-run options for [`fetch_all_repos`]
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Also fetch each repo's optional `tag.txt` into `{repo}.tag.txt`
+    pub fetch_tags: bool,
+    /// Layout of the files written into `repos_dir`
+    pub layout: ReposLayout,
+    /// README paths tried in order; `README.md` when empty
+    pub readme_paths: Vec<String>,
+    /// Branch to read the README from; the default branch when unset
+    pub readme_branch: Option<String>,
+    /// Re-download and overwrite files that already exist in `repos_dir`
+    pub force: bool,
+}
+
+/// Where repository README and worktree data are read from.
+///
+/// `GitHub` talks to the API; `LocalDir` reads `{dir}/{repo}/README.md` and
+/// `{dir}/{repo}/worktree.json`, for offline builds and hermetic tests.
+#[derive(Debug)]
+pub enum Source {
+    GitHub(GitHubFetcher),
+    LocalDir(PathBuf),
+}
+
+impl Source {
+    /// Fetch worktree.json for a repository
+    pub async fn fetch_worktree_json(&self, org: &str, repo: &str) -> Result<String> {
+        match self {
+            Source::GitHub(fetcher) => fetcher.fetch_worktree_json(org, repo).await,
+            Source::LocalDir(dir) => {
+                Ok(fs::read_to_string(dir.join(repo).join("worktree.json")).await?)
+            }
+        }
+    }
+
+    /// Fetch the optional tag.txt for a repository
+    pub async fn fetch_tag_txt(&self, org: &str, repo: &str) -> Result<String> {
+        match self {
+            Source::GitHub(fetcher) => fetcher.fetch_file(org, repo, "tag.txt", None).await,
+            Source::LocalDir(dir) => Ok(fs::read_to_string(dir.join(repo).join("tag.txt")).await?),
+        }
+    }
+
+    /// Human-readable description used in progress output
+    pub fn describe(&self) -> String {
+        match self {
+            Source::GitHub(_) => "GitHub".to_string(),
+            Source::LocalDir(dir) => dir.display().to_string(),
+        }
+    }
+
+    /// Look up the default branch of a repository; local sources have none
+    pub async fn fetch_default_branch(&self, org: &str, repo: &str) -> Result<Option<String>> {
+        match self {
+            Source::GitHub(fetcher) => fetcher.fetch_default_branch(org, repo).await.map(Some),
+            Source::LocalDir(_) => Ok(None),
+        }
+    }
+
+    /// Fetch repository data and save to local files.
+    ///
+    /// The default branch is recorded in `{repo}.branch` for download links.
+    /// It is looked up again whenever the README is revalidated by ETag, so a
+    /// renamed default branch is picked up without `--force-fetch`; a failed
+    /// lookup keeps the recorded one. worktree.json is read from the
+    /// `worktree` branch, falling back to the default branch.
+    pub async fn fetch_repo_data(
+        &self,
+        org: &str,
+        repo: &str,
+        repos_dir: &Path,
+        options: &FetchOptions,
+    ) -> Result<RepoFetch> {
+        let mdx_path = options.layout.repo_file(repos_dir, repo, "mdx");
+        let json_path = options.layout.repo_file(repos_dir, repo, "json");
+        if let Some(parent) = mdx_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let branch_path = options.layout.repo_file(repos_dir, repo, "branch");
+        let cached_branch = if branch_path.exists() {
+            Some(fs::read_to_string(&branch_path).await?.trim().to_string())
+        } else {
+            None
+        };
+        let refresh_branch =
+            options.force || cached_branch.is_none() || etag_path(&mdx_path).exists();
+        let default_branch = if !refresh_branch {
+            cached_branch
+        } else {
+            match self.fetch_default_branch(org, repo).await {
+                Ok(Some(branch)) => {
+                    if cached_branch.as_ref() != Some(&branch) {
+                        fs::write(&branch_path, &branch).await?;
+                    }
+                    Some(branch)
                 }
+                Ok(None) => cached_branch,
                 Err(e) => {
-                    eprintln!("Warning: Failed to fetch README for {}: {}", repo, e);
+                    eprintln!(
+                        "Warning: Failed to look up default branch of {}: {}",
+                        repo, e
+                    );
+                    cached_branch
                 }
             }
+        };
+
+        let default_readme = [DEFAULT_README_PATH.to_string()];
+        let readme_paths = if options.readme_paths.is_empty() {
+            &default_readme[..]
+        } else {
+            &options.readme_paths[..]
+        };
+        let readme = self
+            .sync_file(
+                org,
+                repo,
+                readme_paths,
+                &[options.readme_branch.as_deref()],
+                &mdx_path,
+                options.force,
+            )
+            .await?;
+        let worktree = self
+            .sync_file(
+                org,
+                repo,
+                &["worktree.json".to_string()],
+                &[Some(WORKTREE_BRANCH), default_branch.as_deref()],
+                &json_path,
+                options.force,
+            )
+            .await?;
+
+        // tag.txt is optional, so a missing file is not worth a warning
+        let tag_path = options.layout.repo_file(repos_dir, repo, "tag.txt");
+        if options.fetch_tags && (options.force || !tag_path.exists()) {
+            if let Ok(content) = self.fetch_tag_txt(org, repo).await {
+                fs::write(&tag_path, content).await?;
+            }
         }
 
-        // Fetch worktree.json if not exists
-        if !json_path.exists() {
-            match self.fetch_worktree_json(org, repo).await {
-                Ok(content) => {
-                    fs::write(&json_path, content).await?;
-                }
-                Err(e) => {
-                    eprintln!("Warning: Failed to fetch worktree.json for {}: {}", repo, e);
+        Ok(RepoFetch {
+            repo: repo.to_string(),
+            readme,
+            worktree,
+        })
+    }
+
+    /// Fetch the first of `paths` that can be read from `repo`, trying each
+    /// path in order on each of `branches` in order (`None` is the default
+    /// branch). Returns `None` when GitHub reports it unchanged since `etag`.
+    async fn fetch_first(
+        &self,
+        org: &str,
+        repo: &str,
+        paths: &[String],
+        branches: &[Option<&str>],
+        etag: Option<&str>,
+    ) -> Result<Option<FetchedFile>> {
+        let mut last_error = None;
+        for &branch in branches {
+            for path in paths {
+                let fetched = match self {
+                    Source::GitHub(fetcher) => {
+                        fetcher
+                            .fetch_file_if_changed(org, repo, path, branch, etag)
+                            .await
+                    }
+                    Source::LocalDir(dir) => fs::read_to_string(dir.join(repo).join(path))
+                        .await
+                        .map(|content| {
+                            Some(FetchedFile {
+                                content,
+                                etag: None,
+                            })
+                        })
+                        .map_err(FumaError::from),
+                };
+                match fetched {
+                    Ok(file) => return Ok(file),
+                    Err(e) => last_error = Some(e),
                 }
             }
         }
 
-        Ok(())
+        Err(last_error.unwrap_or_else(|| {
+            FumaError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "no paths to fetch",
+            ))
+        }))
+    }
+
+    /// Download the first available of `remotes` from `repo` into `local`,
+    /// warning on failure. Only errors writing `local` are returned as `Err`.
+    ///
+    /// An existing file is kept, except that a file fetched from GitHub with
+    /// an ETag sidecar (see [`etag_path`]) is re-requested conditionally and
+    /// replaced only when it changed upstream. With `force` it is always
+    /// downloaded again; it is still kept if that fails.
+    async fn sync_file(
+        &self,
+        org: &str,
+        repo: &str,
+        remotes: &[String],
+        branches: &[Option<&str>],
+        local: &Path,
+        force: bool,
+    ) -> Result<FileStatus> {
+        let etag_path = etag_path(local);
+        let etag = if local.exists() && !force {
+            match (self, fs::read_to_string(&etag_path).await) {
+                (Source::GitHub(_), Ok(etag)) => Some(etag.trim().to_string()),
+                _ => return Ok(FileStatus::Skipped),
+            }
+        } else {
+            None
+        };
+
+        match self
+            .fetch_first(org, repo, remotes, branches, etag.as_deref())
+            .await
+        {
+            Ok(Some(file)) => {
+                fs::write(local, file.content).await?;
+                match file.etag {
+                    Some(etag) => fs::write(&etag_path, etag).await?,
+                    None => {
+                        let _ = fs::remove_file(&etag_path).await;
+                    }
+                }
+                Ok(FileStatus::Fetched)
+            }
+            // Unchanged upstream
+            Ok(None) => Ok(FileStatus::Skipped),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to fetch {} for {}: {}",
+                    remotes.join(" / "),
+                    repo,
+                    e
+                );
+                Ok(FileStatus::Failed(e.to_string()))
+            }
+        }
     }
 }
 
-/// Fetch all repositories concurrently with semaphore limiting
+/// Fetch all repositories concurrently with semaphore limiting.
+///
+/// `concurrency` bounds the total number of repos in flight; per-host limits
+/// are configured on the GitHub fetcher itself. A repo whose task errors is
+/// reported with both files failed.
+///
+/// `progress` is called with `(completed, total, repo)` as each repo
+/// finishes, in completion order. Calls come from this task one at a time.
 pub async fn fetch_all_repos(
-    token: String,
+    source: Arc<Source>,
     org: &str,
     repos_list: &[String],
     repos_dir: &Path,
     concurrency: usize,
-) -> Result<()> {
-    use std::sync::Arc;
-    use tokio::sync::Semaphore;
-
-    println!("Fetching {} repositories from GitHub...", repos_list.len());
+    options: &FetchOptions,
+    mut progress: Option<&mut FetchProgress<'_>>,
+) -> Result<FetchReport> {
+    println!(
+        "Fetching {} repositories from {}...",
+        repos_list.len(),
+        source.describe()
+    );
 
     // Create repos directory if not exists
     if !repos_dir.exists() {
         fs::create_dir_all(repos_dir).await?;
     }
 
-    let fetcher = Arc::new(GitHubFetcher::new(token)?);
     let semaphore = Arc::new(Semaphore::new(concurrency));
 
     // Create tasks for all repos
     let tasks: Vec<_> = repos_list
         .iter()
         .map(|repo| {
-            let fetcher = Arc::clone(&fetcher);
+            let source = Arc::clone(&source);
             let semaphore = Arc::clone(&semaphore);
             let org = org.to_string();
             let repo = repo.clone();
             let repos_dir = repos_dir.to_path_buf();
+            let options = options.clone();
 
             tokio::spawn(async move {
                 let _permit = semaphore.acquire().await.unwrap();
-                fetcher.fetch_repo_data(&org, &repo, &repos_dir).await
+                source
+                    .fetch_repo_data(&org, &repo, &repos_dir, &options)
+                    .await
             })
         })
         .collect();
 
-    // Wait for all tasks to complete
-    let results = futures::future::join_all(tasks).await;
-
-    // Count successes and failures
-    let mut success_count = 0;
-    let mut error_count = 0;
+    // Wait for all tasks to complete, reporting each as it finishes
+    let mut pending: FuturesUnordered<_> = tasks
+        .into_iter()
+        .enumerate()
+        .map(|(i, task)| task.map(move |result| (i, result)))
+        .collect();
+    let mut results = Vec::with_capacity(repos_list.len());
+    while let Some((i, result)) = pending.next().await {
+        results.push((i, result));
+        if let Some(ref mut progress) = progress {
+            progress(results.len(), repos_list.len(), &repos_list[i]);
+        }
+    }
+    results.sort_by_key(|(i, _)| *i);
 
-    for result in results {
-        match result {
-            Ok(Ok(())) => success_count += 1,
+    let mut report = FetchReport::default();
+    for (repo, (_, result)) in repos_list.iter().zip(results) {
+        let message = match result {
+            Ok(Ok(fetched)) => {
+                report.repos.push(fetched);
+                continue;
+            }
             Ok(Err(e)) => {
-                error_count += 1;
                 eprintln!("Error: {}", e);
+                e.to_string()
             }
             Err(e) => {
-                error_count += 1;
                 eprintln!("Task error: {}", e);
+                e.to_string()
             }
-        }
+        };
+        report.repos.push(RepoFetch {
+            repo: repo.clone(),
+            readme: FileStatus::Failed(message.clone()),
+            worktree: FileStatus::Failed(message),
+        });
     }
 
+    let error_count = report.failed().count();
     println!(
         "Fetch complete: {} succeeded, {} failed",
-        success_count, error_count
+        report.repos.len() - error_count,
+        error_count
     );
 
-    Ok(())
+    Ok(report)
 }
 
 /// Resolve GitHub token from environment variables
@@ -241,3 +811,685 @@ pub fn resolve_github_token() -> Option<String> {
             }
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one canned HTTP response per connection, in order, repeating the
+    /// last one. Returns the base URL and the request lines served so far.
+    async fn serve(responses: Vec<String>) -> (String, Arc<Mutex<Vec<String>>>) {
+        serve_with(move |_, i| responses[i.min(responses.len() - 1)].clone()).await
+    }
+
+    /// Serve the response `respond` builds from each request line and its
+    /// index. Returns the base URL and the request lines served so far.
+    async fn serve_with<F>(respond: F) -> (String, Arc<Mutex<Vec<String>>>)
+    where
+        F: Fn(&str, usize) -> String + Send + 'static,
+    {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let served = Arc::clone(&hits);
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&request);
+                let request_line = head.lines().next().unwrap_or_default().to_string();
+                let i = {
+                    let mut served = served.lock().unwrap();
+                    served.push(request_line.clone());
+                    served.len() - 1
+                };
+                let response = respond(&request_line, i);
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        (url, hits)
+    }
+
+    /// A minimal HTTP/1.1 response with the given status line and body
+    fn http_response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    /// A contents API body for a UTF-8 file
+    fn contents_body(text: &str) -> String {
+        format!(
+            r#"{{"content":"{}","encoding":"base64"}}"#,
+            BASE64_STANDARD.encode(text)
+        )
+    }
+
+    fn test_fetcher() -> GitHubFetcher {
+        let mut fetcher = GitHubFetcher::new("test-token".to_string()).unwrap();
+        fetcher.retry_base_delay = Duration::from_millis(1);
+        fetcher
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retries_transient_failures() {
+        let (url, hits) = serve(vec![
+            http_response("503 Service Unavailable", ""),
+            http_response("429 Too Many Requests", ""),
+            http_response("200 OK", &contents_body("# COMP1001\n")),
+        ])
+        .await;
+
+        let file = test_fetcher().fetch_contents(&url, None).await.unwrap();
+        assert_eq!(file.unwrap().content, "# COMP1001\n");
+        assert_eq!(hits.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_gives_up_after_max_attempts() {
+        let (url, hits) = serve(vec![http_response("502 Bad Gateway", "")]).await;
+
+        let fetcher = test_fetcher().with_max_attempts(2);
+        assert!(fetcher.fetch_contents(&url, None).await.is_err());
+        assert_eq!(hits.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_errors_carry_http_status() {
+        let (url, _) = serve(vec![http_response("404 Not Found", "")]).await;
+        let err = test_fetcher().fetch_contents(&url, None).await.unwrap_err();
+        assert!(matches!(
+            err,
+            FumaError::Http {
+                status: Some(404),
+                ..
+            }
+        ));
+
+        let (url, _) = serve(vec![http_response("500 Internal Server Error", "")]).await;
+        let err = test_fetcher()
+            .with_max_attempts(1)
+            .fetch_contents(&url, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FumaError::Http {
+                status: Some(500),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_base64_text_ignores_whitespace() {
+        let encoded = BASE64_STANDARD.encode("# 程序设计\n\n课程简介\n");
+        let (head, tail) = encoded.split_at(8);
+        let wrapped = format!(" {}\r\n{} \t\r\n", head, tail);
+        assert_eq!(
+            decode_base64_text(&wrapped).unwrap(),
+            "# 程序设计\n\n课程简介\n"
+        );
+        assert!(decode_base64_text("not base64!").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_large_file_via_download_url() {
+        let large = format!("{{\"data\":\"{}\"}}", "x".repeat(2 * 1024 * 1024));
+        let (raw_url, raw_hits) = serve(vec![http_response("200 OK", &large)]).await;
+        let body = format!(
+            r#"{{"content":"","encoding":"none","size":{},"download_url":"{}/worktree.json"}}"#,
+            large.len(),
+            raw_url
+        );
+        let (url, _) = serve(vec![http_response("200 OK", &body)]).await;
+
+        let file = test_fetcher()
+            .fetch_contents(&url, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.content, large);
+        assert_eq!(
+            *raw_hits.lock().unwrap(),
+            vec!["GET /worktree.json HTTP/1.1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_does_not_retry_not_found() {
+        let (url, hits) = serve(vec![http_response("404 Not Found", "")]).await;
+
+        assert!(test_fetcher().fetch_contents(&url, None).await.is_err());
+        assert_eq!(hits.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_uses_configured_base_url() {
+        let (url, hits) = serve(vec![http_response("200 OK", &contents_body("{}"))]).await;
+        let fetcher = test_fetcher().with_base_url(&format!("{}/api/v3/", url));
+
+        fetcher
+            .fetch_file(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                "worktree.json",
+                Some("worktree"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            *hits.lock().unwrap(),
+            vec!["GET /api/v3/repos/HITSZ-OpenAuto/COMP1001/contents/worktree.json?ref=worktree HTTP/1.1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_raw_content_skips_api() {
+        let (raw_url, raw_hits) = serve(vec![http_response("200 OK", "# raw\n")]).await;
+        let (api_url, api_hits) = serve(vec![http_response("500 Internal Server Error", "")]).await;
+        let mut fetcher = test_fetcher().with_raw_content(true);
+        fetcher.raw_base = raw_url;
+        fetcher.base_url = api_url;
+
+        let content = fetcher
+            .fetch_file("HITSZ-OpenAuto", "COMP1001", "README.md", None)
+            .await
+            .unwrap();
+        assert_eq!(content, "# raw\n");
+        assert_eq!(raw_hits.lock().unwrap().len(), 1);
+        assert_eq!(api_hits.lock().unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_raw_content_falls_back_to_api() {
+        // A private repo is invisible to the raw host
+        let (raw_url, raw_hits) = serve(vec![http_response("404 Not Found", "")]).await;
+        let (api_url, api_hits) =
+            serve(vec![http_response("200 OK", &contents_body("# private\n"))]).await;
+        let mut fetcher = test_fetcher().with_raw_content(true);
+        fetcher.raw_base = raw_url;
+        fetcher.base_url = api_url;
+
+        let content = fetcher
+            .fetch_file("HITSZ-OpenAuto", "COMP1001", "README.md", None)
+            .await
+            .unwrap();
+        assert_eq!(content, "# private\n");
+        assert_eq!(raw_hits.lock().unwrap().len(), 1);
+        assert_eq!(api_hits.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_readme_falls_back_to_next_candidate() {
+        let (url, hits) = serve(vec![
+            http_response("404 Not Found", ""),
+            http_response("200 OK", &contents_body("# lowercase\n")),
+        ])
+        .await;
+        let mut fetcher = test_fetcher();
+        fetcher.base_url = url;
+        let source = Source::GitHub(fetcher);
+
+        let paths = ["README.md".to_string(), "readme.md".to_string()];
+        let file = source
+            .fetch_first("HITSZ-OpenAuto", "COMP1001", &paths, &[Some("docs")], None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.content, "# lowercase\n");
+        assert_eq!(hits.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_repo_data_uses_readme_candidates() {
+        let root = std::env::temp_dir().join("test_fetch_readme_candidates");
+        let _ = std::fs::remove_dir_all(&root);
+        let source_dir = root.join("source");
+        let repos_dir = root.join("repos");
+        std::fs::create_dir_all(source_dir.join("COMP1001")).unwrap();
+        std::fs::write(source_dir.join("COMP1001/readme.md"), "# lowercase\n").unwrap();
+
+        let options = FetchOptions {
+            readme_paths: vec!["README.md".to_string(), "readme.md".to_string()],
+            ..Default::default()
+        };
+        Source::LocalDir(source_dir)
+            .fetch_repo_data("HITSZ-OpenAuto", "COMP1001", &repos_dir, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.mdx")).unwrap(),
+            "# lowercase\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_repo_data_with_master_default_branch() {
+        let root = std::env::temp_dir().join("test_fetch_default_branch");
+        let _ = std::fs::remove_dir_all(&root);
+        let repos_dir = root.join("repos");
+
+        let (url, hits) = serve(vec![
+            http_response("200 OK", r#"{"default_branch":"master"}"#),
+            http_response("200 OK", &contents_body("# COMP1001\n")),
+            http_response("404 Not Found", ""),
+            http_response("200 OK", &contents_body("{}")),
+        ])
+        .await;
+        let mut fetcher = test_fetcher();
+        fetcher.base_url = url;
+
+        Source::GitHub(fetcher)
+            .fetch_repo_data(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                &repos_dir,
+                &FetchOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.branch")).unwrap(),
+            "master"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.json")).unwrap(),
+            "{}"
+        );
+        // worktree.json falls back from the worktree branch to master
+        let requests = hits.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[2].contains("worktree.json?ref=worktree "));
+        assert!(requests[3].contains("worktree.json?ref=master "));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_repo_data_refreshes_cached_branch() {
+        let root = std::env::temp_dir().join("test_fetch_refresh_branch");
+        let _ = std::fs::remove_dir_all(&root);
+        let repos_dir = root.join("repos");
+        std::fs::create_dir_all(&repos_dir).unwrap();
+        // The README is revalidated by ETag; worktree.json has no ETag
+        std::fs::write(repos_dir.join("COMP1001.mdx"), "# COMP1001\n").unwrap();
+        std::fs::write(repos_dir.join("COMP1001.mdx.etag"), "\"abc\"").unwrap();
+        std::fs::write(repos_dir.join("COMP1001.json"), "{}").unwrap();
+        std::fs::write(repos_dir.join("COMP1001.branch"), "master").unwrap();
+
+        let (url, hits) = serve(vec![
+            http_response("200 OK", r#"{"default_branch":"main"}"#),
+            http_response("304 Not Modified", ""),
+        ])
+        .await;
+        let mut fetcher = test_fetcher();
+        fetcher.base_url = url;
+
+        let fetch = Source::GitHub(fetcher)
+            .fetch_repo_data(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                &repos_dir,
+                &FetchOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(fetch.readme, FileStatus::Skipped);
+        assert_eq!(hits.lock().unwrap().len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.branch")).unwrap(),
+            "main"
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_repos_reports_each_repo() {
+        let root = std::env::temp_dir().join("test_fetch_report");
+        let _ = std::fs::remove_dir_all(&root);
+        let repos_dir = root.join("repos");
+        std::fs::create_dir_all(&repos_dir).unwrap();
+        // Cached without ETags, so neither file is requested again
+        std::fs::write(repos_dir.join("CACHED.mdx"), "# CACHED\n").unwrap();
+        std::fs::write(repos_dir.join("CACHED.json"), "{}").unwrap();
+        std::fs::write(repos_dir.join("CACHED.branch"), "main").unwrap();
+
+        // FOUND has both files; MISSING has a README but no worktree data
+        let (url, _) = serve_with(|request, _| {
+            if request.contains("/FOUND/contents/") {
+                http_response("200 OK", &contents_body("{}"))
+            } else if request.contains("/MISSING/contents/README.md") {
+                http_response("200 OK", &contents_body("# MISSING\n"))
+            } else if request.contains("/FOUND ") || request.contains("/MISSING ") {
+                http_response("200 OK", r#"{"default_branch":"main"}"#)
+            } else {
+                http_response("404 Not Found", "")
+            }
+        })
+        .await;
+        let mut fetcher = test_fetcher();
+        fetcher.base_url = url;
+
+        let repos = ["FOUND", "MISSING", "CACHED"].map(str::to_string);
+        let report = fetch_all_repos(
+            Arc::new(Source::GitHub(fetcher)),
+            "HITSZ-OpenAuto",
+            &repos,
+            &repos_dir,
+            2,
+            &FetchOptions::default(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let statuses: Vec<_> = report
+            .repos
+            .iter()
+            .map(|r| (r.repo.as_str(), &r.readme, &r.worktree))
+            .collect();
+        assert_eq!(
+            statuses[0],
+            ("FOUND", &FileStatus::Fetched, &FileStatus::Fetched)
+        );
+        assert_eq!(statuses[1].0, "MISSING");
+        assert_eq!(statuses[1].1, &FileStatus::Fetched);
+        assert!(matches!(statuses[1].2, FileStatus::Failed(_)));
+        assert_eq!(
+            statuses[2],
+            ("CACHED", &FileStatus::Skipped, &FileStatus::Skipped)
+        );
+
+        let failed: Vec<_> = report.failed().map(|r| r.repo.as_str()).collect();
+        assert_eq!(failed, vec!["MISSING"]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_sync_file_stores_etag_and_keeps_unmodified_file() {
+        let root = std::env::temp_dir().join("test_fetch_etag");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let local = root.join("COMP1001.mdx");
+
+        let fresh = format!(
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {0}\r\nConnection: close\r\n\r\n{1}",
+            contents_body("# v1\n").len(),
+            contents_body("# v1\n")
+        );
+        let (url, hits) = serve(vec![fresh, http_response("304 Not Modified", "")]).await;
+        let mut fetcher = test_fetcher();
+        fetcher.base_url = url;
+        let source = Source::GitHub(fetcher);
+        let readme = ["README.md".to_string()];
+
+        source
+            .sync_file(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                &readme,
+                &[None],
+                &local,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&local).unwrap(), "# v1\n");
+        assert_eq!(
+            std::fs::read_to_string(root.join("COMP1001.mdx.etag")).unwrap(),
+            "\"v1\""
+        );
+
+        // A 304 on the conditional request leaves the local copy alone
+        std::fs::write(&local, "# edited\n").unwrap();
+        source
+            .sync_file(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                &readme,
+                &[None],
+                &local,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits.lock().unwrap().len(), 2);
+        assert_eq!(std::fs::read_to_string(&local).unwrap(), "# edited\n");
+
+        // Without a sidecar an existing file is not requested at all
+        std::fs::remove_file(root.join("COMP1001.mdx.etag")).unwrap();
+        source
+            .sync_file(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                &readme,
+                &[None],
+                &local,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits.lock().unwrap().len(), 2);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_host_limiter_caps_each_host_independently() {
+        let limiter = Arc::new(HostLimiter::new(2));
+        let hosts = ["https://api.github.com/a", "https://raw.example.com/b"];
+        let in_flight: Arc<Vec<AtomicUsize>> =
+            Arc::new(vec![AtomicUsize::new(0), AtomicUsize::new(0)]);
+        let peak: Arc<Vec<AtomicUsize>> = Arc::new(vec![AtomicUsize::new(0), AtomicUsize::new(0)]);
+
+        let tasks: Vec<_> = (0..12)
+            .map(|i| {
+                let limiter = Arc::clone(&limiter);
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                let host = i % 2;
+                let url = hosts[host];
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire(url).await;
+                    let now = in_flight[host].fetch_add(1, Ordering::SeqCst) + 1;
+                    peak[host].fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    in_flight[host].fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+
+        for task in futures::future::join_all(tasks).await {
+            task.unwrap();
+        }
+
+        // Each host reaches its own cap without borrowing from the other
+        assert_eq!(peak[0].load(Ordering::SeqCst), 2);
+        assert_eq!(peak[1].load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_repos_from_local_dir() {
+        let root = std::env::temp_dir().join("test_fetch_local_dir");
+        let _ = std::fs::remove_dir_all(&root);
+        let source_dir = root.join("source");
+        let repos_dir = root.join("repos");
+        std::fs::create_dir_all(source_dir.join("COMP1001")).unwrap();
+        std::fs::write(source_dir.join("COMP1001/README.md"), "# COMP1001\n").unwrap();
+        std::fs::write(source_dir.join("COMP1001/worktree.json"), "{}").unwrap();
+
+        fetch_all_repos(
+            Arc::new(Source::LocalDir(source_dir)),
+            "HITSZ-OpenAuto",
+            &["COMP1001".to_string()],
+            &repos_dir,
+            4,
+            &FetchOptions::default(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.mdx")).unwrap(),
+            "# COMP1001\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.json")).unwrap(),
+            "{}"
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_repos_reports_progress() {
+        let root = std::env::temp_dir().join("test_fetch_progress");
+        let _ = std::fs::remove_dir_all(&root);
+        let source_dir = root.join("source");
+        let repos: Vec<String> = (1..=5).map(|i| format!("COMP100{}", i)).collect();
+        for repo in &repos {
+            std::fs::create_dir_all(source_dir.join(repo)).unwrap();
+            std::fs::write(source_dir.join(repo).join("README.md"), "# README\n").unwrap();
+        }
+
+        let mut calls = Vec::new();
+        let mut record = |done: usize, total: usize, repo: &str| {
+            calls.push((done, total, repo.to_string()));
+        };
+        fetch_all_repos(
+            Arc::new(Source::LocalDir(source_dir)),
+            "HITSZ-OpenAuto",
+            &repos,
+            &root.join("repos"),
+            2,
+            &FetchOptions::default(),
+            Some(&mut record),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(calls.len(), repos.len());
+        let completed: Vec<usize> = calls.iter().map(|(done, _, _)| *done).collect();
+        assert_eq!(completed, vec![1, 2, 3, 4, 5]);
+        assert!(calls.iter().all(|(_, total, _)| *total == 5));
+        let mut reported: Vec<String> = calls.into_iter().map(|(_, _, repo)| repo).collect();
+        reported.sort();
+        assert_eq!(reported, repos);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_repo_data_force_overwrites_existing_files() {
+        let root = std::env::temp_dir().join("test_fetch_force");
+        let _ = std::fs::remove_dir_all(&root);
+        let source_dir = root.join("source");
+        let repos_dir = root.join("repos");
+        std::fs::create_dir_all(source_dir.join("COMP1001")).unwrap();
+        std::fs::create_dir_all(&repos_dir).unwrap();
+        std::fs::write(source_dir.join("COMP1001/README.md"), "# new\n").unwrap();
+        std::fs::write(source_dir.join("COMP1001/worktree.json"), "{\"new\":{}}").unwrap();
+        std::fs::write(repos_dir.join("COMP1001.mdx"), "# stale\n").unwrap();
+        std::fs::write(repos_dir.join("COMP1001.json"), "{}").unwrap();
+        let source = Source::LocalDir(source_dir);
+
+        let fetched = source
+            .fetch_repo_data(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                &repos_dir,
+                &FetchOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(fetched.readme, FileStatus::Skipped);
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.mdx")).unwrap(),
+            "# stale\n"
+        );
+
+        let options = FetchOptions {
+            force: true,
+            ..Default::default()
+        };
+        let fetched = source
+            .fetch_repo_data("HITSZ-OpenAuto", "COMP1001", &repos_dir, &options)
+            .await
+            .unwrap();
+        assert_eq!(fetched.readme, FileStatus::Fetched);
+        assert_eq!(fetched.worktree, FileStatus::Fetched);
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.mdx")).unwrap(),
+            "# new\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.json")).unwrap(),
+            "{\"new\":{}}"
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag_txt_when_enabled() {
+        let root = std::env::temp_dir().join("test_fetch_tag_txt");
+        let _ = std::fs::remove_dir_all(&root);
+        let source_dir = root.join("source");
+        let repos_dir = root.join("repos");
+        std::fs::create_dir_all(source_dir.join("COMP1001")).unwrap();
+        std::fs::create_dir_all(&repos_dir).unwrap();
+        std::fs::write(source_dir.join("COMP1001/tag.txt"), "编程\n入门\n").unwrap();
+        let source = Source::LocalDir(source_dir);
+
+        source
+            .fetch_repo_data(
+                "HITSZ-OpenAuto",
+                "COMP1001",
+                &repos_dir,
+                &FetchOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!repos_dir.join("COMP1001.tag.txt").exists());
+
+        let options = FetchOptions {
+            fetch_tags: true,
+            ..Default::default()
+        };
+        source
+            .fetch_repo_data("HITSZ-OpenAuto", "COMP1001", &repos_dir, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repos_dir.join("COMP1001.tag.txt")).unwrap(),
+            "编程\n入门\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,29 +1,221 @@
+use crate::config::FormatConfig;
+use crate::constants::VOID_ELEMENTS;
+use crate::report::GenerationReport;
+use rayon::prelude::*;
 use regex::Regex;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-/// Format a single MDX file with all transformations
-pub fn format_mdx_file(content: &str) -> String {
+/// Split a leading `---` ... `---` YAML frontmatter block off `content`.
+///
+/// Returns the block, delimiters and trailing newline included, and the rest.
+/// A `---` anywhere but the first line is a thematic break, not frontmatter.
+pub fn strip_frontmatter(content: &str) -> (Option<String>, String) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content.to_string());
+    };
+
+    let mut offset = content.len() - rest.len();
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return (
+                Some(content[..offset].to_string()),
+                content[offset..].to_string(),
+            );
+        }
+    }
+    (None, content.to_string())
+}
+
+/// Whether the leading frontmatter of `content` sets `fuma_format: false`,
+/// asking the formatter to leave the file alone
+fn opts_out_of_formatting(content: &str) -> bool {
+    let (Some(frontmatter), _) = strip_frontmatter(content) else {
+        return false;
+    };
+    let yaml = frontmatter
+        .trim_end()
+        .trim_start_matches("---")
+        .trim_end_matches("---");
+    serde_yaml::from_str::<serde_yaml::Value>(yaml)
+        .ok()
+        .and_then(|v| v.get("fuma_format")?.as_bool())
+        == Some(false)
+}
+
+/// Format a single MDX file with all transformations. A leading frontmatter
+/// block is kept as is and only the body is formatted.
+///
+/// Line endings are normalized to `\n` first; with `preserve_crlf` a file
+/// that used `\r\n` gets them back at the end.
+pub fn format_mdx_file(content: &str, config: &FormatConfig) -> String {
+    let uses_crlf = content.contains("\r\n");
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+
+    let (frontmatter, body) = strip_frontmatter(&content);
+    let formatted = format_mdx_body(&body, config);
+    let formatted = match frontmatter {
+        Some(frontmatter) => format!("{}{}", frontmatter, formatted),
+        None => formatted,
+    };
+
+    if uses_crlf && config.preserve_crlf {
+        formatted.replace('\n', "\r\n")
+    } else {
+        formatted
+    }
+}
+
+/// Apply all transformations to an MDX body without frontmatter
+fn format_mdx_body(content: &str, config: &FormatConfig) -> String {
     let mut result = content.to_string();
 
     // Apply all transformations in order
     result = remove_html_comments(&result);
-    result = remove_shield_badges(&result);
+    result = normalize_fence_languages(&result, &config.fence_language_aliases);
+    result = remove_shield_badges(&result, &config.badge_hosts, &config.badge_alt_denylist);
     result = convert_bare_urls_to_links(&result);
-    result = fix_self_closing_tags(&result);
+    result = fix_self_closing_tags(&result, &config.extra_void_elements);
     result = fix_malformed_html(&result);
+    result = convert_font_tags(&result);
     result = convert_style_to_jsx(&result);
+    result = convert_github_alerts(&result);
     result = convert_hugo_callout_shortcodes(&result);
+    result = convert_hugo_notice_shortcodes(&result);
+    result = convert_html_details_to_accordion(&result);
     result = convert_hugo_details_to_accordion(&result);
+    result = convert_bracket_math(&result);
     result = convert_math_blocks(&result);
     result = convert_inline_math(&result);
 
-    // Clean up multiple consecutive blank lines
-    let re = Regex::new(r"\n{3,}").unwrap();
-    result = re.replace_all(&result, "\n\n").to_string();
+    cleanup_blank_lines(&result, config.max_blank_lines, config.trim_blank_lines)
+}
+
+/// Collapse runs of more than `max_blank` blank lines, optionally trimming
+/// blank lines at the start and end of the document
+fn cleanup_blank_lines(content: &str, max_blank: usize, trim: bool) -> String {
+    let re = Regex::new(&format!(r"\n{{{},}}", max_blank + 2)).unwrap();
+    let result = re.replace_all(content, "\n".repeat(max_blank + 1).as_str());
 
-    result
+    if !trim {
+        return result.into_owned();
+    }
+    let trimmed = result.trim_start_matches('\n').trim_end_matches('\n');
+    if trimmed.is_empty() {
+        String::new()
+    } else if result.ends_with('\n') {
+        // Keep a single final newline
+        format!("{}\n", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Marker character and length of a code fence line: three or more
+/// backticks or tildes after optional indentation
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start();
+    let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = trimmed.chars().take_while(|&c| c == marker).count();
+    (len >= 3).then_some((marker, len))
+}
+
+/// Whether `line` closes a block opened by the fence `open`: only a bare
+/// fence of the same kind and at least the same length does
+fn closes_fence(line: &str, open: (char, usize)) -> bool {
+    let trimmed = line.trim();
+    fence_marker(trimmed).is_some_and(|(marker, len)| {
+        marker == open.0 && len >= open.1 && trimmed.chars().all(|c| c == marker)
+    })
+}
+
+/// Whether `line` is a fence or inside a fenced code block, given the fence
+/// that opened the current block (if any) in `open`, which is updated
+fn in_fence(line: &str, open: &mut Option<(char, usize)>) -> bool {
+    match *open {
+        Some(fence) => {
+            if closes_fence(line, fence) {
+                *open = None;
+            }
+            true
+        }
+        None => {
+            *open = fence_marker(line);
+            open.is_some()
+        }
+    }
+}
+
+/// Byte ranges of the fenced code blocks in `content`, from the opening
+/// fence to the end of the closing fence (without its line break). An
+/// unclosed block runs to the end of the content.
+fn fenced_blocks(content: &str) -> Vec<std::ops::Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut open: Option<((char, usize), usize)> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches('\n');
+        match open {
+            Some((fence, start)) if closes_fence(text, fence) => {
+                blocks.push(start..offset + text.len());
+                open = None;
+            }
+            Some(_) => {}
+            None => open = fence_marker(text).map(|fence| (fence, offset)),
+        }
+        offset += line.len();
+    }
+    if let Some((_, start)) = open {
+        blocks.push(start..content.len());
+    }
+    blocks
+}
+
+/// Rename code fence languages through `aliases` (e.g. `py` → `python`).
+///
+/// Matching is case-insensitive and only the first word of the info string
+/// changes; unknown languages are left as written.
+fn normalize_fence_languages(content: &str, aliases: &HashMap<String, String>) -> String {
+    if aliases.is_empty() {
+        return content.to_string();
+    }
+    let aliases: HashMap<String, &str> = aliases
+        .iter()
+        .map(|(alias, lang)| (alias.to_lowercase(), lang.as_str()))
+        .collect();
+    let fence_re = Regex::new(r"^(\s*(?:```+|~~~+)[ \t]*)([^\s`]+)(.*)$").unwrap();
+    // The fence that opened the current block
+    let mut open_fence: Option<(char, usize)> = None;
+
+    content
+        .split('\n')
+        .map(|line| {
+            if let Some(open) = open_fence {
+                if closes_fence(line, open) {
+                    open_fence = None;
+                }
+                return line.to_string();
+            }
+            open_fence = fence_marker(line);
+            if open_fence.is_none() {
+                return line.to_string();
+            }
+            match fence_re.captures(line) {
+                Some(caps) => match aliases.get(&caps[2].to_lowercase()) {
+                    Some(lang) => format!("{}{}{}", &caps[1], lang, &caps[3]),
+                    None => line.to_string(),
+                },
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// Remove HTML comments from content
@@ -38,28 +230,84 @@ fn convert_bare_urls_to_links(content: &str) -> String {
     re.replace_all(content, "[$1]($1)").to_string()
 }
 
-/// Remove shield.io badges (markdown image syntax)
-fn remove_shield_badges(content: &str) -> String {
+/// Remove badges (markdown images served from one of `hosts`, e.g. shields.io).
+///
+/// With an empty `alt_denylist` every line mentioning a badge host is dropped.
+/// Otherwise only badges whose alt text is in the denylist are removed
+/// (together with a wrapping link), and lines left empty are dropped.
+fn remove_shield_badges(content: &str, hosts: &[String], alt_denylist: &[String]) -> String {
+    let is_badge_url = |text: &str| {
+        hosts
+            .iter()
+            .any(|host| !host.trim().is_empty() && text.contains(host.trim()))
+    };
+    if alt_denylist.is_empty() {
+        return content
+            .split('\n')
+            .filter(|&line| !is_badge_url(line))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    let badge_re = Regex::new(r"(\[)?!\[([^\]]*)\]\((https?://[^)]*)\)(\]\([^)]*\))?").unwrap();
+    let is_denied = |alt: &str| {
+        alt_denylist
+            .iter()
+            .any(|label| label.trim().eq_ignore_ascii_case(alt.trim()))
+    };
+
     content
         .split('\n')
-        .filter(|&line| !line.contains("https://img.shields.io"))
+        .filter_map(|line| {
+            let mut removed = false;
+            let kept = badge_re.replace_all(line, |caps: &regex::Captures| {
+                if !is_badge_url(&caps[3]) || !is_denied(&caps[2]) {
+                    return caps[0].to_string();
+                }
+                removed = true;
+                // A leading `[` that doesn't open a link around the badge is kept
+                match (caps.get(1), caps.get(4)) {
+                    (Some(_), None) => "[".to_string(),
+                    _ => String::new(),
+                }
+            });
+            if removed && kept.trim().is_empty() {
+                None
+            } else {
+                Some(kept.into_owned())
+            }
+        })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
-/// Convert HTML tags to self-closing format for MDX compatibility
-fn fix_self_closing_tags(content: &str) -> String {
-    let mut result = content.to_string();
-
-    // Convert <br> to <br />
-    let re_br = Regex::new(r"<br\s*>").unwrap();
-    result = re_br.replace_all(&result, "<br />").to_string();
-
-    // Convert <hr> to <hr />
-    let re_hr = Regex::new(r"<hr\s*>").unwrap();
-    result = re_hr.replace_all(&result, "<hr />").to_string();
-
-    result
+/// Convert void HTML tags to self-closing format for MDX compatibility.
+///
+/// Handles the built-in `VOID_ELEMENTS` plus any `extra` tag names, e.g.
+/// `<img src="x">` becomes `<img src="x" />`. Tags that are already
+/// self-closed keep a single ` />`. Names match case-sensitively, so JSX
+/// components such as `<Link>` or `<Source>` are left alone, and code is
+/// skipped.
+fn fix_self_closing_tags(content: &str, extra: &[String]) -> String {
+    let names: Vec<String> = VOID_ELEMENTS
+        .iter()
+        .map(|name| regex::escape(name))
+        .chain(extra.iter().map(|name| regex::escape(name.trim())))
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    // Attribute values may be quoted and contain `>` or `/`
+    let re = Regex::new(&format!(
+        r#"<({})((?:\s(?:[^<>"']|"[^"]*"|'[^']*')*?)?)\s*/?>"#,
+        names.join("|")
+    ))
+    .unwrap();
+
+    let (protected, code) = protect_code(content);
+    let result = re.replace_all(&protected, |caps: &regex::Captures| {
+        format!("<{}{} />", &caps[1], caps[2].trim_end())
+    });
+    restore_code(&result, &code)
 }
 
 /// Fix common malformed HTML patterns
@@ -97,12 +345,64 @@ fn css_property_to_camel_case(prop: &str) -> String {
     result
 }
 
-/// Convert HTML style attributes to JSX format
+/// Rewrite legacy `<font>` tags, which MDX rejects, as `<span>` with an
+/// equivalent `style` attribute (turned into JSX by [`convert_style_to_jsx`]).
+/// `color`, `face` and absolute `size` values 1–7 are kept; other attributes
+/// are dropped. Code is left untouched.
+fn convert_font_tags(content: &str) -> String {
+    const FONT_SIZES: [&str; 7] = [
+        "x-small",
+        "small",
+        "medium",
+        "large",
+        "x-large",
+        "xx-large",
+        "xxx-large",
+    ];
+    let (protected, code) = protect_code(content);
+    let open_re = Regex::new(r"(?i)<font\b([^>]*)>").unwrap();
+    let attr_re = Regex::new(r#"(\w+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap();
+
+    let result = open_re.replace_all(&protected, |caps: &regex::Captures| {
+        let styles: Vec<String> = attr_re
+            .captures_iter(&caps[1])
+            .filter_map(|attr| {
+                let value = attr.get(2).or(attr.get(3)).or(attr.get(4))?.as_str().trim();
+                match attr[1].to_lowercase().as_str() {
+                    "color" => Some(format!("color: {}", value)),
+                    "face" => Some(format!("font-family: {}", value)),
+                    // Relative sizes like "+1" have no fixed equivalent
+                    "size" if value.chars().all(|c| c.is_ascii_digit()) => {
+                        let size = value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| (1..=7).contains(n))?;
+                        Some(format!("font-size: {}", FONT_SIZES[size - 1]))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        if styles.is_empty() {
+            "<span>".to_string()
+        } else {
+            format!("<span style=\"{}\">", styles.join("; "))
+        }
+    });
+    let close_re = Regex::new(r"(?i)</font\s*>").unwrap();
+    let result = close_re.replace_all(&result, "</span>");
+
+    restore_code(&result, &code)
+}
+
+/// Convert HTML style attributes (double- or single-quoted) to JSX format.
+/// Values are always emitted in double quotes; quotes inside a value, as in
+/// `font-family: "Noto Sans"`, become single quotes.
 fn convert_style_to_jsx(content: &str) -> String {
-    let re = Regex::new(r#"style="([^"]*)""#).unwrap();
+    let re = Regex::new(r#"(?i)(\s)style=(?:"([^"]*)"|'([^']*)')"#).unwrap();
 
     re.replace_all(content, |caps: &regex::Captures| {
-        let style_str = &caps[1];
+        let style_str = caps.get(2).or(caps.get(3)).map_or("", |m| m.as_str());
         let mut jsx_props = Vec::new();
 
         for prop in style_str.split(';') {
@@ -114,20 +414,64 @@ fn convert_style_to_jsx(content: &str) -> String {
             let parts: Vec<&str> = prop.splitn(2, ':').collect();
             if parts.len() == 2 {
                 let name = css_property_to_camel_case(parts[0].trim());
-                let value = parts[1].trim();
+                let value = parts[1].trim().replace('"', "'");
                 jsx_props.push(format!("{}: \"{}\"", name, value));
             }
         }
 
         if jsx_props.is_empty() {
-            String::new()
+            caps[1].to_string()
         } else {
-            format!("style={{{{{}}}}}", jsx_props.join(", "))
+            format!("{}style={{{{{}}}}}", &caps[1], jsx_props.join(", "))
         }
     })
     .to_string()
 }
 
+/// Convert GitHub alert blockquotes (`> [!NOTE]`, `> [!WARNING]`, ...) to
+/// Fumadocs `<Callout>` components. The rest of the blockquote becomes the
+/// callout body; other blockquotes and fenced code blocks are left alone.
+fn convert_github_alerts(content: &str) -> String {
+    let alert_re =
+        Regex::new(r"(?i)^\s*>\s*\[!(NOTE|TIP|IMPORTANT|WARNING|CAUTION)\]\s*$").unwrap();
+    let quote_re = Regex::new(r"^\s*> ?(.*)$").unwrap();
+
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut result = Vec::new();
+    let mut open_fence = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        let in_code_block = in_fence(line, &mut open_fence);
+        let Some(caps) = alert_re.captures(line).filter(|_| !in_code_block) else {
+            result.push(line.to_string());
+            continue;
+        };
+
+        let callout_type = match caps[1].to_uppercase().as_str() {
+            "WARNING" => "warn",
+            "IMPORTANT" | "CAUTION" => "error",
+            _ => "info",
+        };
+        let mut body = Vec::new();
+        while let Some(caps) = lines.get(i).and_then(|l| quote_re.captures(l)) {
+            body.push(caps[1].trim_end().to_string());
+            i += 1;
+        }
+        while body.last().is_some_and(|l| l.is_empty()) {
+            body.pop();
+        }
+
+        result.push(format!("<Callout type=\"{}\">", callout_type));
+        result.extend(body);
+        result.push("</Callout>".to_string());
+    }
+
+    result.join("\n")
+}
+
 /// Remove Hugo callout shortcodes that are invalid in MDX.
 fn convert_hugo_callout_shortcodes(content: &str) -> String {
     let mut result = content.to_string();
@@ -145,100 +489,202 @@ fn convert_hugo_callout_shortcodes(content: &str) -> String {
     result
 }
 
-/// Convert Hugo details shortcode to Fumadocs Accordion components
-fn convert_hugo_details_to_accordion(content: &str) -> String {
-    let mut result = content.to_string();
-
-    // First, handle single-line shortcodes: {{% details title="..." %}} content {{% /details %}}
-    let re_single_line =
-        Regex::new(r#"\{\{% details title="([^"]*)"[^%]*%\}\}\s*(.+?)\s*\{\{% /details %\}\}"#)
-            .unwrap();
-    result = re_single_line
-        .replace_all(&result, "<Accordion title=\"$1\">\n$2\n</Accordion>")
-        .to_string();
+/// Convert Hugo notice shortcodes (`{{% notice warning %}} ... {{% /notice %}}`)
+/// to Fumadocs Callout components
+fn convert_hugo_notice_shortcodes(content: &str) -> String {
+    let callout_open = |caps: &regex::Captures| {
+        let callout_type = match caps[1].to_lowercase().as_str() {
+            "warning" => "warn",
+            "danger" => "error",
+            _ => "info",
+        };
+        format!("<Callout type=\"{}\">", callout_type)
+    };
+
+    // Single-line shortcodes: {{% notice info %}} content {{% /notice %}}
+    let re_single_line = Regex::new(
+        r"\{\{[<%]\s*notice\s+(\w+)\s*[>%]\}\}[ \t]*([^\n]+?)[ \t]*\{\{[<%]\s*/notice\s*[>%]\}\}",
+    )
+    .unwrap();
+    let result = re_single_line.replace_all(content, |caps: &regex::Captures| {
+        format!("{}\n{}\n</Callout>", callout_open(caps), &caps[2])
+    });
 
     // Convert opening tags
-    let re_open = Regex::new(r#"\{\{% details title="([^"]*)"[^%]*%\}\}"#).unwrap();
-    result = re_open
-        .replace_all(&result, r#"<Accordion title="$1">"#)
-        .to_string();
+    let re_open = Regex::new(r"\{\{[<%]\s*notice\s+(\w+)\s*[>%]\}\}").unwrap();
+    let result = re_open.replace_all(&result, callout_open);
 
-    // Convert closing tags - ensure they're on their own line for MDX compatibility
-    // Replace any occurrence where {{% /details %}} appears at end of line content
-    let re_closing = Regex::new(r#"([^\n])\s*\{\{% /details %\}\}"#).unwrap();
-    result = re_closing
-        .replace_all(&result, "$1\n</Accordion>")
-        .to_string();
+    // Closing tags go on their own line for MDX compatibility
+    let re_closing = Regex::new(r"([^\n])[ \t]*\{\{[<%]\s*/notice\s*[>%]\}\}").unwrap();
+    let result = re_closing.replace_all(&result, "$1\n</Callout>");
 
-    // Handle any remaining standalone closing tags
-    result = result.replace("{{% /details %}}", "</Accordion>");
+    let re_standalone = Regex::new(r"\{\{[<%]\s*/notice\s*[>%]\}\}").unwrap();
+    re_standalone.replace_all(&result, "</Callout>").to_string()
+}
+
+/// Replace the block tags matched by `tag_re` with Accordion tags.
+///
+/// Matches with an `open` group start an accordion titled by the optional
+/// `title` group; other matches close one. Tags are paired with a stack, so
+/// nested blocks become nested accordions, and are put on their own lines
+/// for MDX. A closing tag with no open block is left as is.
+fn convert_blocks_to_accordions(content: &str, tag_re: &Regex) -> String {
+    let mut result = String::new();
+    let mut depth = 0;
+    let mut last = 0;
+    let mut after_open = false;
+
+    for caps in tag_re.captures_iter(content) {
+        let tag = caps.get(0).unwrap();
+        let text = &content[last..tag.start()];
+        result.push_str(if after_open { text.trim_start() } else { text });
+        last = tag.end();
+        after_open = false;
+
+        if caps.name("open").is_some() {
+            let title = caps.name("title").map_or("", |t| t.as_str().trim());
+            result.truncate(result.trim_end_matches([' ', '\t']).len());
+            if !result.is_empty() && !result.ends_with('\n') {
+                result.push('\n');
+            }
+            result.push_str(&format!("<Accordion title=\"{}\">\n", title));
+            depth += 1;
+            after_open = true;
+        } else if depth > 0 {
+            result.truncate(result.trim_end().len());
+            result.push_str("\n</Accordion>");
+            depth -= 1;
+        } else {
+            result.push_str(tag.as_str());
+        }
+    }
+    let rest = &content[last..];
+    result.push_str(if after_open { rest.trim_start() } else { rest });
+    result
+}
+
+/// Convert native HTML `<details><summary>` blocks to Accordion components.
+/// A block without `<summary>` gets an empty title. Wrapping in
+/// `<Accordions>` is left to [`convert_hugo_details_to_accordion`].
+fn convert_html_details_to_accordion(content: &str) -> String {
+    let tag_re = Regex::new(
+        r"(?i)(?P<open><details\b[^>]*>\s*(?:<summary\b[^>]*>(?P<title>[\s\S]*?)</summary\s*>)?)|</details\s*>",
+    )
+    .unwrap();
+    convert_blocks_to_accordions(content, &tag_re)
+}
+
+/// Convert Hugo details shortcode to Fumadocs Accordion components, then
+/// wrap consecutive accordions in `<Accordions>`
+fn convert_hugo_details_to_accordion(content: &str) -> String {
+    let tag_re = Regex::new(
+        r#"(?P<open>\{\{% details title="(?P<title>[^"]*)"[^%]*%\}\})|\{\{% /details %\}\}"#,
+    )
+    .unwrap();
+    let result = convert_blocks_to_accordions(content, &tag_re);
 
     // Wrap consecutive Accordion blocks in Accordions
-    result = wrap_accordions_in_container(&result);
+    wrap_accordions_in_container(&result)
+}
 
+/// Replace fenced code blocks and inline code spans with placeholders so
+/// later rewrites leave them alone. Fences are found as in
+/// [`normalize_fence_languages`], so `~~~` and longer backtick fences count.
+/// Undo with [`restore_code`].
+fn protect_code(content: &str) -> (String, Vec<String>) {
+    let mut code = Vec::new();
+    let mut placeholder = |span: &str| {
+        code.push(span.to_string());
+        format!("___CODE_SPAN_PLACEHOLDER_{}___", code.len() - 1)
+    };
+
+    let mut fenced = String::with_capacity(content.len());
+    let mut last = 0;
+    for block in fenced_blocks(content) {
+        fenced.push_str(&content[last..block.start]);
+        fenced.push_str(&placeholder(&content[block.clone()]));
+        last = block.end;
+    }
+    fenced.push_str(&content[last..]);
+
+    let span_re = Regex::new(r"```[^`\n]+```|``[^`\n]+``|`[^`\n]+`").unwrap();
+    let protected = span_re
+        .replace_all(&fenced, |caps: &regex::Captures| placeholder(&caps[0]))
+        .into_owned();
+    (protected, code)
+}
+
+/// Put back the code replaced by [`protect_code`]
+fn restore_code(content: &str, code: &[String]) -> String {
+    let mut result = content.to_string();
+    for (i, span) in code.iter().enumerate() {
+        result = result.replace(&format!("___CODE_SPAN_PLACEHOLDER_{}___", i), span);
+    }
     result
 }
 
-/// Convert block-level math delimiters $$ $$ to ```math code blocks
-/// Preserves whether there's a newline after the opening $$
-fn convert_math_blocks(content: &str) -> String {
-    // First, extract and protect code blocks
-    let code_block_re = Regex::new(r"```[\s\S]*?```").unwrap();
-    let mut code_blocks = Vec::new();
-    let mut protected_content = content.to_string();
+/// Convert LaTeX bracket delimiters to dollar math: `\(x\)` becomes `$x$`
+/// and a `\[` ... `\]` display block becomes `$$` ... `$$`, which the math
+/// conversions below then handle. Display math is either a whole line
+/// `\[ x \]`, or `\[` alone on its line followed by non-blank lines up to
+/// one ending in `\]`, with no other `\[` in between. Escaped brackets in
+/// prose like `\[1\] Smith` are kept. Code is skipped.
+fn convert_bracket_math(content: &str) -> String {
+    let (protected, code) = protect_code(content);
+
+    // A character of a line other than `\[`, `\]` or a line break
+    const PLAIN: &str = r"(?:[^\\\n]|\\[^\[\]\n])";
+    let block_re = Regex::new(&format!(
+        r"(?m)^([ \t]*)\\\[[ \t]*\n((?:[ \t]*(?:[^\\\s]|\\[^\[\n])(?:[^\\\n]|\\[^\[\n])*\n)*?)[ \t]*({PLAIN}*)\\\][ \t]*$"
+    ))
+    .unwrap();
+    let result = block_re.replace_all(&protected, |caps: &regex::Captures| {
+        let body = format!("{}{}", &caps[2], &caps[3]);
+        format!("{}$$\n{}\n$$", &caps[1], body.trim())
+    });
+
+    let line_re = Regex::new(&format!(r"(?m)^([ \t]*)\\\[({PLAIN}+?)\\\][ \t]*$")).unwrap();
+    let result = line_re.replace_all(&result, |caps: &regex::Captures| {
+        format!("{}$$\n{}\n$$", &caps[1], caps[2].trim())
+    });
+
+    let inline_re = Regex::new(r"\\\(([^\n]+?)\\\)").unwrap();
+    let result = inline_re.replace_all(&result, |caps: &regex::Captures| {
+        format!("${}$", caps[1].trim())
+    });
+
+    restore_code(&result, &code)
+}
 
-    // Replace code blocks with placeholders
-    for (i, mat) in code_block_re.find_iter(content).enumerate() {
-        code_blocks.push(mat.as_str().to_string());
-        let placeholder = format!("___CODE_BLOCK_PLACEHOLDER_{}___", i);
-        protected_content = protected_content.replacen(mat.as_str(), &placeholder, 1);
-    }
+/// Convert display math delimited by $$ $$ to ```math code blocks.
+///
+/// Only `$$` blocks that start and end their lines are display math; `$$x$$`
+/// inside a line of text is inline math (as produced by
+/// [`convert_inline_math`]) and is kept, so formatting stays idempotent.
+fn convert_math_blocks(content: &str) -> String {
+    // First, protect code blocks and inline code spans
+    let (protected_content, code_blocks) = protect_code(content);
 
-    // Match $$ ... $$ (both inline and block forms) only outside code blocks
-    // This regex captures: opening $$, optional newline, content, optional newline, closing $$
-    let re = Regex::new(r"\$\$(\r?\n)?([\s\S]*?)(\r?\n)?\$\$").unwrap();
+    // Opening $$ at the start of a line, closing $$ at the end of one, and no
+    // $$ in between; the newlines next to the delimiters are optional
+    let re = Regex::new(r"(?m)^([ \t]*)\$\$(\r?\n)?((?:[^$]|\$[^$])*?)(\r?\n)?[ \t]*\$\$[ \t]*$")
+        .unwrap();
 
     let result = re
         .replace_all(&protected_content, |caps: &regex::Captures| {
-            let has_opening_newline = caps.get(1).is_some();
-            let math_content = &caps[2];
-            let has_closing_newline = caps.get(3).is_some();
-
-            // If original format had newlines, preserve them; otherwise add them
-            if has_opening_newline && has_closing_newline {
-                // Block format: $$\ncontent\n$$ -> ```math\ncontent\n```
-                format!("```math\n{}\n```", math_content)
-            } else {
-                // Inline format: $$content$$ -> ```math\ncontent\n```
-                format!("```math\n{}\n```", math_content)
-            }
+            let indent = &caps[1];
+            format!("{}```math\n{}\n{}```", indent, &caps[3], indent)
         })
         .to_string();
 
-    // Restore code blocks
-    let mut final_result = result;
-    for (i, block) in code_blocks.iter().enumerate() {
-        let placeholder = format!("___CODE_BLOCK_PLACEHOLDER_{}___", i);
-        final_result = final_result.replace(&placeholder, block);
-    }
-
-    final_result
+    restore_code(&result, &code_blocks)
 }
 
 /// Convert inline math delimiters $ $ to $$ $$
 /// Only converts single dollar signs, not double dollar signs
 fn convert_inline_math(content: &str) -> String {
-    // First, extract and protect code blocks
-    let code_block_re = Regex::new(r"```[\s\S]*?```").unwrap();
-    let mut code_blocks = Vec::new();
-    let mut protected_content = content.to_string();
-
-    // Replace code blocks with placeholders
-    for (i, mat) in code_block_re.find_iter(content).enumerate() {
-        code_blocks.push(mat.as_str().to_string());
-        let placeholder = format!("___CODE_BLOCK_PLACEHOLDER_{}___", i);
-        protected_content = protected_content.replacen(mat.as_str(), &placeholder, 1);
-    }
+    // First, protect code blocks and inline code spans
+    let (protected_content, code_blocks) = protect_code(content);
 
     let mut result = String::new();
     let mut chars = protected_content.chars().peekable();
@@ -304,41 +750,59 @@ fn convert_inline_math(content: &str) -> String {
         result.push_str(&math_buffer);
     }
 
-    // Restore code blocks
-    let mut final_result = result;
-    for (i, block) in code_blocks.iter().enumerate() {
-        let placeholder = format!("___CODE_BLOCK_PLACEHOLDER_{}___", i);
-        final_result = final_result.replace(&placeholder, block);
-    }
-
-    final_result
+    restore_code(&result, &code_blocks)
 }
 
-/// Wrap consecutive Accordion blocks in a single Accordions container
+/// Wrap consecutive Accordion blocks in a single Accordions container.
+/// Accordions nested inside an Accordion get their own container; tags
+/// inside fenced code blocks or an existing `<Accordions>` are left alone.
 fn wrap_accordions_in_container(content: &str) -> String {
-    let lines: Vec<&str> = content.lines().collect();
+    // split keeps a trailing newline, which lines() would drop
+    let lines: Vec<&str> = content.split('\n').collect();
     let mut result = Vec::new();
     let mut in_sequence = false;
     let mut accordion_buffer = Vec::new();
     let mut depth = 0;
+    // Buffer index of the top-level Accordion currently open
+    let mut open_at = 0;
+    let mut open_fence = None;
+    // Depth of `<Accordions>` containers already in the content
+    let mut container_depth = 0usize;
 
     for (i, line) in lines.iter().enumerate() {
-        if line.contains("<Accordion ") && !in_sequence {
+        let is_tag_line = !in_fence(line, &mut open_fence);
+
+        if is_tag_line && line.contains("<Accordion ") && !in_sequence && container_depth == 0 {
             // Start of accordion sequence
             in_sequence = true;
+            open_at = accordion_buffer.len();
             accordion_buffer.push(line.to_string());
             depth = 1;
         } else if in_sequence {
             accordion_buffer.push(line.to_string());
 
             // Track depth
-            if line.contains("<Accordion ") {
+            if is_tag_line && line.contains("<Accordion ") {
+                if depth == 0 {
+                    open_at = accordion_buffer.len() - 1;
+                }
                 depth += 1;
             }
-            if line.contains("</Accordion>") {
+            let closes_top_level = is_tag_line && line.contains("</Accordion>") && depth == 1;
+            if is_tag_line && line.contains("</Accordion>") {
                 depth -= 1;
             }
 
+            if closes_top_level {
+                // Wrap the accordions nested in the one that just closed
+                let mut inner = accordion_buffer.split_off(open_at + 1);
+                let closing = inner.pop();
+                if !inner.is_empty() {
+                    accordion_buffer.push(wrap_accordions_in_container(&inner.join("\n")));
+                }
+                accordion_buffer.extend(closing);
+            }
+
             // Check if sequence ends
             if depth == 0 {
                 // Look ahead to see if next non-empty line is another Accordion
@@ -363,6 +827,11 @@ fn wrap_accordions_in_container(content: &str) -> String {
                 }
             }
         } else {
+            if is_tag_line {
+                container_depth += line.matches("<Accordions>").count();
+                container_depth =
+                    container_depth.saturating_sub(line.matches("</Accordions>").count());
+            }
             result.push(line.to_string());
         }
     }
@@ -377,32 +846,336 @@ fn wrap_accordions_in_container(content: &str) -> String {
     result.join("\n")
 }
 
-/// Format all MDX files in a directory recursively
-pub fn format_all_mdx_files(docs_dir: &Path) -> crate::error::Result<usize> {
-    let mut modified_count = 0;
+/// Check that every `<Accordion>` sits directly inside an `<Accordions>` and
+/// that no `<Accordions>` is nested directly in another.
+///
+/// Returns one message per problem, with 1-based line numbers. Fenced code
+/// blocks are ignored.
+pub fn validate_accordions(content: &str) -> Vec<String> {
+    let tag_re = Regex::new(r"<(/?)(Accordions|Accordion)\b[^>]*?(/?)>").unwrap();
+    let mut stack: Vec<(&str, usize)> = Vec::new();
+    let mut problems = Vec::new();
+    let mut open_fence = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        if in_fence(line, &mut open_fence) {
+            continue;
+        }
+
+        for cap in tag_re.captures_iter(line) {
+            let is_close = &cap[1] == "/";
+            let self_closing = &cap[3] == "/";
+            let name = if &cap[2] == "Accordions" {
+                "Accordions"
+            } else {
+                "Accordion"
+            };
+
+            if is_close {
+                match stack.pop() {
+                    Some((open, _)) if open == name => {}
+                    Some((open, open_line)) => problems.push(format!(
+                        "line {}: </{}> closes <{}> opened on line {}",
+                        line_no, name, open, open_line
+                    )),
+                    None => problems.push(format!("line {}: unmatched </{}>", line_no, name)),
+                }
+                continue;
+            }
+
+            let parent = stack.last().map(|(open, _)| *open);
+            if name == "Accordions" && parent == Some("Accordions") {
+                problems.push(format!(
+                    "line {}: <Accordions> nested directly inside <Accordions>",
+                    line_no
+                ));
+            }
+            if name == "Accordion" && parent != Some("Accordions") {
+                problems.push(format!(
+                    "line {}: <Accordion> outside of an <Accordions> container",
+                    line_no
+                ));
+            }
+            if !self_closing {
+                stack.push((name, line_no));
+            }
+        }
+    }
+
+    for (open, open_line) in stack {
+        problems.push(format!("line {}: unclosed <{}>", open_line, open));
+    }
+
+    problems
+}
+
+/// Check that HTML and JSX tags are balanced, as MDX requires.
+///
+/// Tags are matched by name with a stack; self-closing tags are skipped.
+/// Returns one message per problem with 1-based line numbers, such as
+/// `line 9: expected </Table>, found </List>`. Code is ignored.
+pub fn validate_mdx_tags(content: &str) -> Vec<String> {
+    // Blank out code but keep its newlines, so line numbers stay right
+    let code_re = Regex::new(r"```[\s\S]*?```|`[^`\n]+`").unwrap();
+    let protected = code_re.replace_all(content, |caps: &regex::Captures| {
+        caps[0]
+            .chars()
+            .map(|c| if c == '\n' { '\n' } else { ' ' })
+            .collect::<String>()
+    });
+    let tag_re = Regex::new(
+        r#"<(/?)([A-Za-z][\w.-]*)((?:\s(?:[^<>"'{}]|"[^"]*"|'[^']*'|\{[^{}]*(?:\{[^{}]*\}[^{}]*)*\})*?)?)\s*(/?)>"#,
+    )
+    .unwrap();
+    let mut stack: Vec<(String, usize)> = Vec::new();
+    let mut problems = Vec::new();
+
+    for caps in tag_re.captures_iter(&protected) {
+        let line_no = protected[..caps.get(0).unwrap().start()]
+            .matches('\n')
+            .count()
+            + 1;
+        let name = caps[2].to_string();
+        if &caps[4] == "/" {
+            continue;
+        }
+        if &caps[1] != "/" {
+            stack.push((name, line_no));
+            continue;
+        }
+
+        match stack.last() {
+            Some((open, _)) if *open == name => {
+                stack.pop();
+            }
+            Some((open, open_line)) => {
+                problems.push(format!(
+                    "line {}: expected </{}> (opened on line {}), found </{}>",
+                    line_no, open, open_line, name
+                ));
+                // Recover by closing up to the matching open tag, if any
+                if let Some(pos) = stack.iter().rposition(|(open, _)| *open == name) {
+                    stack.truncate(pos);
+                }
+            }
+            None => problems.push(format!("line {}: unmatched </{}>", line_no, name)),
+        }
+    }
+
+    for (open, open_line) in stack {
+        problems.push(format!("line {}: unclosed <{}>", open_line, open));
+    }
+
+    problems
+}
 
-    for entry in WalkDir::new(docs_dir)
+/// Format every MDX file under `docs_dir` accepted by `include` in memory, in
+/// parallel, then pass each result to `inspect` in walk order. Returns the
+/// paths whose formatted output differs from disk, with that output. Files
+/// that opt out with `fuma_format: false` are skipped.
+fn format_tree(
+    docs_dir: &Path,
+    config: &FormatConfig,
+    include: impl Fn(&Path) -> bool,
+    mut inspect: impl FnMut(&Path, &str),
+) -> crate::error::Result<Vec<(PathBuf, String)>> {
+    let paths: Vec<PathBuf> = WalkDir::new(docs_dir)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.path().extension().is_some_and(|ext| ext == "mdx"))
-    {
-        let path = entry.path();
-        let original = fs::read_to_string(path)?;
-        let formatted = format_mdx_file(&original);
-
-        if formatted != original {
-            fs::write(path, formatted)?;
-            modified_count += 1;
+        .filter(|e| include(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+
+    let results = paths
+        .into_par_iter()
+        .map(|path| {
+            let original = fs::read_to_string(&path)?;
+            if opts_out_of_formatting(&original) {
+                return Ok(None);
+            }
+            let formatted = format_mdx_file(&original, config);
+            let is_changed = formatted != original;
+            Ok(Some((path, formatted, is_changed)))
+        })
+        .collect::<crate::error::Result<Vec<_>>>()?;
+
+    let mut changed = Vec::new();
+    for (path, formatted, is_changed) in results.into_iter().flatten() {
+        inspect(&path, &formatted);
+        if is_changed {
+            changed.push((path, formatted));
         }
     }
 
-    Ok(modified_count)
+    Ok(changed)
+}
+
+/// List the MDX files under `docs_dir` that formatting would change, without
+/// writing anything
+pub fn check_all_mdx_files(
+    docs_dir: &Path,
+    config: &FormatConfig,
+) -> crate::error::Result<Vec<PathBuf>> {
+    let changed = format_tree(docs_dir, config, |_| true, |_, _| {})?;
+    Ok(changed.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Format all MDX files in a directory recursively, returning the paths of
+/// the files that were rewritten. Unchanged files are not written.
+///
+/// Structural problems found in the formatted output are printed and added
+/// to `report`.
+pub fn format_all_mdx_files(
+    docs_dir: &Path,
+    config: &FormatConfig,
+    report: &mut GenerationReport,
+) -> crate::error::Result<Vec<PathBuf>> {
+    format_mdx_files_where(docs_dir, config, report, |_| true)
+}
+
+/// Like [`format_all_mdx_files`], but only for the files `include` accepts,
+/// e.g. the index pages of an index-only run.
+pub fn format_mdx_files_where(
+    docs_dir: &Path,
+    config: &FormatConfig,
+    report: &mut GenerationReport,
+    include: impl Fn(&Path) -> bool,
+) -> crate::error::Result<Vec<PathBuf>> {
+    let changed = format_tree(docs_dir, config, include, |path, formatted| {
+        let problems = validate_accordions(formatted)
+            .into_iter()
+            .chain(validate_mdx_tags(formatted));
+        for problem in problems {
+            let warning = format!("{}: {}", path.display(), problem);
+            eprintln!("Warning: {}", warning);
+            report.format_warnings.push(warning);
+        }
+    })?;
+
+    changed
+        .into_par_iter()
+        .map(|(path, formatted)| {
+            fs::write(&path, formatted)?;
+            Ok(path)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn common_aliases() -> HashMap<String, String> {
+        [
+            ("c++", "cpp"),
+            ("py", "python"),
+            ("js", "javascript"),
+            ("sh", "bash"),
+        ]
+        .into_iter()
+        .map(|(alias, lang)| (alias.to_string(), lang.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_normalize_fence_languages() {
+        let aliases = common_aliases();
+        let input = "```C++\nint x;\n```\n\n```py title=\"main.py\"\nprint(1)\n```\n\n```JS\nf()\n```\n\n~~~sh\nls\n~~~";
+        assert_eq!(
+            normalize_fence_languages(input, &aliases),
+            "```cpp\nint x;\n```\n\n```python title=\"main.py\"\nprint(1)\n```\n\n```javascript\nf()\n```\n\n~~~bash\nls\n~~~"
+        );
+    }
+
+    #[test]
+    fn test_normalize_fence_languages_leaves_unknown() {
+        let aliases = common_aliases();
+        let input = "```rust\nlet py = 1;\n```\n```\nplain\n```";
+        assert_eq!(normalize_fence_languages(input, &aliases), input);
+    }
+
+    #[test]
+    fn test_normalize_fence_languages_case_insensitive_keys() {
+        let aliases: HashMap<String, String> = [("CPP".to_string(), "cpp".to_string())]
+            .into_iter()
+            .collect();
+        let input = "```Cpp\nint x;\n```\n\n```cpp\nint y;\n```";
+        assert_eq!(
+            normalize_fence_languages(input, &aliases),
+            "```cpp\nint x;\n```\n\n```cpp\nint y;\n```"
+        );
+    }
+
+    #[test]
+    fn test_normalize_fence_languages_mixed_fences() {
+        let aliases = common_aliases();
+        let input = "```md\n~~~py\n~~~\n```\n\n~~~~sh\n```\n~~~\n~~~~\n\n```js\nf()\n```";
+        assert_eq!(
+            normalize_fence_languages(input, &aliases),
+            "```md\n~~~py\n~~~\n```\n\n~~~~bash\n```\n~~~\n~~~~\n\n```javascript\nf()\n```"
+        );
+    }
+
+    #[test]
+    fn test_format_config_default_has_no_fence_aliases() {
+        let input = "```py\nprint(1)\n```";
+        let aliases = FormatConfig::default().fence_language_aliases;
+        assert!(aliases.is_empty());
+        assert_eq!(normalize_fence_languages(input, &aliases), input);
+    }
+
+    #[test]
+    fn test_strip_frontmatter() {
+        let (frontmatter, body) = strip_frontmatter("---\ntitle: A\n---\n\n# Body\n");
+        assert_eq!(frontmatter.as_deref(), Some("---\ntitle: A\n---\n"));
+        assert_eq!(body, "\n# Body\n");
+
+        let (frontmatter, body) = strip_frontmatter("# Body\n\ntext");
+        assert_eq!(frontmatter, None);
+        assert_eq!(body, "# Body\n\ntext");
+    }
+
+    #[test]
+    fn test_strip_frontmatter_ignores_thematic_break() {
+        let input = "Intro\n\n---\n\nMore\n---\n";
+        assert_eq!(strip_frontmatter(input), (None, input.to_string()));
+        // Unclosed block at the start is not frontmatter either
+        let input = "---\nNot closed";
+        assert_eq!(strip_frontmatter(input), (None, input.to_string()));
+    }
+
+    #[test]
+    fn test_format_mdx_file_keeps_frontmatter() {
+        let input = "---\ntitle: \"<!-- x -->\"\n---\n\n<!-- note -->Body<br>";
+        assert_eq!(
+            format_mdx_file(input, &FormatConfig::default()),
+            "---\ntitle: \"<!-- x -->\"\n---\n\nBody<br />"
+        );
+    }
+
+    #[test]
+    fn test_format_mdx_file_normalizes_crlf() {
+        let input =
+            "# Title\r\n\r\n\r\n\r\n{{% details title=\"Q\" %}}\r\nA\r\n{{% /details %}}\r\n";
+        let output = format_mdx_file(input, &FormatConfig::default());
+        assert!(!output.contains('\r'));
+        assert_eq!(
+            output,
+            "# Title\n\n<Accordions>\n<Accordion title=\"Q\">\nA\n</Accordion>\n</Accordions>\n"
+        );
+
+        let config = FormatConfig {
+            preserve_crlf: true,
+            ..FormatConfig::default()
+        };
+        assert_eq!(
+            format_mdx_file(input, &config),
+            output.replace('\n', "\r\n")
+        );
+    }
+
     #[test]
     fn test_remove_html_comments() {
         let input = "Hello <!-- comment --> World";
@@ -427,11 +1200,46 @@ mod tests {
     #[test]
     fn test_remove_shield_badges() {
         let input = "# Title\n![badge](https://img.shields.io/badge/test)\nNormal content";
-        let output = remove_shield_badges(input);
+        let output = remove_shield_badges(input, &FormatConfig::default().badge_hosts, &[]);
         assert!(!output.contains("shields.io"));
         assert!(output.contains("Normal content"));
     }
 
+    #[test]
+    fn test_remove_shield_badges_by_alt() {
+        let denylist = vec!["build".to_string(), "ci".to_string()];
+        let input = "# Title\n\
+[![Build](https://img.shields.io/badge/build-passing-green)](https://ci.example.com)\n\
+![docs](https://img.shields.io/badge/docs-online-blue) ![ci](https://img.shields.io/badge/ci-ok-green)\n\
+Normal content";
+        let output = remove_shield_badges(input, &FormatConfig::default().badge_hosts, &denylist);
+        assert_eq!(
+            output,
+            "# Title\n![docs](https://img.shields.io/badge/docs-online-blue) \nNormal content"
+        );
+    }
+
+    #[test]
+    fn test_remove_badges_custom_hosts() {
+        let hosts = vec!["badgen.net".to_string(), "badges.hoa.moe".to_string()];
+        let input = "![npm](https://badgen.net/npm/v/foo)\n\
+![ci](https://badges.hoa.moe/ci.svg)\n\
+![diagram](https://example.com/diagram.png)\n\
+![stars](https://img.shields.io/github/stars/foo)";
+        assert_eq!(
+            remove_shield_badges(input, &hosts, &[]),
+            "![diagram](https://example.com/diagram.png)\n![stars](https://img.shields.io/github/stars/foo)"
+        );
+
+        let denylist = vec!["npm".to_string(), "diagram".to_string()];
+        assert_eq!(
+            remove_shield_badges(input, &hosts, &denylist),
+            "![ci](https://badges.hoa.moe/ci.svg)\n\
+![diagram](https://example.com/diagram.png)\n\
+![stars](https://img.shields.io/github/stars/foo)"
+        );
+    }
+
     #[test]
     fn test_convert_bare_urls_to_links_http() {
         let input = "See <http://example.com> for more.";
@@ -445,17 +1253,63 @@ mod tests {
     #[test]
     fn test_fix_self_closing_tags() {
         let input = "Line 1<br>Line 2<hr>Line 3";
-        let output = fix_self_closing_tags(input);
+        let output = fix_self_closing_tags(input, &[]);
         assert_eq!(output, "Line 1<br />Line 2<hr />Line 3");
     }
 
     #[test]
     fn test_fix_self_closing_tags_with_spaces() {
         let input = "Text<br >more<hr  >end";
-        let output = fix_self_closing_tags(input);
+        let output = fix_self_closing_tags(input, &[]);
         assert_eq!(output, "Text<br />more<hr />end");
     }
 
+    #[test]
+    fn test_fix_self_closing_tags_img() {
+        assert_eq!(
+            fix_self_closing_tags(r#"<img src="x">"#, &[]),
+            r#"<img src="x" />"#
+        );
+        assert_eq!(
+            fix_self_closing_tags(r#"<img src="x" alt="a > b" width="200">"#, &[]),
+            r#"<img src="x" alt="a > b" width="200" />"#
+        );
+    }
+
+    #[test]
+    fn test_fix_self_closing_tags_already_closed() {
+        let input = r#"<img src="x" /> and <br/>"#;
+        assert_eq!(
+            fix_self_closing_tags(input, &[]),
+            r#"<img src="x" /> and <br />"#
+        );
+    }
+
+    #[test]
+    fn test_fix_self_closing_tags_extra_and_lookalikes() {
+        let input = "<col span=2><colgroup><col-x><spacer><image-box>";
+        assert_eq!(
+            fix_self_closing_tags(input, &["spacer".to_string()]),
+            "<col span=2 /><colgroup><col-x><spacer /><image-box>"
+        );
+    }
+
+    #[test]
+    fn test_fix_self_closing_tags_skips_components() {
+        let input = r#"<Link href="/a">text</Link> <Source src="x"></Source> <Input value="v" />"#;
+        assert_eq!(fix_self_closing_tags(input, &[]), input);
+        assert_eq!(format_mdx_file(input, &FormatConfig::default()), input);
+    }
+
+    #[test]
+    fn test_fix_self_closing_tags_skips_code() {
+        let input = "```html\n<img src=\"a.png\">\n```\n\nUse `<br>` or <br>";
+        assert_eq!(
+            fix_self_closing_tags(input, &[]),
+            "```html\n<img src=\"a.png\">\n```\n\nUse `<br>` or <br />"
+        );
+    }
+
     #[test]
     fn test_fix_malformed_html() {
         let input = "<table><tr></table>";
@@ -491,6 +1345,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_convert_font_tags_color() {
+        let input = r#"<font color="red">重点</font> and <FONT size=4 color='#00f'>big</FONT>"#;
+        let output = convert_font_tags(input);
+        assert_eq!(
+            output,
+            r##"<span style="color: red">重点</span> and <span style="font-size: large; color: #00f">big</span>"##
+        );
+        assert_eq!(
+            convert_style_to_jsx(&output),
+            r##"<span style={{color: "red"}}>重点</span> and <span style={{fontSize: "large", color: "#00f"}}>big</span>"##
+        );
+    }
+
+    #[test]
+    fn test_convert_font_tags_without_known_attributes() {
+        assert_eq!(
+            convert_font_tags(r#"<font size="+1" class="x">text</font>"#),
+            "<span>text</span>"
+        );
+        let code = "`<font color=\"red\">`\n```html\n<font>x</font>\n```";
+        assert_eq!(convert_font_tags(code), code);
+    }
+
     #[test]
     fn test_convert_style_to_jsx() {
         let input = r#"<div style="text-align:center;color:red;"></div>"#;
@@ -499,6 +1377,32 @@ mod tests {
         assert!(output.contains("color"));
     }
 
+    #[test]
+    fn test_convert_style_to_jsx_single_quotes() {
+        let input = r#"<span style='color:red; font-family: "Noto Sans"'>Text</span>"#;
+        assert_eq!(
+            convert_style_to_jsx(input),
+            r#"<span style={{color: "red", fontFamily: "'Noto Sans'"}}>Text</span>"#
+        );
+    }
+
+    #[test]
+    fn test_img_with_style_is_self_closed_jsx() {
+        let config = FormatConfig::default();
+        assert_eq!(
+            format_mdx_file(r#"<img src="a.png" style="width:200px">"#, &config),
+            r#"<img src="a.png" style={{width: "200px"}} />"#
+        );
+        assert_eq!(
+            format_mdx_file(r#"<IMG SRC="a.png" STYLE="width:200px">"#, &config),
+            r#"<IMG SRC="a.png" style={{width: "200px"}}>"#
+        );
+        assert_eq!(
+            format_mdx_file(r#"<img data-style="x" src="a.png">"#, &config),
+            r#"<img data-style="x" src="a.png" />"#
+        );
+    }
+
     #[test]
     fn test_convert_style_to_jsx_empty() {
         let input = r#"<div style=""></div>"#;
@@ -525,6 +1429,37 @@ mod tests {
         assert!(output.contains("Content here"));
     }
 
+    #[test]
+    fn test_convert_github_alerts_types() {
+        for (alert, callout_type) in [
+            ("NOTE", "info"),
+            ("TIP", "info"),
+            ("WARNING", "warn"),
+            ("IMPORTANT", "error"),
+        ] {
+            let input = format!("> [!{}]\n> 内容", alert);
+            assert_eq!(
+                convert_github_alerts(&input),
+                format!("<Callout type=\"{}\">\n内容\n</Callout>", callout_type)
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_github_alerts_multi_paragraph() {
+        let input = "Before\n\n> [!WARNING]\n> First paragraph\n>\n> Second paragraph\n\nAfter";
+        assert_eq!(
+            convert_github_alerts(input),
+            "Before\n\n<Callout type=\"warn\">\nFirst paragraph\n\nSecond paragraph\n</Callout>\n\nAfter"
+        );
+    }
+
+    #[test]
+    fn test_convert_github_alerts_leaves_plain_quotes() {
+        let input = "> Just a quote\n> [!NOTE] inline\n\n```\n> [!NOTE]\n```";
+        assert_eq!(convert_github_alerts(input), input);
+    }
+
     #[test]
     fn test_convert_hugo_callout_shortcodes() {
         let input = r#"{{< callout type="info" >}}
@@ -547,6 +1482,44 @@ Warning content
         assert!(output.contains("Warning content"));
     }
 
+    #[test]
+    fn test_convert_hugo_notice_shortcodes() {
+        let input = r#"{{% notice warning %}}
+Warning content
+{{% /notice %}}"#;
+        let output = convert_hugo_notice_shortcodes(input);
+        assert_eq!(
+            output,
+            "<Callout type=\"warn\">\nWarning content\n</Callout>"
+        );
+    }
+
+    #[test]
+    fn test_convert_hugo_notice_shortcodes_types() {
+        for (notice, callout_type) in [
+            ("info", "info"),
+            ("tip", "info"),
+            ("note", "info"),
+            ("warning", "warn"),
+            ("danger", "error"),
+        ] {
+            let input = format!("{{{{% notice {} %}}}} Text {{{{% /notice %}}}}", notice);
+            assert_eq!(
+                convert_hugo_notice_shortcodes(&input),
+                format!("<Callout type=\"{}\">\nText\n</Callout>", callout_type)
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_hugo_notice_closing_on_own_line() {
+        let input = "{{% notice info %}}\nLine 1\nLine 2 {{% /notice %}}";
+        assert_eq!(
+            convert_hugo_notice_shortcodes(input),
+            "<Callout type=\"info\">\nLine 1\nLine 2\n</Callout>"
+        );
+    }
+
     #[test]
     fn test_convert_hugo_details_multiline() {
         let input = r#"{{% details title="Question" %}}
@@ -572,6 +1545,107 @@ A2
         assert!(output.contains("</Accordions>"));
     }
 
+    #[test]
+    fn test_convert_hugo_details_nested() {
+        let input = r#"{{% details title="FAQ" %}}
+{{% details title="Q1" %}}
+A1
+{{% /details %}}
+{{% details title="Q2" %}}A2{{% /details %}}
+{{% /details %}}"#;
+        let output = convert_hugo_details_to_accordion(input);
+        assert_eq!(
+            output,
+            r#"<Accordions>
+<Accordion title="FAQ">
+<Accordions>
+<Accordion title="Q1">
+A1
+</Accordion>
+<Accordion title="Q2">
+A2
+</Accordion>
+</Accordions>
+</Accordion>
+</Accordions>"#
+        );
+        assert!(validate_accordions(&output).is_empty());
+    }
+
+    #[test]
+    fn test_convert_html_details_to_accordion() {
+        let input = "<details>\n<summary>第一题</summary>\n\n答案 A\n</details>";
+        assert_eq!(
+            convert_html_details_to_accordion(input),
+            "<Accordion title=\"第一题\">\n答案 A\n</Accordion>"
+        );
+    }
+
+    #[test]
+    fn test_convert_html_details_without_summary() {
+        let input = "Intro\n<details open>Hidden text</details>";
+        assert_eq!(
+            convert_html_details_to_accordion(input),
+            "Intro\n<Accordion title=\"\">\nHidden text\n</Accordion>"
+        );
+    }
+
+    #[test]
+    fn test_format_mdx_file_wraps_html_details() {
+        let input = "<details><summary>Q1</summary>A1</details>\n<details><summary>Q2</summary>A2</details>";
+        let output = format_mdx_file(input, &FormatConfig::default());
+        assert!(output.starts_with("<Accordions>\n<Accordion title=\"Q1\">"));
+        assert!(validate_accordions(&output).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_accordions_ignores_code_blocks() {
+        let input = r#"<Accordion title="Real">
+Answer
+</Accordion>
+
+```mdx
+<Accordion title="Example">
+Shown as code
+</Accordion>
+```"#;
+        let output = wrap_accordions_in_container(input);
+        assert_eq!(
+            output,
+            r#"<Accordions>
+<Accordion title="Real">
+Answer
+</Accordion>
+</Accordions>
+
+```mdx
+<Accordion title="Example">
+Shown as code
+</Accordion>
+```"#
+        );
+    }
+
+    #[test]
+    fn test_wrap_accordions_nested_with_blank_lines() {
+        let input = r#"<Accordion title="Outer">
+<Accordion title="A">
+a
+</Accordion>
+
+<Accordion title="B">
+b
+</Accordion>
+</Accordion>
+
+<Accordion title="Next">
+n
+</Accordion>"#;
+        let output = wrap_accordions_in_container(input);
+        assert_eq!(output.matches("<Accordions>").count(), 2);
+        assert!(validate_accordions(&output).is_empty());
+    }
+
     #[test]
     fn test_wrap_accordions_single() {
         let input = r#"<Accordion title="Q1">
@@ -592,7 +1666,7 @@ A1
 Math: $x = {1}$
 {{% details title="Test" %}}Answer{{% /details %}}"#;
 
-        let output = format_mdx_file(input);
+        let output = format_mdx_file(input, &FormatConfig::default());
 
         // Check all transformations applied
         assert!(!output.contains("<!--"));
@@ -613,10 +1687,15 @@ Math: $x = {1}$
 
     #[test]
     fn test_convert_math_blocks_inline_format() {
-        let input = "Some text $$x = y + z$$ more text";
+        // $$ on a line of its own is display math, even without newlines inside
+        let input = "Some text\n$$x = y + z$$\nmore text";
         let output = convert_math_blocks(input);
         assert!(output.contains("```math\nx = y + z\n```"));
         assert!(!output.contains("$$x"));
+
+        // Inside a line of text it is inline math and stays
+        let input = "Some text $$x = y + z$$ more text";
+        assert_eq!(convert_math_blocks(input), input);
     }
 
     #[test]
@@ -629,6 +1708,78 @@ Math: $x = {1}$
         assert!(output.contains("```"));
     }
 
+    #[test]
+    fn test_convert_bracket_math_inline() {
+        let input = "面积 \\( S = \\frac{1}{2}ab \\) 见下";
+        assert_eq!(
+            convert_bracket_math(input),
+            "面积 $S = \\frac{1}{2}ab$ 见下"
+        );
+        assert_eq!(
+            format_mdx_file(input, &FormatConfig::default()),
+            "面积 $$S = \\frac{1}{2}ab$$ 见下"
+        );
+    }
+
+    #[test]
+    fn test_convert_bracket_math_display() {
+        let input = "Text\n\\[\n\\sum_{i=1}^{n} i\n\\]\nMore";
+        assert_eq!(
+            convert_bracket_math(input),
+            "Text\n$$\n\\sum_{i=1}^{n} i\n$$\nMore"
+        );
+        assert_eq!(
+            format_mdx_file(input, &FormatConfig::default()),
+            "Text\n```math\n\\sum_{i=1}^{n} i\n```\nMore"
+        );
+    }
+
+    #[test]
+    fn test_convert_bracket_math_skips_code_and_escapes() {
+        let input = "See \\[1\\] and `\\(x\\)`\n```latex\n\\(y\\)\n```";
+        assert_eq!(convert_bracket_math(input), input);
+    }
+
+    #[test]
+    fn test_convert_bracket_math_single_line_display() {
+        assert_eq!(
+            convert_bracket_math("Text\n  \\[ x^2 \\]\nMore"),
+            "Text\n  $$\nx^2\n$$\nMore"
+        );
+    }
+
+    #[test]
+    fn test_convert_bracket_math_keeps_citations_across_lines() {
+        let input = "\\[1\\] Smith, Book.\n\nSome text.\nSee also \\[2\\]";
+        assert_eq!(convert_bracket_math(input), input);
+        assert_eq!(format_mdx_file(input, &FormatConfig::default()), input);
+
+        // A blank line or a second `\[` ends the candidate block
+        let input = "\\[\nx = 1\n\nSee also \\[2\\]";
+        assert_eq!(convert_bracket_math(input), input);
+        let input = "\\[\nx = 1\n\\[2\\] y \\]";
+        assert_eq!(convert_bracket_math(input), input);
+    }
+
+    #[test]
+    fn test_protect_code_tilde_and_long_fences() {
+        let input = "~~~\n$x = {1}$\n~~~\n\n````md\n```\n$y$\n```\n````\n\nText $z$";
+        assert_eq!(
+            format_mdx_file(input, &FormatConfig::default()),
+            "~~~\n$x = {1}$\n~~~\n\n````md\n```\n$y$\n```\n````\n\nText $$z$$"
+        );
+
+        let (protected, code) = protect_code("a\n~~~~\n```\n~~~\n~~~~\nb `c`");
+        assert_eq!(
+            protected,
+            "a\n___CODE_SPAN_PLACEHOLDER_0___\nb ___CODE_SPAN_PLACEHOLDER_1___"
+        );
+        assert_eq!(code, vec!["~~~~\n```\n~~~\n~~~~", "`c`"]);
+
+        // The line-based passes agree on what is fenced
+        assert!(validate_accordions("~~~\n<Accordion title=\"x\">\n~~~").is_empty());
+    }
+
     #[test]
     fn test_convert_inline_math() {
         let input = "The equation $x = y + z$ is simple.";
@@ -668,7 +1819,7 @@ Math: $x = {1}$
 
     #[test]
     fn test_math_conversion_integration() {
-        let input = "Text $inline$ math\n$$\nblock\nmath\n$$\nMore $x$ and $$E=mc^2$$";
+        let input = "Text $inline$ math\n$$\nblock\nmath\n$$\nMore $x$ and\n$$E=mc^2$$";
         let mut output = convert_math_blocks(input);
         output = convert_inline_math(&output);
 
@@ -688,7 +1839,7 @@ Math: $x = {1}$
     #[test]
     fn test_convert_math_blocks_ignores_code_blocks() {
         // Math inside code blocks should NOT be converted
-        let input = "Normal text $$x = y$$\n```markdown\n$$\\sin x$$\n```\nMore $$a = b$$";
+        let input = "Normal text\n$$x = y$$\n```markdown\n$$\\sin x$$\n```\nMore\n$$a = b$$";
         let output = convert_math_blocks(input);
 
         // Math outside code blocks should be converted
@@ -713,6 +1864,13 @@ Math: $x = {1}$
         assert!(output.contains("```javascript\nlet price = $100;\n```"));
     }
 
+    #[test]
+    fn test_math_ignores_inline_code() {
+        let input = "Write `$x = {1}$` or `$$y$$` for math, e.g. $z$";
+        let output = convert_inline_math(&convert_math_blocks(input));
+        assert_eq!(output, "Write `$x = {1}$` or `$$y$$` for math, e.g. $$z$$");
+    }
+
     #[test]
     fn test_code_block_protection_with_multiple_blocks() {
         let input = r#"Text with $inline$ math.
@@ -720,7 +1878,8 @@ Math: $x = {1}$
 # This has $$math$$ in code
 x = $5
 ```
-More $$block$$ math here.
+More math here:
+$$block$$
 ```rust
 let formula = "$$E=mc^2$$";
 ```
@@ -739,4 +1898,272 @@ Final $a$ inline."#;
         assert!(output.contains("x = $5"));
         assert!(output.contains(r#"let formula = "$$E=mc^2$$";"#));
     }
+
+    #[test]
+    fn test_validate_accordions_wrapped_pair() {
+        let input = r#"<Accordion title="Q1">
+A1
+</Accordion>
+<Accordion title="Q2">
+A2
+</Accordion>"#;
+        let output = wrap_accordions_in_container(input);
+        assert!(validate_accordions(&output).is_empty());
+    }
+
+    #[test]
+    fn test_validate_accordions_double_wrapped() {
+        let input = r#"<Accordions>
+<Accordions>
+<Accordion title="Q1">
+A1
+</Accordion>
+</Accordions>
+</Accordions>"#;
+        let problems = validate_accordions(input);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("line 2"));
+        assert!(problems[0].contains("nested directly"));
+    }
+
+    #[test]
+    fn test_validate_accordions_orphan() {
+        let input = r#"Intro
+
+<Accordion title="Q1">
+A1
+</Accordion>"#;
+        let problems = validate_accordions(input);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("line 3"));
+        assert!(problems[0].contains("outside"));
+    }
+
+    #[test]
+    fn test_validate_mdx_tags_mismatch() {
+        let content = "<Table>\n<List>\nitem\n</Table>\n</List>";
+        assert_eq!(
+            validate_mdx_tags(content),
+            vec![
+                "line 4: expected </List> (opened on line 2), found </Table>".to_string(),
+                "line 5: unmatched </List>".to_string(),
+            ]
+        );
+        assert_eq!(
+            validate_mdx_tags("```\n<pre>\n```\n<div>\n<Callout>"),
+            vec![
+                "line 4: unclosed <div>".to_string(),
+                "line 5: unclosed <Callout>".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_mdx_tags_nested_ok() {
+        let content = r#"<Accordions>
+<Accordion title="Q">
+<div style={{textAlign: "center"}}><img src="a.png" /><br /></div>
+See <https://example.com> and `<span>`
+</Accordion>
+</Accordions>
+
+```html
+<table>
+```"#;
+        assert!(validate_mdx_tags(content).is_empty());
+    }
+
+    #[test]
+    fn test_cleanup_blank_lines_default() {
+        assert_eq!(cleanup_blank_lines("a\n\n\n\nb", 1, false), "a\n\nb");
+        assert_eq!(
+            cleanup_blank_lines("\n\na\n\nb\n", 1, false),
+            "\n\na\n\nb\n"
+        );
+    }
+
+    #[test]
+    fn test_cleanup_blank_lines_max_two() {
+        assert_eq!(cleanup_blank_lines("a\n\n\nb", 2, false), "a\n\n\nb");
+        assert_eq!(cleanup_blank_lines("a\n\n\n\n\n\nb", 2, false), "a\n\n\nb");
+    }
+
+    #[test]
+    fn test_cleanup_blank_lines_trim() {
+        assert_eq!(
+            cleanup_blank_lines("\n\n\na\n\n\n\nb\n\n\n", 1, true),
+            "a\n\nb\n"
+        );
+        assert_eq!(cleanup_blank_lines("\n\na", 1, true), "a");
+        assert_eq!(cleanup_blank_lines("\n\n", 1, true), "");
+    }
+
+    #[test]
+    fn test_check_all_mdx_files() {
+        let root = std::env::temp_dir().join("test_check_all_mdx_files");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("2023")).unwrap();
+        let formatted = root.join("2023/formatted.mdx");
+        let unformatted = root.join("2023/unformatted.mdx");
+        fs::write(&formatted, "# Title\n\nBody").unwrap();
+        fs::write(&unformatted, "# Title\n<!-- note -->\n<br>\n").unwrap();
+
+        let config = FormatConfig::default();
+        assert_eq!(
+            check_all_mdx_files(&root, &config).unwrap(),
+            vec![unformatted.clone()]
+        );
+        // Nothing was written
+        assert_eq!(
+            fs::read_to_string(&unformatted).unwrap(),
+            "# Title\n<!-- note -->\n<br>\n"
+        );
+
+        let mut report = GenerationReport::default();
+        assert_eq!(
+            format_all_mdx_files(&root, &config, &mut report).unwrap(),
+            vec![unformatted.clone()]
+        );
+        assert_eq!(
+            fs::read_to_string(&unformatted).unwrap(),
+            "# Title\n\n<br />\n"
+        );
+        assert!(check_all_mdx_files(&root, &config).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_format_all_mdx_files_many() {
+        let root = std::env::temp_dir().join("test_format_all_mdx_files_many");
+        let _ = fs::remove_dir_all(&root);
+        let mut expected = Vec::new();
+        for i in 0..24 {
+            let dir = root.join(format!("dir{}", i % 4));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join(format!("page{}.mdx", i));
+            if i % 3 == 0 {
+                fs::write(&path, "Line<br>").unwrap();
+                expected.push(path);
+            } else {
+                fs::write(&path, "Line<br />").unwrap();
+            }
+        }
+
+        let mut report = GenerationReport::default();
+        let mut written =
+            format_all_mdx_files(&root, &FormatConfig::default(), &mut report).unwrap();
+        written.sort();
+        expected.sort();
+        assert_eq!(written, expected);
+        for path in &expected {
+            assert_eq!(fs::read_to_string(path).unwrap(), "Line<br />");
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_format_mdx_files_where_skips_excluded() {
+        let root = std::env::temp_dir().join("test_format_mdx_files_where");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("2023")).unwrap();
+        let index = root.join("2023/index.mdx");
+        let page = root.join("2023/COMP1001.mdx");
+        fs::write(&index, "Line<br>").unwrap();
+        fs::write(&page, "Line<br>").unwrap();
+
+        let mut report = GenerationReport::default();
+        let written =
+            format_mdx_files_where(&root, &FormatConfig::default(), &mut report, |path| {
+                path.file_stem().is_some_and(|stem| stem != "COMP1001")
+            })
+            .unwrap();
+        assert_eq!(written, vec![index.clone()]);
+        assert_eq!(fs::read_to_string(&index).unwrap(), "Line<br />");
+        assert_eq!(fs::read_to_string(&page).unwrap(), "Line<br>");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_format_all_mdx_files_read_error() {
+        let root = std::env::temp_dir().join("test_format_all_mdx_files_read_error");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("ok.mdx"), "Fine").unwrap();
+        // Not valid UTF-8, so reading it as a string fails
+        fs::write(root.join("bad.mdx"), [0xff, 0xfe, 0xfd]).unwrap();
+
+        let mut report = GenerationReport::default();
+        assert!(matches!(
+            format_all_mdx_files(&root, &FormatConfig::default(), &mut report),
+            Err(crate::error::FumaError::Io(_))
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_format_all_mdx_files_respects_opt_out() {
+        let root = std::env::temp_dir().join("test_format_opt_out");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let opted_out = root.join("tuned.mdx");
+        let normal = root.join("normal.mdx");
+        let tuned = "---\ntitle: Tuned\nfuma_format: false\n---\n\n<br>\n<!-- keep -->\n";
+        fs::write(&opted_out, tuned).unwrap();
+        fs::write(&normal, "---\ntitle: Normal\n---\n\n<br>").unwrap();
+        // Only a leading block counts
+        fs::write(
+            root.join("body.mdx"),
+            "<br>\n\n---\nfuma_format: false\n---\n",
+        )
+        .unwrap();
+
+        let config = FormatConfig::default();
+        let mut unformatted = check_all_mdx_files(&root, &config).unwrap();
+        unformatted.sort();
+        assert_eq!(unformatted, vec![root.join("body.mdx"), normal.clone()]);
+
+        let mut report = GenerationReport::default();
+        let written = format_all_mdx_files(&root, &config, &mut report).unwrap();
+        assert_eq!(written.len(), 2);
+        assert!(!written.contains(&opted_out));
+        assert_eq!(fs::read_to_string(&opted_out).unwrap(), tuned);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    /// Formatting must be idempotent, or `--check-format` flags pages that
+    /// were just formatted. Past culprits:
+    /// - `wrap_accordions_in_container` wrapped accordions that were already
+    ///   inside `<Accordions>` again, and dropped a trailing newline per pass
+    /// - `convert_math_blocks` turned the inline `$$x$$` produced by
+    ///   `convert_inline_math` into a display block on the next pass
+    #[test]
+    fn test_format_mdx_file_idempotent() {
+        let corpus = [
+            "# Title\n\n{{% details title=\"Q1\" %}}A1{{% /details %}}\n\n{{% details title=\"Q2\" %}}\nA2\n{{% /details %}}\n",
+            "{{% details title=\"FAQ\" %}}\n{{% details title=\"Q\" %}}A{{% /details %}}\n{{% /details %}}",
+            "<details><summary>S</summary>body</details>\ntext",
+            "<Accordions>\n<Accordion title=\"x\">\ny\n</Accordion>\n</Accordions>\n",
+            "Math $x$ and $$y$$ and\n$$\nz\n$$\n",
+            "\\(a\\) and\n\\[\nb\n\\]\n",
+            "Costs $100 and $200",
+            "> [!NOTE]\n> hi\n\n> plain",
+            "{{% notice warning %}}x{{% /notice %}}",
+            "<font color=\"red\">r</font><img src=\"a.png\" style=\"width:1px\">",
+            "Line\n\n\n\n\nLine2\n\n\n",
+            "<!-- c -->\n![b](https://img.shields.io/x)\n<https://example.com>\n",
+            "```py\nx = {1}\n```\n`$y$`\n",
+            "---\ntitle: T\n---\n\n<br>\n",
+        ];
+        let config = FormatConfig::default();
+        for input in corpus {
+            let once = format_mdx_file(input, &config);
+            let twice = format_mdx_file(&once, &config);
+            assert_eq!(twice, once, "not idempotent for {:?}", input);
+        }
+    }
 }
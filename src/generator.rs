@@ -1,80 +1,400 @@
-use crate::constants::{get_semester_title_by_folder, parse_semester_folders, SEMESTER_MAPPING};
-use crate::error::Result;
+use crate::config::{Config, CourseOrder, GeneratorConfig, ZeroCredit};
+use crate::constants::{
+    format_semester_title, order_semester_folders, parse_semester_folders, DEFAULT_BRANCH,
+    GITHUB_ORG,
+};
+use crate::error::{FumaError, Result};
+use crate::loader::SharedCategoriesConfig;
 use crate::models::{
-    Course, CourseMetadata, Frontmatter, GradeDetail, GradingItem, HourDistributionMeta, Plan,
-    SharedCategory, WorktreeData,
+    validate_frontmatter_yaml, Course, CreditValue, Frontmatter, GradeDetail, GradingItem, Plan,
+    WorktreeData,
 };
+use crate::report::GenerationReport;
 use crate::tree::{build_file_tree, tree_to_jsx};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
-/// Build YAML frontmatter for a course page using serde_yaml
-fn build_frontmatter(title: &str, course: &Course) -> String {
-    let credit = course.credit.unwrap_or(0.0);
-    let assessment_method = course
-        .assessment_method
-        .as_deref()
-        .unwrap_or("")
-        .to_string();
-    let course_nature = course.course_nature.as_deref().unwrap_or("").to_string();
-
-    let hour_distribution = if let Some(ref h) = course.hours {
-        HourDistributionMeta {
-            theory: h.theory.unwrap_or(0),
-            lab: h.lab.unwrap_or(0),
-            practice: h.practice.unwrap_or(0),
-            exercise: h.exercise.unwrap_or(0),
-            computer: h.computer.unwrap_or(0),
-            tutoring: h.tutoring.unwrap_or(0),
+/// Collect the grading items with a positive percentage from a course's grade details
+fn build_grading_scheme(course: &Course) -> Vec<GradingItem> {
+    let Some(ref details) = course.grade_details else {
+        return Vec::new();
+    };
+
+    details
+        .iter()
+        .filter_map(|detail| {
+            let percent = detail
+                .percent_text()
+                .and_then(|percent| percent.parse::<u32>().ok())
+                .unwrap_or(0);
+
+            (percent > 0).then(|| GradingItem {
+                name: detail.name.clone(),
+                percent,
+            })
+        })
+        .collect()
+}
+
+/// Render grading items as a markdown table, or `None` when there are none
+fn render_grade_table(items: &[GradingItem]) -> Option<String> {
+    if items.is_empty() {
+        return None;
+    }
+
+    let mut lines = vec!["| 项目 | 占比 |".to_string(), "| --- | --- |".to_string()];
+    for item in items {
+        lines.push(format!("| {} | {}% |", item.name, item.percent));
+    }
+    Some(lines.join("\n"))
+}
+
+/// Assemble a course page: frontmatter, the optional JSON-LD script,
+/// optional `<CourseInfo />`, extra preamble blocks, then the README body and
+/// file tree.
+fn compose_page(
+    frontmatter: &str,
+    json_ld: Option<&str>,
+    use_course_info: bool,
+    preamble: &[String],
+    content: &str,
+    filetree_content: &str,
+) -> String {
+    let mut blocks = vec![frontmatter.to_string()];
+    blocks.extend(json_ld.map(str::to_string));
+    if use_course_info {
+        blocks.push("<CourseInfo />".to_string());
+    }
+    blocks.extend(preamble.iter().cloned());
+    blocks.push(content.to_string());
+    format!("{}{}", blocks.join("\n\n"), filetree_content)
+}
+
+/// Slugify a heading the way Fumadocs (github-slugger) assigns heading ids:
+/// lowercase, drop punctuation, spaces become hyphens. CJK text is kept.
+fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render a table of contents for the level 2–3 headings of `body`, linking
+/// to their Fumadocs anchors. Returns `None` with fewer than `min_headings`.
+fn render_toc(body: &str, min_headings: usize) -> Option<String> {
+    let link_re = regex::Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut entries = Vec::new();
+    let mut in_code = false;
+
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
         }
+        let (depth, text) = match line {
+            _ if in_code => continue,
+            l if l.starts_with("## ") => (0, &l[3..]),
+            l if l.starts_with("### ") => (1, &l[4..]),
+            _ => continue,
+        };
+        let text = link_re.replace_all(text.trim_end_matches('#').trim(), "$1");
+        let text = text.replace(['*', '`'], "");
+
+        // Repeated headings get -1, -2, ... like github-slugger
+        let base = heading_slug(&text);
+        let count = seen.entry(base.clone()).or_insert(0);
+        let slug = match *count {
+            0 => base,
+            n => format!("{}-{}", base, n),
+        };
+        *count += 1;
+
+        entries.push(format!(
+            "{}- [{}](#{})",
+            "  ".repeat(depth),
+            text.trim(),
+            slug
+        ));
+    }
+
+    if entries.is_empty() || entries.len() < min_headings {
+        return None;
+    }
+    Some(entries.join("\n"))
+}
+
+/// Build the blocks inserted between `<CourseInfo />` and the README body
+/// of a course page under `year/major_code`
+fn build_preamble(
+    course: &Course,
+    body: &str,
+    config: &GeneratorConfig,
+    year: &str,
+    major_code: &str,
+) -> Vec<String> {
+    let mut preamble: Vec<String> = config
+        .notices
+        .iter()
+        .filter(|notice| notice.applies_to(year, major_code))
+        .map(|notice| notice.markdown.trim().to_string())
+        .collect();
+    if config.grade_table {
+        preamble.extend(render_grade_table(&build_grading_scheme(course)));
+    }
+    if let Some(min_headings) = config.toc_min_headings {
+        preamble.extend(render_toc(body, min_headings));
+    }
+    preamble
+}
+
+/// Render links to the neighboring courses of a semester. Returns `None`
+/// when the course has no neighbors.
+fn render_course_nav(
+    prev: Option<&Course>,
+    next: Option<&Course>,
+    href: impl Fn(&Course) -> String,
+) -> Option<String> {
+    let mut links = Vec::new();
+    if let Some(course) = prev {
+        links.push(format!("上一门：[{}]({})", course.name, href(course)));
+    }
+    if let Some(course) = next {
+        links.push(format!("下一门：[{}]({})", course.name, href(course)));
+    }
+    if links.is_empty() {
+        None
     } else {
-        HourDistributionMeta {
-            theory: 0,
-            lab: 0,
-            practice: 0,
-            exercise: 0,
-            computer: 0,
-            tutoring: 0,
+        Some(links.join(" | "))
+    }
+}
+
+/// Render a schema.org `Course` JSON-LD script for a course page.
+///
+/// The JSON is passed to `dangerouslySetInnerHTML` as a string literal so MDX
+/// doesn't parse its braces; `<`, `>` and `&` are escaped so the content can
+/// never close the script tag.
+fn render_json_ld(course: &Course, description: Option<&str>, provider: &str) -> String {
+    let mut json_ld = serde_json::json!({
+        "@context": "https://schema.org",
+        "@type": "Course",
+        "name": course.name,
+        "courseCode": course.repo_id,
+    });
+    if let Some(description) = description {
+        json_ld["description"] = serde_json::json!(description);
+    }
+    if !provider.is_empty() {
+        json_ld["provider"] = serde_json::json!({
+            "@type": "Organization",
+            "name": provider,
+        });
+    }
+
+    let literal = serde_json::to_string(&json_ld.to_string())
+        .unwrap_or_else(|_| "\"{}\"".to_string())
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026");
+    format!(
+        "<script type=\"application/ld+json\" dangerouslySetInnerHTML={{{{ __html: {} }}}} />",
+        literal
+    )
+}
+
+/// Parse a `tag.txt` body into tags: one per line, trimmed and deduplicated
+fn parse_tag_txt(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty() && seen.insert(*tag))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Collect frontmatter tags for a repo from its enabled tag sources
+fn collect_tags(course: &Course, repos_dir: &Path, config: &Config) -> Result<Vec<String>> {
+    let mut tags = Vec::new();
+
+    if config.generator.tag_txt_tags {
+        let tag_path = config
+            .cache
+            .layout
+            .repo_file(repos_dir, &course.repo_id, "tag.txt");
+        if tag_path.exists() {
+            tags.extend(parse_tag_txt(&fs::read_to_string(&tag_path)?));
         }
-    };
+    }
 
-    let grading_scheme = if let Some(ref details) = course.grade_details {
-        details
-            .iter()
-            .filter_map(|detail| {
-                let percent = if let Some(ref percent_str) = detail.percent {
-                    percent_str
-                        .trim_end_matches('%')
-                        .parse::<u32>()
-                        .unwrap_or(0)
-                } else {
-                    0
-                };
+    if config.generator.assessment_tag {
+        let tag = course
+            .assessment_method
+            .as_deref()
+            .and_then(normalize_assessment_method);
+        if let Some(tag) = tag.filter(|tag| !tags.contains(tag)) {
+            tags.push(tag);
+        }
+    }
 
-                (percent > 0).then(|| GradingItem {
-                    name: detail.name.clone(),
-                    percent,
-                })
-            })
-            .collect()
+    Ok(tags)
+}
+
+/// Normalize an assessment method to a tag: `考试` for exams, `考查` for
+/// coursework-assessed courses, `合格制` for pass/fail; other values are
+/// kept as written.
+fn normalize_assessment_method(method: &str) -> Option<String> {
+    let method = method.trim();
+    if method.is_empty() {
+        return None;
+    }
+
+    let lower = method.to_lowercase();
+    let tag = if method.contains("考试") || lower == "exam" {
+        "考试"
+    } else if method.contains("考查") || lower == "assessment" {
+        "考查"
+    } else if method.contains("合格") || lower.replace(' ', "") == "pass/fail" {
+        "合格制"
     } else {
-        Vec::new()
+        method
     };
+    Some(tag.to_string())
+}
 
-    let frontmatter = Frontmatter {
-        title: title.to_string(),
-        description: String::new(),
-        course: CourseMetadata {
-            credit,
-            assessment_method,
-            course_nature,
-            hour_distribution,
-            grading_scheme,
-        },
+/// Build YAML frontmatter for a course page using serde_yaml.
+///
+/// `extra_fields` names unmodeled plan TOML fields copied into `course`
+/// when the course has them.
+fn build_frontmatter(
+    title: &str,
+    course: &Course,
+    tags: Vec<String>,
+    description: Option<String>,
+    zero_credit: ZeroCredit,
+    extra_fields: &[String],
+) -> String {
+    let extra = extra_fields
+        .iter()
+        .filter_map(|field| Some((field.clone(), course.extra.get(field)?.clone())))
+        .collect();
+    Frontmatter::builder(title)
+        .english_title(course.english_name.clone())
+        .description(description)
+        .tags(tags)
+        .credit(course.credit)
+        .zero_credit(zero_credit)
+        .assessment_method(course.assessment_method.clone())
+        .course_nature(course.course_nature.clone())
+        .hours(course.hours.clone())
+        .grading_scheme(build_grading_scheme(course))
+        .extra(extra)
+        .build()
+        .to_yaml()
+}
+
+/// Branch that download links for `repo_id` point at: `tree.download_branch`
+/// if set, else the default branch recorded by the fetcher in
+/// `{repo}.branch`, or `main`.
+fn repo_branch(repos_dir: &Path, repo_id: &str, config: &Config) -> String {
+    if let Some(ref branch) = config.tree.download_branch {
+        return branch.clone();
+    }
+    let branch_path = config.cache.layout.repo_file(repos_dir, repo_id, "branch");
+    fs::read_to_string(branch_path)
+        .ok()
+        .map(|branch| branch.trim().to_string())
+        .filter(|branch| !branch.is_empty())
+        .unwrap_or_else(|| DEFAULT_BRANCH.to_string())
+}
+
+/// Read and parse a cached worktree.json.
+///
+/// If it doesn't parse and a re-fetch source is configured, the file is
+/// fetched again once, rewritten, and parsed before giving up.
+async fn load_worktree(json_path: &Path, repo_id: &str, config: &Config) -> Result<WorktreeData> {
+    let content = fs::read_to_string(json_path)?;
+    let err = match serde_json::from_str(&content) {
+        Ok(worktree) => return Ok(worktree),
+        Err(e) => e,
+    };
+    let Some(ref source) = config.cache.refetch_source else {
+        return Err(err.into());
     };
 
-    frontmatter.to_yaml()
+    eprintln!(
+        "Warning: {} is corrupt ({}), re-fetching from {}",
+        json_path.display(),
+        err,
+        source.describe()
+    );
+    let fresh = source.fetch_worktree_json(GITHUB_ORG, repo_id).await?;
+    fs::write(json_path, &fresh)?;
+    Ok(serde_json::from_str(&fresh)?)
+}
+
+/// In strict mode, fail if `frontmatter` doesn't parse back with `title` intact
+fn ensure_valid_frontmatter(
+    frontmatter: &str,
+    title: &str,
+    page: &str,
+    config: &GeneratorConfig,
+) -> Result<()> {
+    if !config.strict_frontmatter {
+        return Ok(());
+    }
+    validate_frontmatter_yaml(frontmatter, title).map_err(|reason| FumaError::InvalidFrontmatter {
+        page: page.to_string(),
+        reason,
+    })
+}
+
+/// Split a cached README into the `description` of its own frontmatter
+/// block (if any) and the page body.
+///
+/// The generated frontmatter replaces the README's, so keeping it would put
+/// two blocks on the page. Without frontmatter the first two lines (title
+/// heading and blank line) are dropped, as before.
+fn split_readme(readme: &str) -> (Option<String>, String) {
+    let mut lines = readme.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return (None, readme.lines().skip(2).collect::<Vec<_>>().join("\n"));
+    }
+
+    let mut block = Vec::new();
+    let mut closed = false;
+    for line in lines.by_ref() {
+        if line.trim_end() == "---" {
+            closed = true;
+            break;
+        }
+        block.push(line);
+    }
+    if !closed {
+        return (None, readme.lines().skip(2).collect::<Vec<_>>().join("\n"));
+    }
+
+    let description = serde_yaml::from_str::<serde_yaml::Value>(&block.join("\n"))
+        .ok()
+        .and_then(|v| v.get("description")?.as_str().map(str::to_string))
+        .filter(|d| !d.trim().is_empty());
+
+    // Drop the title heading that usually follows, plus surrounding blanks
+    let mut rest: Vec<&str> = lines.skip_while(|l| l.trim().is_empty()).collect();
+    if rest.first().is_some_and(|l| l.starts_with("# ")) {
+        rest.remove(0);
+        while rest.first().is_some_and(|l| l.trim().is_empty()) {
+            rest.remove(0);
+        }
+    }
+    (description, rest.join("\n"))
 }
 
 fn title_from_mdx(mdx_content: &str, fallback: &str) -> String {
@@ -99,33 +419,123 @@ fn title_from_mdx(mdx_content: &str, fallback: &str) -> String {
     fallback.to_string()
 }
 
+/// Build a site-absolute docs link, honoring the configured `base_href`.
+fn docs_href(base_href: &str, segments: &[&str]) -> String {
+    format!(
+        "{}/docs/{}",
+        base_href.trim_end_matches('/'),
+        segments.join("/")
+    )
+}
+
+/// Sum the credits of a plan's published courses, counting each repo once.
+///
+/// Returns the total and the number of courses whose credit is unknown.
+fn total_credits(plan: &Plan, repos_set: &HashSet<String>) -> (f64, usize) {
+    let mut seen = HashSet::new();
+    let mut total = 0.0;
+    let mut unknown = 0;
+    for course in &plan.courses {
+        if (!repos_set.is_empty() && !repos_set.contains(&course.repo_id))
+            || !seen.insert(course.repo_id.as_str())
+        {
+            continue;
+        }
+        match course.credit {
+            Some(credit) => total += credit,
+            None => unknown += 1,
+        }
+    }
+    // Round away float noise such as 0.1 + 0.2
+    ((total * 10.0).round() / 10.0, unknown)
+}
+
+/// Write a folder `meta.json` whose `pages` list `index` first, then `slugs`
+fn write_folder_meta(dir: &Path, title: &str, slugs: &[String]) -> Result<()> {
+    let pages: Vec<&str> = std::iter::once("index")
+        .chain(slugs.iter().map(String::as_str))
+        .collect();
+    let meta = serde_json::json!({
+        "title": title,
+        "pages": pages,
+    });
+    fs::write(dir.join("meta.json"), serde_json::to_string_pretty(&meta)?)?;
+    Ok(())
+}
+
+/// Write `search.mdx`: every course once, sorted by title, with a link to it
+fn write_search_page(docs_dir: &Path, catalog: HashMap<String, (String, String)>) -> Result<()> {
+    let mut entries: Vec<(String, String, String)> = catalog
+        .into_iter()
+        .map(|(repo_id, (title, href))| (title, repo_id, href))
+        .collect();
+    entries.sort();
+
+    let mut lines = vec![
+        "---".to_string(),
+        "title: 课程索引".to_string(),
+        "---".to_string(),
+        "".to_string(),
+    ];
+    for (title, repo_id, href) in entries {
+        lines.push(format!("- [{}]({}) `{}`", title, href, repo_id));
+    }
+    fs::write(docs_dir.join("search.mdx"), lines.join("\n"))?;
+    Ok(())
+}
+
 fn minimal_course(repo_id: &str, name: &str, grade_details: Option<Vec<GradeDetail>>) -> Course {
     Course {
         repo_id: repo_id.to_string(),
         name: name.to_string(),
+        english_name: None,
         credit: None,
         assessment_method: None,
         course_nature: None,
         recommended_semester: None,
         hours: None,
         grade_details,
+        extra: HashMap::new(),
     }
 }
 
-/// Generate all course pages and index pages
+/// Generate all course pages and index pages.
+///
+/// Returns a report of the issues noticed along the way, such as repos with
+/// no cached README.
 pub async fn generate_course_pages(
     plans: &[Plan],
-    shared_categories: &[SharedCategory],
-    no_course_info_repo_ids: &HashSet<String>,
+    shared_categories_config: &SharedCategoriesConfig,
     grades_summary: &HashMap<String, HashMap<String, Vec<GradeDetail>>>,
     repos_dir: &Path,
     docs_dir: &Path,
     repos_set: &HashSet<String>,
-) -> Result<()> {
+    config: &Config,
+) -> Result<GenerationReport> {
+    let mut report = GenerationReport::default();
+    let shared_categories = &shared_categories_config.categories;
+    let no_course_info_repo_ids = &shared_categories_config.no_course_info_repo_ids;
+    let base_href = config.generator.base_href.as_str();
+    let semesters = &config.generator.semesters;
+
+    let partial = config.generator.changed_repo_ids.is_some();
+    // Every course page across plans, keyed by repo ID: (title, href)
+    let mut catalog: HashMap<String, (String, String)> = HashMap::new();
     let mut years: HashSet<String> = HashSet::new();
     let mut majors_by_year: HashMap<String, Vec<(String, String)>> = HashMap::new();
 
     for plan in plans {
+        if config
+            .generator
+            .is_major_blocked(&plan.year, &plan.major_code)
+        {
+            println!(
+                "Skipping blocklisted major {}/{}",
+                plan.year, plan.major_code
+            );
+            continue;
+        }
+
         years.insert(plan.year.clone());
 
         majors_by_year
@@ -138,6 +548,36 @@ pub async fn generate_course_pages(
 
         // Track courses by semester for this major
         let mut courses_by_semester: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        // Semester folders and category IDs whose courses were regenerated;
+        // with `changed_repo_ids` only their indexes are rewritten
+        let mut touched_folders: HashSet<String> = HashSet::new();
+
+        // Pages are written one course at a time, so the neighbors used for
+        // prev/next links are collected up front in plan order
+        let mut semester_order: HashMap<&str, Vec<&Course>> = HashMap::new();
+        if config.generator.course_nav && !config.generator.index_only {
+            for course in &plan.courses {
+                if (!repos_set.is_empty() && !repos_set.contains(&course.repo_id))
+                    || !config
+                        .cache
+                        .layout
+                        .repo_file(repos_dir, &course.repo_id, "mdx")
+                        .exists()
+                {
+                    continue;
+                }
+                let folders = course
+                    .recommended_semester
+                    .as_deref()
+                    .map(|s| {
+                        parse_semester_folders(semesters, s, config.generator.numeric_semesters)
+                    })
+                    .unwrap_or_default();
+                for (folder, _title) in folders {
+                    semester_order.entry(folder).or_default().push(course);
+                }
+            }
+        }
 
         // Process each course
         for course in &plan.courses {
@@ -146,28 +586,63 @@ pub async fn generate_course_pages(
                 continue;
             }
 
-            let mdx_path = repos_dir.join(format!("{}.mdx", course.repo_id));
-            let json_path = repos_dir.join(format!("{}.json", course.repo_id));
+            // Determine target directories based on semester (supports multi-semester values)
+            let semester_folders = course
+                .recommended_semester
+                .as_deref()
+                .map(|s| parse_semester_folders(semesters, s, config.generator.numeric_semesters))
+                .unwrap_or_default();
+
+            if !config.generator.should_regenerate(&course.repo_id) {
+                // Only list pages a previous run already wrote; never touch them
+                let page_path = major_dir.join(format!("{}.mdx", course.repo_id));
+                if semester_folders.is_empty() && page_path.exists() {
+                    catalog.entry(course.repo_id.clone()).or_insert_with(|| {
+                        let href =
+                            docs_href(base_href, &[&plan.year, &plan.major_code, &course.repo_id]);
+                        (course.name.clone(), href)
+                    });
+                }
+                for (folder, _title) in semester_folders {
+                    let page_path = major_dir
+                        .join(folder)
+                        .join(format!("{}.mdx", course.repo_id));
+                    if page_path.exists() {
+                        courses_by_semester
+                            .entry(folder.to_string())
+                            .or_default()
+                            .push((course.repo_id.clone(), course.name.clone()));
+                    }
+                }
+                continue;
+            }
+
+            let mdx_path = config
+                .cache
+                .layout
+                .repo_file(repos_dir, &course.repo_id, "mdx");
+            let json_path = config
+                .cache
+                .layout
+                .repo_file(repos_dir, &course.repo_id, "json");
 
             if !mdx_path.exists() {
+                report.missing_readmes.insert(course.repo_id.clone());
                 continue;
             }
 
             // Read README content (skip first 2 lines which are title)
             let readme_content = fs::read_to_string(&mdx_path)?;
-            let content_lines: Vec<&str> = readme_content.lines().skip(2).collect();
-            let content = content_lines.join("\n");
-
-            // Determine target directories based on semester (supports multi-semester values)
-            let semester_folders = course
-                .recommended_semester
-                .as_deref()
-                .map(parse_semester_folders)
-                .unwrap_or_default();
+            let (description, content) = split_readme(&readme_content);
 
             let mut target_dirs = Vec::new();
             if semester_folders.is_empty() {
-                target_dirs.push(major_dir.clone());
+                target_dirs.push((major_dir.clone(), None));
+                catalog.entry(course.repo_id.clone()).or_insert_with(|| {
+                    let href =
+                        docs_href(base_href, &[&plan.year, &plan.major_code, &course.repo_id]);
+                    (course.name.clone(), href)
+                });
             } else {
                 for (folder, _title) in semester_folders {
                     let sem_dir = major_dir.join(folder);
@@ -176,15 +651,16 @@ pub async fn generate_course_pages(
                         .entry(folder.to_string())
                         .or_default()
                         .push((course.repo_id.clone(), course.name.clone()));
-                    target_dirs.push(sem_dir);
+                    touched_folders.insert(folder.to_string());
+                    target_dirs.push((sem_dir, Some(folder)));
                 }
             }
 
             // Generate file tree from worktree.json
             let filetree_content = if json_path.exists() {
-                let json_content = fs::read_to_string(&json_path)?;
-                let worktree: WorktreeData = serde_json::from_str(&json_content)?;
-                let tree = build_file_tree(&worktree, &course.repo_id);
+                let worktree = load_worktree(&json_path, &course.repo_id, config).await?;
+                let branch = repo_branch(repos_dir, &course.repo_id, config);
+                let tree = build_file_tree(&worktree, &course.repo_id, &branch, &config.tree);
                 let jsx = tree_to_jsx(&tree, 1);
                 format!(
                     "\n\n## 资源下载\n\n<Files url=\"https://open.osa.moe/openauto/{}\">\n{}\n</Files>",
@@ -195,36 +671,95 @@ pub async fn generate_course_pages(
             };
 
             // Build frontmatter
-            let frontmatter = build_frontmatter(&course.name, course);
+            let tags = collect_tags(course, repos_dir, config)?;
+            let json_ld = config.generator.json_ld.then(|| {
+                render_json_ld(
+                    course,
+                    description.as_deref(),
+                    &config.generator.json_ld_provider,
+                )
+            });
+            let frontmatter = build_frontmatter(
+                &course.name,
+                course,
+                tags,
+                description,
+                config.generator.zero_credit,
+                &config.generator.course_extra_fields,
+            );
+            ensure_valid_frontmatter(
+                &frontmatter,
+                &course.name,
+                &course.repo_id,
+                &config.generator,
+            )?;
 
             // Write course page
-            let page_content = format!(
-                "{}\n\n<CourseInfo />\n\n{}{}",
-                frontmatter, content, filetree_content
+            let preamble = build_preamble(
+                course,
+                &content,
+                &config.generator,
+                &plan.year,
+                &plan.major_code,
+            );
+            let page_content = compose_page(
+                &frontmatter,
+                json_ld.as_deref(),
+                true,
+                &preamble,
+                &content,
+                &filetree_content,
             );
-            for target_dir in target_dirs {
-                fs::write(
-                    target_dir.join(format!("{}.mdx", course.repo_id)),
-                    &page_content,
-                )?;
+            for (target_dir, folder) in target_dirs {
+                let siblings =
+                    folder.and_then(|f| semester_order.get(f).map(|courses| (f, courses)));
+                let nav = siblings.and_then(|(folder, courses)| {
+                    let pos = courses.iter().position(|c| c.repo_id == course.repo_id)?;
+                    let prev = pos.checked_sub(1).and_then(|i| courses.get(i)).copied();
+                    let next = courses.get(pos + 1).copied();
+                    render_course_nav(prev, next, |c| {
+                        docs_href(
+                            base_href,
+                            &[&plan.year, &plan.major_code, folder, &c.repo_id],
+                        )
+                    })
+                });
+
+                let page_path = target_dir.join(format!("{}.mdx", course.repo_id));
+                match nav {
+                    Some(nav) => fs::write(page_path, format!("{}\n\n{}", page_content, nav))?,
+                    None => fs::write(page_path, &page_content)?,
+                }
             }
         }
 
         // Keep semester pages and navigation in semantic order
-        let ordered_semester_folders: Vec<String> = SEMESTER_MAPPING
-            .iter()
-            .filter_map(|(_, folder, _)| {
-                courses_by_semester
-                    .contains_key(*folder)
-                    .then_some((*folder).to_string())
-            })
-            .collect();
+        let semester_folder_set: HashSet<String> = courses_by_semester.keys().cloned().collect();
+        let ordered_semester_folders = order_semester_folders(
+            semesters,
+            &semester_folder_set,
+            &config.generator.semester_order,
+        );
+
+        for folder in &ordered_semester_folders {
+            for (slug, name) in courses_by_semester.get(folder).into_iter().flatten() {
+                catalog.entry(slug.clone()).or_insert_with(|| {
+                    let href = docs_href(base_href, &[&plan.year, &plan.major_code, folder, slug]);
+                    (name.clone(), href)
+                });
+            }
+        }
 
         // Generate semester index pages
         for folder in &ordered_semester_folders {
+            if partial && !touched_folders.contains(folder) {
+                continue;
+            }
             let courses = courses_by_semester.get(folder).cloned().unwrap_or_default();
             let sem_dir = major_dir.join(folder);
-            let sem_title = get_semester_title_by_folder(folder).unwrap_or(folder.as_str());
+            let sem_title =
+                format_semester_title(semesters, folder, &config.generator.semester_title_template)
+                    .unwrap_or_else(|| folder.clone());
 
             let mut cards = vec![
                 "---".to_string(),
@@ -236,13 +771,33 @@ pub async fn generate_course_pages(
 
             for (slug, name) in &courses {
                 cards.push(format!(
-                    "  <Card title=\"{}\" href=\"/docs/{}/{}/{}/{}\" />",
-                    name, plan.year, plan.major_code, folder, slug
+                    "  <Card title=\"{}\" href=\"{}\" />",
+                    name,
+                    docs_href(base_href, &[&plan.year, &plan.major_code, folder, slug])
                 ));
             }
             cards.push("</Cards>".to_string());
 
             fs::write(sem_dir.join("index.mdx"), cards.join("\n"))?;
+
+            if config.generator.folder_meta {
+                let mut slugs: Vec<String> = courses.iter().map(|(slug, _)| slug.clone()).collect();
+                if config.generator.folder_meta_order == CourseOrder::Credit {
+                    let credit_of = |slug: &String| {
+                        plan.courses
+                            .iter()
+                            .find(|c| &c.repo_id == slug)
+                            .and_then(|c| c.credit)
+                    };
+                    // Stable sort keeps plan order for ties; unknown credits sort last
+                    slugs.sort_by(|a, b| {
+                        credit_of(b)
+                            .partial_cmp(&credit_of(a))
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    });
+                }
+                write_folder_meta(&sem_dir, &sem_title, &slugs)?;
+            }
         }
 
         // Shared categories
@@ -255,27 +810,43 @@ pub async fn generate_course_pages(
 
             for repo_id in &cat.repo_ids {
                 if !repos_set.is_empty() && !repos_set.contains(repo_id) {
+                    report
+                        .dangling_cards
+                        .insert(format!("{}/{}", cat.id, repo_id));
+                    continue;
+                }
+
+                if !config.generator.should_regenerate(repo_id) {
+                    let page_path = cat_dir.join(format!("{}.mdx", repo_id));
+                    if page_path.exists() {
+                        let title = title_from_mdx(&fs::read_to_string(&page_path)?, repo_id);
+                        category_courses.push((repo_id.clone(), title));
+                    }
                     continue;
                 }
 
-                let mdx_path = repos_dir.join(format!("{}.mdx", repo_id));
-                let json_path = repos_dir.join(format!("{}.json", repo_id));
+                let mdx_path = config.cache.layout.repo_file(repos_dir, repo_id, "mdx");
+                let json_path = config.cache.layout.repo_file(repos_dir, repo_id, "json");
 
                 if !mdx_path.exists() {
+                    report.missing_readmes.insert(repo_id.clone());
+                    report
+                        .dangling_cards
+                        .insert(format!("{}/{}", cat.id, repo_id));
                     continue;
                 }
 
                 let readme_content = fs::read_to_string(&mdx_path)?;
                 let title = title_from_mdx(&readme_content, repo_id);
                 category_courses.push((repo_id.clone(), title.clone()));
+                touched_folders.insert(cat.id.clone());
 
-                let content_lines: Vec<&str> = readme_content.lines().skip(2).collect();
-                let content = content_lines.join("\n");
+                let (description, content) = split_readme(&readme_content);
 
                 let filetree_content = if json_path.exists() {
-                    let json_content = fs::read_to_string(&json_path)?;
-                    let worktree: WorktreeData = serde_json::from_str(&json_content)?;
-                    let tree = build_file_tree(&worktree, repo_id);
+                    let worktree = load_worktree(&json_path, repo_id, config).await?;
+                    let branch = repo_branch(repos_dir, repo_id, config);
+                    let tree = build_file_tree(&worktree, repo_id, &branch, &config.tree);
                     let jsx = tree_to_jsx(&tree, 1);
                     format!(
                         "\n\n## 资源下载\n\n<Files url=\"https://open.osa.moe/openauto/{}\">\n{}\n</Files>",
@@ -290,18 +861,54 @@ pub async fn generate_course_pages(
                     .and_then(|m| m.get("default"))
                     .cloned();
                 let course = minimal_course(repo_id, &title, grade_details);
-                let frontmatter = build_frontmatter(&title, &course);
+                let tags = collect_tags(&course, repos_dir, config)?;
+                let json_ld = config.generator.json_ld.then(|| {
+                    render_json_ld(
+                        &course,
+                        description.as_deref(),
+                        &config.generator.json_ld_provider,
+                    )
+                });
+                let frontmatter = build_frontmatter(
+                    &title,
+                    &course,
+                    tags,
+                    description,
+                    config.generator.zero_credit,
+                    &config.generator.course_extra_fields,
+                );
+                ensure_valid_frontmatter(&frontmatter, &title, repo_id, &config.generator)?;
                 let use_course_info = !no_course_info_repo_ids.contains(repo_id);
-                let page_content = if use_course_info {
-                    format!("{}\n\n<CourseInfo />\n\n{}{}", frontmatter, content, filetree_content)
-                } else {
-                    format!("{}\n\n{}{}", frontmatter, content, filetree_content)
-                };
+                let preamble = build_preamble(
+                    &course,
+                    &content,
+                    &config.generator,
+                    &plan.year,
+                    &plan.major_code,
+                );
+                let page_content = compose_page(
+                    &frontmatter,
+                    json_ld.as_deref(),
+                    use_course_info,
+                    &preamble,
+                    &content,
+                    &filetree_content,
+                );
                 fs::write(cat_dir.join(format!("{}.mdx", repo_id)), &page_content)?;
             }
 
+            for (slug, name) in &category_courses {
+                catalog.entry(slug.clone()).or_insert_with(|| {
+                    let href = docs_href(base_href, &[&plan.year, &plan.major_code, &cat.id, slug]);
+                    (name.clone(), href)
+                });
+            }
+
             if !category_courses.is_empty() {
                 category_pages.push(cat.id.clone());
+                if partial && !touched_folders.contains(&cat.id) {
+                    continue;
+                }
 
                 let mut cards = vec![
                     "---".to_string(),
@@ -312,27 +919,49 @@ pub async fn generate_course_pages(
                 ];
                 for (slug, name) in &category_courses {
                     cards.push(format!(
-                        "  <Card title=\"{}\" href=\"/docs/{}/{}/{}/{}\" />",
-                        name, plan.year, plan.major_code, cat.id, slug
+                        "  <Card title=\"{}\" href=\"{}\" />",
+                        name,
+                        docs_href(base_href, &[&plan.year, &plan.major_code, &cat.id, slug])
                     ));
                 }
                 cards.push("</Cards>".to_string());
                 fs::write(cat_dir.join("index.mdx"), cards.join("\n"))?;
+
+                if config.generator.folder_meta {
+                    let slugs: Vec<String> = category_courses
+                        .iter()
+                        .map(|(slug, _)| slug.clone())
+                        .collect();
+                    write_folder_meta(&cat_dir, &cat.title, &slugs)?;
+                }
             }
         }
 
+        if partial && touched_folders.is_empty() {
+            continue;
+        }
+
         // Write major metadata
         let pages: Vec<String> = std::iter::once("...".to_string())
             .chain(ordered_semester_folders.iter().cloned())
             .chain(category_pages.iter().cloned())
             .collect();
 
-        let major_meta = serde_json::json!({
+        let mut major_meta = serde_json::json!({
             "title": plan.major_name,
             "root": true,
             "defaultOpen": true,
             "pages": pages,
         });
+        let credits = config
+            .generator
+            .major_credits
+            .then(|| total_credits(plan, repos_set));
+        if let Some((total, unknown)) = credits {
+            // A whole total is written as an integer, like frontmatter credits
+            major_meta["totalCredits"] = serde_json::json!(CreditValue::Value(total));
+            major_meta["unknownCreditCourses"] = serde_json::json!(unknown);
+        }
         fs::write(
             major_dir.join("meta.json"),
             serde_json::to_string_pretty(&major_meta)?,
@@ -344,21 +973,33 @@ pub async fn generate_course_pages(
             "title: 目录".to_string(),
             "---".to_string(),
             "".to_string(),
-            "<Cards>".to_string(),
         ];
+        if let Some((total, unknown)) = credits {
+            let mut line = format!("总学分：{}", total);
+            if unknown > 0 {
+                line.push_str(&format!("（另有 {} 门课程学分未知）", unknown));
+            }
+            major_index.push(line);
+            major_index.push("".to_string());
+        }
+        major_index.push("<Cards>".to_string());
 
         for folder in &ordered_semester_folders {
-            let title = get_semester_title_by_folder(folder).unwrap_or(folder.as_str());
+            let title =
+                format_semester_title(semesters, folder, &config.generator.semester_title_template)
+                    .unwrap_or_else(|| folder.clone());
             major_index.push(format!(
-                "  <Card title=\"{}\" href=\"/docs/{}/{}/{}\" />",
-                title, plan.year, plan.major_code, folder
+                "  <Card title=\"{}\" href=\"{}\" />",
+                title,
+                docs_href(base_href, &[&plan.year, &plan.major_code, folder])
             ));
         }
         for cat in shared_categories {
             if category_pages.contains(&cat.id) {
                 major_index.push(format!(
-                    "  <Card title=\"{}\" href=\"/docs/{}/{}/{}\" />",
-                    cat.title, plan.year, plan.major_code, cat.id
+                    "  <Card title=\"{}\" href=\"{}\" />",
+                    cat.title,
+                    docs_href(base_href, &[&plan.year, &plan.major_code, &cat.id])
                 ));
             }
         }
@@ -367,6 +1008,15 @@ pub async fn generate_course_pages(
         fs::write(major_dir.join("index.mdx"), major_index.join("\n"))?;
     }
 
+    if config.generator.search_page {
+        write_search_page(docs_dir, catalog)?;
+    }
+
+    // Year indexes list majors only, so changed courses never affect them
+    if partial {
+        return Ok(report);
+    }
+
     // Generate year index pages in sorted order
     let mut year_list: Vec<String> = years.into_iter().collect();
     year_list.sort();
@@ -390,8 +1040,9 @@ pub async fn generate_course_pages(
 
             for (code, name) in majors {
                 year_index.push(format!(
-                    "  <Card title=\"{}\" href=\"/docs/{}/{}\" />",
-                    name, year, code
+                    "  <Card title=\"{}\" href=\"{}\" />",
+                    name,
+                    docs_href(base_href, &[year, code])
                 ));
             }
             year_index.push("</Cards>".to_string());
@@ -400,5 +1051,1233 @@ pub async fn generate_course_pages(
         }
     }
 
-    Ok(())
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Notice, NoticeScope, ReposLayout};
+    use crate::models::SharedCategory;
+    use std::path::PathBuf;
+
+    fn test_course(repo_id: &str, name: &str, semester: Option<&str>) -> Course {
+        Course {
+            recommended_semester: semester.map(str::to_string),
+            ..minimal_course(repo_id, name, None)
+        }
+    }
+
+    fn test_plan(year: &str, major_code: &str, courses: Vec<Course>) -> Plan {
+        Plan {
+            year: year.to_string(),
+            major_code: major_code.to_string(),
+            major_name: format!("{} Major", major_code),
+            courses,
+        }
+    }
+
+    fn no_shared_categories() -> SharedCategoriesConfig {
+        SharedCategoriesConfig {
+            categories: Vec::new(),
+            no_course_info_repo_ids: HashSet::new(),
+        }
+    }
+
+    /// Create a fresh temp workspace with a `repos/` dir holding README stubs.
+    fn setup_workspace(name: &str, repo_ids: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("repos")).unwrap();
+        for repo_id in repo_ids {
+            fs::write(
+                root.join("repos").join(format!("{}.mdx", repo_id)),
+                format!("# {} - {} Title\n\nBody of {}\n", repo_id, repo_id, repo_id),
+            )
+            .unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_repo_branch_reads_fetched_default_branch() {
+        let repos_dir = std::env::temp_dir().join("test_repo_branch");
+        let _ = fs::remove_dir_all(&repos_dir);
+        fs::create_dir_all(&repos_dir).unwrap();
+        fs::write(repos_dir.join("COMP1001.branch"), "master\n").unwrap();
+
+        let config = Config::default();
+        assert_eq!(repo_branch(&repos_dir, "COMP1001", &config), "master");
+        assert_eq!(repo_branch(&repos_dir, "COMP1002", &config), "main");
+
+        let mut config = Config::default();
+        config.tree.download_branch = Some("release".to_string());
+        assert_eq!(repo_branch(&repos_dir, "COMP1001", &config), "release");
+        assert_eq!(repo_branch(&repos_dir, "COMP1002", &config), "release");
+
+        let _ = fs::remove_dir_all(&repos_dir);
+    }
+
+    #[test]
+    fn test_docs_href_default_root() {
+        assert_eq!(docs_href("", &["2023", "CS"]), "/docs/2023/CS");
+        assert_eq!(docs_href("/wiki/", &["2023"]), "/wiki/docs/2023");
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_base_href() {
+        let root = setup_workspace("test_generate_base_href", &["MATH101", "GEN101"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("MATH101", "Calculus", Some("第一学年秋季"))],
+        )];
+        let shared = SharedCategoriesConfig {
+            categories: vec![SharedCategory {
+                id: "general".to_string(),
+                title: "General".to_string(),
+                repo_ids: vec!["GEN101".to_string()],
+            }],
+            no_course_info_repo_ids: HashSet::new(),
+        };
+        let config = Config {
+            generator: GeneratorConfig {
+                base_href: "/wiki".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        generate_course_pages(
+            &plans,
+            &shared,
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let read = |p: &str| fs::read_to_string(docs_dir.join(p)).unwrap();
+        assert!(read("2023/CS/fresh-autumn/index.mdx")
+            .contains("href=\"/wiki/docs/2023/CS/fresh-autumn/MATH101\""));
+        assert!(read("2023/CS/general/index.mdx")
+            .contains("href=\"/wiki/docs/2023/CS/general/GEN101\""));
+        let major_index = read("2023/CS/index.mdx");
+        assert!(major_index.contains("href=\"/wiki/docs/2023/CS/fresh-autumn\""));
+        assert!(major_index.contains("href=\"/wiki/docs/2023/CS/general\""));
+        assert!(read("2023/index.mdx").contains("href=\"/wiki/docs/2023/CS\""));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_shared_category_repeated_repo() {
+        let root = setup_workspace(
+            "test_generate_repeated_category_repo",
+            &["GEN101", "GEN102"],
+        );
+        let docs_dir = root.join("docs");
+        fs::write(
+            root.join("shared_categories.toml"),
+            r#"
+[[categories]]
+id = "general"
+title = "General"
+repo_ids = ["GEN101", "GEN102", "GEN101"]
+"#,
+        )
+        .unwrap();
+        let shared = crate::loader::load_shared_categories(&root);
+        let plans = vec![test_plan("2023", "CS", Vec::new())];
+
+        generate_course_pages(
+            &plans,
+            &shared,
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &Config::default(),
+        )
+        .await
+        .unwrap();
+
+        let index = fs::read_to_string(docs_dir.join("2023/CS/general/index.mdx")).unwrap();
+        assert_eq!(index.matches("<Card ").count(), 2);
+        assert_eq!(index.matches("/docs/2023/CS/general/GEN101\"").count(), 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_json_ld_on_every_course_page() {
+        let root = setup_workspace("test_generate_json_ld", &["MATH101", "GEN101"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("MATH101", "Calculus", Some("第一学年秋季"))],
+        )];
+        let shared = SharedCategoriesConfig {
+            categories: vec![SharedCategory {
+                id: "general".to_string(),
+                title: "General".to_string(),
+                repo_ids: vec!["GEN101".to_string()],
+            }],
+            no_course_info_repo_ids: HashSet::new(),
+        };
+        let mut config = Config::default();
+        config.generator.json_ld = true;
+
+        generate_course_pages(
+            &plans,
+            &shared,
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        for (page, code) in [
+            ("2023/CS/fresh-autumn/MATH101.mdx", "MATH101"),
+            ("2023/CS/general/GEN101.mdx", "GEN101"),
+        ] {
+            let content = fs::read_to_string(docs_dir.join(page)).unwrap();
+            let (_, after_frontmatter) = content[3..].split_once("---\n\n").unwrap();
+            assert!(
+                after_frontmatter.starts_with("<script type=\"application/ld+json\""),
+                "{}",
+                content
+            );
+            assert!(after_frontmatter.contains(&format!("\\\"courseCode\\\":\\\"{}\\\"", code)));
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_default_hrefs_at_root() {
+        let root = setup_workspace("test_generate_default_hrefs", &["MATH101"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("MATH101", "Calculus", Some("第一学年秋季"))],
+        )];
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &Config::default(),
+        )
+        .await
+        .unwrap();
+
+        let index = fs::read_to_string(docs_dir.join("2023/CS/fresh-autumn/index.mdx")).unwrap();
+        assert!(index.contains("href=\"/docs/2023/CS/fresh-autumn/MATH101\""));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_compose_page_matches_legacy_layout() {
+        assert_eq!(
+            compose_page("---\n---", None, true, &[], "Body", "\n\nTree"),
+            "---\n---\n\n<CourseInfo />\n\nBody\n\nTree"
+        );
+        assert_eq!(
+            compose_page("---\n---", None, false, &[], "Body", ""),
+            "---\n---\n\nBody"
+        );
+        assert_eq!(
+            compose_page("---\n---", Some("<script />"), true, &[], "Body", ""),
+            "---\n---\n\n<script />\n\n<CourseInfo />\n\nBody"
+        );
+    }
+
+    #[test]
+    fn test_build_grading_scheme_fullwidth_percents() {
+        let mut course = test_course("MATH101", "Calculus", None);
+        course.grade_details = Some(vec![
+            GradeDetail {
+                name: "期末考试".to_string(),
+                percent: Some("70％".to_string()),
+            },
+            GradeDetail {
+                name: "平时作业".to_string(),
+                percent: Some("２０%".to_string()),
+            },
+            GradeDetail {
+                name: "实验".to_string(),
+                percent: Some("１０％".to_string()),
+            },
+        ]);
+
+        let items: Vec<(String, u32)> = build_grading_scheme(&course)
+            .into_iter()
+            .map(|item| (item.name, item.percent))
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                ("期末考试".to_string(), 70),
+                ("平时作业".to_string(), 20),
+                ("实验".to_string(), 10),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_grade_table() {
+        let root = setup_workspace("test_generate_grade_table", &["MATH101"]);
+        let docs_dir = root.join("docs");
+        let mut course = test_course("MATH101", "Calculus", None);
+        course.grade_details = Some(vec![
+            GradeDetail {
+                name: "期末考试".to_string(),
+                percent: Some("70%".to_string()),
+            },
+            GradeDetail {
+                name: "平时作业".to_string(),
+                percent: Some("30%".to_string()),
+            },
+        ]);
+        let plans = vec![test_plan("2023", "CS", vec![course])];
+
+        for enabled in [true, false] {
+            let config = Config {
+                generator: GeneratorConfig {
+                    grade_table: enabled,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            generate_course_pages(
+                &plans,
+                &no_shared_categories(),
+                &HashMap::new(),
+                &root.join("repos"),
+                &docs_dir,
+                &HashSet::new(),
+                &config,
+            )
+            .await
+            .unwrap();
+
+            let page = fs::read_to_string(docs_dir.join("2023/CS/MATH101.mdx")).unwrap();
+            if enabled {
+                assert!(page.contains(
+                    "<CourseInfo />\n\n| 项目 | 占比 |\n| --- | --- |\n| 期末考试 | 70% |\n| 平时作业 | 30% |"
+                ));
+            } else {
+                assert!(!page.contains("| 项目 | 占比 |"));
+            }
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_render_grade_table_empty() {
+        assert_eq!(render_grade_table(&[]), None);
+    }
+
+    #[test]
+    fn test_build_frontmatter_english_name() {
+        let mut course = minimal_course("COMP1001", "程序设计", None);
+        assert!(
+            !build_frontmatter("程序设计", &course, vec![], None, ZeroCredit::Zero, &[])
+                .contains("englishTitle")
+        );
+
+        course.english_name = Some("Programming".to_string());
+        assert!(
+            build_frontmatter("程序设计", &course, vec![], None, ZeroCredit::Zero, &[])
+                .contains("englishTitle: \"Programming\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_index_only_keeps_pages() {
+        let root = setup_workspace("test_generate_index_only", &["MATH101"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("MATH101", "Calculus", Some("第一学年秋季"))],
+        )];
+        let run = |config: Config| {
+            let plans = plans.clone();
+            let root = root.clone();
+            let docs_dir = docs_dir.clone();
+            async move {
+                generate_course_pages(
+                    &plans,
+                    &no_shared_categories(),
+                    &HashMap::new(),
+                    &root.join("repos"),
+                    &docs_dir,
+                    &HashSet::new(),
+                    &config,
+                )
+                .await
+                .unwrap();
+            }
+        };
+
+        run(Config::default()).await;
+
+        let page_path = docs_dir.join("2023/CS/fresh-autumn/MATH101.mdx");
+        fs::write(&page_path, "hand-edited page").unwrap();
+        let page_mtime = fs::metadata(&page_path).unwrap().modified().unwrap();
+        fs::remove_file(docs_dir.join("2023/CS/meta.json")).unwrap();
+        fs::remove_file(docs_dir.join("2023/CS/fresh-autumn/index.mdx")).unwrap();
+
+        let mut config = Config::default();
+        config.generator.index_only = true;
+        run(config).await;
+
+        assert_eq!(
+            fs::metadata(&page_path).unwrap().modified().unwrap(),
+            page_mtime
+        );
+        assert_eq!(fs::read_to_string(&page_path).unwrap(), "hand-edited page");
+        assert!(docs_dir.join("2023/CS/meta.json").exists());
+        assert!(
+            fs::read_to_string(docs_dir.join("2023/CS/fresh-autumn/index.mdx"))
+                .unwrap()
+                .contains("MATH101")
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_parse_tag_txt() {
+        assert_eq!(
+            parse_tag_txt("  编程 \n\n入门\r\n编程\n"),
+            vec!["编程".to_string(), "入门".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_tags_from_tag_txt() {
+        let root = setup_workspace("test_generate_tag_txt", &["COMP1001"]);
+        let docs_dir = root.join("docs");
+        fs::write(
+            root.join("repos/COMP1001.tag.txt"),
+            "programming\nbeginner\nC language\n",
+        )
+        .unwrap();
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("COMP1001", "程序设计", None)],
+        )];
+        let mut config = Config::default();
+        config.generator.tag_txt_tags = true;
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let page = fs::read_to_string(docs_dir.join("2023/CS/COMP1001.mdx")).unwrap();
+        assert!(page.contains("tags:\n- programming\n- beginner\n- C language\n"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_semester_meta_order() {
+        let root = setup_workspace("test_generate_semester_meta", &["A101", "B102", "C103"]);
+        let docs_dir = root.join("docs");
+        let mut courses = vec![
+            test_course("A101", "Alpha", Some("第一学年秋季")),
+            test_course("B102", "Beta", Some("第一学年秋季")),
+            test_course("C103", "Gamma", Some("第一学年秋季")),
+        ];
+        courses[0].credit = Some(2.0);
+        courses[1].credit = None;
+        courses[2].credit = Some(4.0);
+        let plans = vec![test_plan("2023", "CS", courses)];
+
+        for (order, expected) in [
+            (CourseOrder::Plan, ["index", "A101", "B102", "C103"]),
+            (CourseOrder::Credit, ["index", "C103", "A101", "B102"]),
+        ] {
+            let mut config = Config::default();
+            config.generator.folder_meta = true;
+            config.generator.folder_meta_order = order;
+
+            generate_course_pages(
+                &plans,
+                &no_shared_categories(),
+                &HashMap::new(),
+                &root.join("repos"),
+                &docs_dir,
+                &HashSet::new(),
+                &config,
+            )
+            .await
+            .unwrap();
+
+            let meta: serde_json::Value = serde_json::from_str(
+                &fs::read_to_string(docs_dir.join("2023/CS/fresh-autumn/meta.json")).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(meta["title"], "大一·秋");
+            assert_eq!(meta["pages"], serde_json::json!(expected));
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_reads_sharded_repos() {
+        let root = std::env::temp_dir().join("test_generate_sharded_repos");
+        let _ = fs::remove_dir_all(&root);
+        let source_dir = root.join("source");
+        let repos_dir = root.join("repos");
+        let docs_dir = root.join("docs");
+        fs::create_dir_all(source_dir.join("COMP1001")).unwrap();
+        fs::write(
+            source_dir.join("COMP1001/README.md"),
+            "# COMP1001 - 程序设计\n\nSharded body\n",
+        )
+        .unwrap();
+        fs::write(source_dir.join("COMP1001/worktree.json"), "{}").unwrap();
+
+        let mut config = Config::default();
+        config.cache.layout = ReposLayout::Sharded;
+        let options = crate::fetcher::FetchOptions {
+            layout: ReposLayout::Sharded,
+            ..Default::default()
+        };
+        crate::fetcher::fetch_all_repos(
+            std::sync::Arc::new(crate::fetcher::Source::LocalDir(source_dir)),
+            "HITSZ-OpenAuto",
+            &["COMP1001".to_string()],
+            &repos_dir,
+            1,
+            &options,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(repos_dir.join("CO/COMP1001.mdx").exists());
+        assert!(!repos_dir.join("COMP1001.mdx").exists());
+
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("COMP1001", "程序设计", None)],
+        )];
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &repos_dir,
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let page = fs::read_to_string(docs_dir.join("2023/CS/COMP1001.mdx")).unwrap();
+        assert!(page.contains("Sharded body"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_course_nav() {
+        let root = setup_workspace("test_generate_course_nav", &["A101", "B102", "C103"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![
+                test_course("A101", "Alpha", Some("第一学年秋季")),
+                test_course("B102", "Beta", Some("第一学年秋季")),
+                test_course("C103", "Gamma", Some("第一学年秋季")),
+            ],
+        )];
+        let mut config = Config::default();
+        config.generator.course_nav = true;
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let read = |slug: &str| {
+            fs::read_to_string(docs_dir.join(format!("2023/CS/fresh-autumn/{}.mdx", slug))).unwrap()
+        };
+        assert!(read("B102").ends_with(
+            "上一门：[Alpha](/docs/2023/CS/fresh-autumn/A101) | 下一门：[Gamma](/docs/2023/CS/fresh-autumn/C103)"
+        ));
+        assert!(read("A101").ends_with("下一门：[Beta](/docs/2023/CS/fresh-autumn/B102)"));
+        assert!(!read("A101").contains("上一门"));
+        assert!(read("C103").ends_with("上一门：[Beta](/docs/2023/CS/fresh-autumn/B102)"));
+        assert!(!read("C103").contains("下一门"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_major_scoped_notice() {
+        let root = setup_workspace("test_generate_major_notice", &["COMP1001", "ELEC1001"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![
+            test_plan(
+                "2023",
+                "CS",
+                vec![test_course("COMP1001", "程序设计", None)],
+            ),
+            test_plan("2023", "EE", vec![test_course("ELEC1001", "电路", None)]),
+        ];
+        let mut config = Config::default();
+        config.generator.notices = vec![Notice {
+            scope: NoticeScope::Major,
+            target: "CS".to_string(),
+            markdown: "> 考试周期间资源可能延迟更新".to_string(),
+        }];
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let cs_page = fs::read_to_string(docs_dir.join("2023/CS/COMP1001.mdx")).unwrap();
+        assert!(cs_page.contains("<CourseInfo />\n\n> 考试周期间资源可能延迟更新\n\n"));
+        let ee_page = fs::read_to_string(docs_dir.join("2023/EE/ELEC1001.mdx")).unwrap();
+        assert!(!ee_page.contains("考试周"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_ensure_valid_frontmatter_strict() {
+        let mut config = GeneratorConfig::default();
+        let broken = "---\ntitle: a: b\n---";
+        assert!(ensure_valid_frontmatter(broken, "a: b", "X", &config).is_ok());
+
+        config.strict_frontmatter = true;
+        assert!(matches!(
+            ensure_valid_frontmatter(broken, "a: b", "X", &config),
+            Err(FumaError::InvalidFrontmatter { ref page, .. }) if page == "X"
+        ));
+        let course = test_course("COMP1001", "数据结构: \"进阶\"", None);
+        let frontmatter =
+            build_frontmatter(&course.name, &course, vec![], None, ZeroCredit::Zero, &[]);
+        assert!(ensure_valid_frontmatter(&frontmatter, &course.name, "COMP1001", &config).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_generate_skips_blocklisted_major() {
+        let root = setup_workspace("test_generate_major_blocklist", &["COMP1001", "ELEC1001"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![
+            test_plan(
+                "2023",
+                "CS",
+                vec![test_course("COMP1001", "程序设计", None)],
+            ),
+            test_plan("2023", "EE", vec![test_course("ELEC1001", "电路", None)]),
+        ];
+        let mut config = Config::default();
+        config.generator.major_blocklist = vec!["2023/EE".to_string()];
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert!(docs_dir.join("2023/CS/COMP1001.mdx").exists());
+        assert!(!docs_dir.join("2023/EE").exists());
+        let year_index = fs::read_to_string(docs_dir.join("2023/index.mdx")).unwrap();
+        assert!(year_index.contains("href=\"/docs/2023/CS\""));
+        assert!(!year_index.contains("EE"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_major_total_credits() {
+        let root = setup_workspace("test_generate_major_credits", &["A101", "B102", "C103"]);
+        let docs_dir = root.join("docs");
+        let mut courses = vec![
+            test_course("A101", "Alpha", Some("第一学年秋季,第一学年春季")),
+            test_course("B102", "Beta", Some("第一学年秋季")),
+            test_course("C103", "Gamma", None),
+            test_course("A101", "Alpha", Some("第二学年秋季")),
+        ];
+        courses[0].credit = Some(3.0);
+        courses[1].credit = Some(2.5);
+        courses[2].credit = None;
+        courses[3].credit = Some(3.0);
+        let plans = vec![test_plan("2023", "CS", courses)];
+        let mut config = Config::default();
+        config.generator.major_credits = true;
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let meta: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(docs_dir.join("2023/CS/meta.json")).unwrap())
+                .unwrap();
+        assert_eq!(meta["totalCredits"], 5.5);
+        assert_eq!(meta["unknownCreditCourses"], 1);
+        let index = fs::read_to_string(docs_dir.join("2023/CS/index.mdx")).unwrap();
+        assert!(index.contains("总学分：5.5（另有 1 门课程学分未知）\n\n<Cards>"));
+
+        // An integral total is a JSON integer, not 6.0
+        let mut plans = plans;
+        plans[0].courses[1].credit = Some(3.0);
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+        let meta_text = fs::read_to_string(docs_dir.join("2023/CS/meta.json")).unwrap();
+        assert!(meta_text.contains("\"totalCredits\": 6,"), "{}", meta_text);
+        let index = fs::read_to_string(docs_dir.join("2023/CS/index.mdx")).unwrap();
+        assert!(index.contains("总学分：6（另有 1 门课程学分未知）"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_custom_semester_order() {
+        let root = setup_workspace("test_generate_semester_order", &["A101", "B102", "C103"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![
+                test_course("A101", "Alpha", Some("第一学年秋季")),
+                test_course("B102", "Beta", Some("第一学年春季")),
+                test_course("C103", "Gamma", Some("第一学年夏季")),
+            ],
+        )];
+
+        for (order, expected) in [
+            (
+                vec![],
+                ["...", "fresh-autumn", "fresh-spring", "fresh-summer"],
+            ),
+            (
+                vec!["fresh-spring", "fresh-summer", "fresh-autumn"],
+                ["...", "fresh-spring", "fresh-summer", "fresh-autumn"],
+            ),
+        ] {
+            let mut config = Config::default();
+            config.generator.semester_order = order.into_iter().map(str::to_string).collect();
+
+            generate_course_pages(
+                &plans,
+                &no_shared_categories(),
+                &HashMap::new(),
+                &root.join("repos"),
+                &docs_dir,
+                &HashSet::new(),
+                &config,
+            )
+            .await
+            .unwrap();
+
+            let meta: serde_json::Value = serde_json::from_str(
+                &fs::read_to_string(docs_dir.join("2023/CS/meta.json")).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(meta["pages"], serde_json::json!(expected));
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_custom_semester_mapping() {
+        let root = setup_workspace("test_generate_semester_mapping", &["A101", "B102"]);
+        let docs_dir = root.join("docs");
+        fs::write(
+            root.join("semesters.toml"),
+            r#"
+[[semesters]]
+name = "第二学期"
+folder = "term-2"
+title = "Term 2"
+
+[[semesters]]
+name = "第一学期"
+folder = "term-1"
+title = "Term 1"
+"#,
+        )
+        .unwrap();
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![
+                test_course("A101", "Alpha", Some("第一学期")),
+                test_course("B102", "Beta", Some("第二学期")),
+            ],
+        )];
+        let mut config = Config::default();
+        config.generator.semesters = crate::loader::load_semester_mapping(&root).unwrap();
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert!(docs_dir.join("2023/CS/term-1/A101.mdx").exists());
+        assert!(docs_dir.join("2023/CS/term-2/B102.mdx").exists());
+        let read = |p: &str| fs::read_to_string(docs_dir.join(p)).unwrap();
+        assert!(read("2023/CS/term-1/index.mdx").starts_with("---\ntitle: Term 1\n"));
+        let meta: serde_json::Value = serde_json::from_str(&read("2023/CS/meta.json")).unwrap();
+        assert_eq!(
+            meta["pages"],
+            serde_json::json!(["...", "term-2", "term-1"])
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_build_frontmatter_extra_fields() {
+        let mut course = minimal_course("COMP1001", "程序设计", None);
+        course.extra.insert(
+            "department".to_string(),
+            toml::Value::String("计算机学院".to_string()),
+        );
+        course
+            .extra
+            .insert("weekly_hours".to_string(), toml::Value::Integer(4));
+
+        let fields = ["department".to_string(), "campus".to_string()];
+        let frontmatter =
+            build_frontmatter("程序设计", &course, vec![], None, ZeroCredit::Zero, &fields);
+        assert!(frontmatter.contains("  department: 计算机学院\n"));
+        assert!(!frontmatter.contains("campus"));
+        assert!(!frontmatter.contains("weekly_hours"));
+
+        let frontmatter =
+            build_frontmatter("程序设计", &course, vec![], None, ZeroCredit::Zero, &[]);
+        assert!(!frontmatter.contains("department"));
+    }
+
+    #[test]
+    fn test_split_readme_without_frontmatter() {
+        let readme = "# COMP1001 - 程序设计\n\nBody line\n\nMore";
+        assert_eq!(
+            split_readme(readme),
+            (None, "Body line\n\nMore".to_string())
+        );
+    }
+
+    #[test]
+    fn test_split_readme_with_frontmatter() {
+        let readme = "---\ntitle: 程序设计\ndescription: 入门课程\n---\n\n# COMP1001 - 程序设计\n\nBody line";
+        assert_eq!(
+            split_readme(readme),
+            (Some("入门课程".to_string()), "Body line".to_string())
+        );
+        // No heading after the block: the body is kept whole
+        assert_eq!(
+            split_readme("---\ntitle: x\n---\nFirst\nSecond"),
+            (None, "First\nSecond".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_strips_readme_frontmatter() {
+        let root = setup_workspace("test_generate_readme_frontmatter", &["COMP1001"]);
+        let docs_dir = root.join("docs");
+        fs::write(
+            root.join("repos/COMP1001.mdx"),
+            "---\ntitle: 程序设计\ndescription: 入门课程\n---\n# COMP1001 - 程序设计\n\nBody\n",
+        )
+        .unwrap();
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("COMP1001", "程序设计", None)],
+        )];
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &Config::default(),
+        )
+        .await
+        .unwrap();
+
+        let page = fs::read_to_string(docs_dir.join("2023/CS/COMP1001.mdx")).unwrap();
+        assert_eq!(page.matches("---").count(), 2);
+        assert!(page.contains("description: \"入门课程\"\n"));
+        assert!(page.ends_with("<CourseInfo />\n\nBody"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_render_json_ld() {
+        let course = test_course("COMP1001", "程序设计 </script>", None);
+        let script = render_json_ld(&course, Some("入门 & 进阶"), "HITSZ");
+
+        assert!(!script.contains("</script>"));
+        let literal = script
+            .strip_prefix(
+                "<script type=\"application/ld+json\" dangerouslySetInnerHTML={{ __html: ",
+            )
+            .and_then(|rest| rest.strip_suffix(" }} />"))
+            .unwrap();
+        let json: String = serde_json::from_str(literal).unwrap();
+        let json_ld: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json_ld["@type"], "Course");
+        assert_eq!(json_ld["name"], "程序设计 </script>");
+        assert_eq!(json_ld["courseCode"], "COMP1001");
+        assert_eq!(json_ld["description"], "入门 & 进阶");
+        assert_eq!(json_ld["provider"]["name"], "HITSZ");
+    }
+
+    #[tokio::test]
+    async fn test_generate_refetches_corrupt_worktree() {
+        let root = setup_workspace("test_generate_refetch_corrupt", &["COMP1001"]);
+        let docs_dir = root.join("docs");
+        let source_dir = root.join("source");
+        fs::create_dir_all(source_dir.join("COMP1001")).unwrap();
+        fs::write(
+            source_dir.join("COMP1001/worktree.json"),
+            r#"{"notes.pdf": {"size": 10, "time": null}}"#,
+        )
+        .unwrap();
+        // Truncated by an interrupted write
+        fs::write(root.join("repos/COMP1001.json"), r#"{"notes.pdf": {"si"#).unwrap();
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![test_course("COMP1001", "程序设计", None)],
+        )];
+        let mut config = Config::default();
+
+        for refetch in [false, true] {
+            if refetch {
+                config.cache.refetch_source = Some(std::sync::Arc::new(
+                    crate::fetcher::Source::LocalDir(source_dir.clone()),
+                ));
+            }
+            let result = generate_course_pages(
+                &plans,
+                &no_shared_categories(),
+                &HashMap::new(),
+                &root.join("repos"),
+                &docs_dir,
+                &HashSet::new(),
+                &config,
+            )
+            .await;
+            assert_eq!(result.is_ok(), refetch);
+        }
+
+        let page = fs::read_to_string(docs_dir.join("2023/CS/COMP1001.mdx")).unwrap();
+        assert!(page.contains("<File name=\"notes.pdf\""));
+        assert!(serde_json::from_str::<WorktreeData>(
+            &fs::read_to_string(root.join("repos/COMP1001.json")).unwrap()
+        )
+        .is_ok());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_normalize_assessment_method() {
+        assert_eq!(
+            normalize_assessment_method(" 考试 ").as_deref(),
+            Some("考试")
+        );
+        assert_eq!(
+            normalize_assessment_method("闭卷考试").as_deref(),
+            Some("考试")
+        );
+        assert_eq!(normalize_assessment_method("Exam").as_deref(), Some("考试"));
+        assert_eq!(normalize_assessment_method("考查").as_deref(), Some("考查"));
+        assert_eq!(
+            normalize_assessment_method("Pass / Fail").as_deref(),
+            Some("合格制")
+        );
+        assert_eq!(
+            normalize_assessment_method("合格/不合格").as_deref(),
+            Some("合格制")
+        );
+        assert_eq!(normalize_assessment_method("答辩").as_deref(), Some("答辩"));
+        assert_eq!(normalize_assessment_method("  "), None);
+    }
+
+    #[test]
+    fn test_collect_tags_assessment_method() {
+        let root = setup_workspace("test_collect_tags_assessment", &[]);
+        let mut config = Config::default();
+        config.generator.assessment_tag = true;
+
+        let mut exam = test_course("COMP1001", "程序设计", None);
+        exam.assessment_method = Some("考试".to_string());
+        let mut pass_fail = test_course("PE1001", "体育", None);
+        pass_fail.assessment_method = Some("pass/fail".to_string());
+
+        let repos_dir = root.join("repos");
+        assert_eq!(
+            collect_tags(&exam, &repos_dir, &config).unwrap(),
+            vec!["考试"]
+        );
+        assert_eq!(
+            collect_tags(&pass_fail, &repos_dir, &config).unwrap(),
+            vec!["合格制"]
+        );
+        config.generator.assessment_tag = false;
+        assert!(collect_tags(&exam, &repos_dir, &config).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_partial_regeneration() {
+        let root = setup_workspace("test_generate_partial", &["A101", "B102"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![
+                test_course("A101", "Alpha", Some("第一学年秋季")),
+                test_course("B102", "Beta", Some("第一学年春季")),
+            ],
+        )];
+        let run = |config: Config| {
+            let plans = plans.clone();
+            let root = root.clone();
+            async move {
+                generate_course_pages(
+                    &plans,
+                    &no_shared_categories(),
+                    &HashMap::new(),
+                    &root.join("repos"),
+                    &root.join("docs"),
+                    &HashSet::new(),
+                    &config,
+                )
+                .await
+                .unwrap();
+            }
+        };
+        run(Config::default()).await;
+
+        // Mark every output so rewrites are detectable
+        let files = [
+            "2023/CS/fresh-autumn/A101.mdx",
+            "2023/CS/fresh-spring/B102.mdx",
+            "2023/CS/fresh-autumn/index.mdx",
+            "2023/CS/fresh-spring/index.mdx",
+            "2023/CS/index.mdx",
+            "2023/CS/meta.json",
+            "2023/index.mdx",
+        ];
+        for file in files {
+            fs::write(docs_dir.join(file), "stale").unwrap();
+        }
+        for repo_id in ["A101", "B102"] {
+            fs::write(
+                root.join(format!("repos/{}.mdx", repo_id)),
+                "# X - X\n\nEdited body\n",
+            )
+            .unwrap();
+        }
+
+        let mut config = Config::default();
+        config.generator.changed_repo_ids = Some(HashSet::from(["A101".to_string()]));
+        run(config).await;
+
+        let read = |file: &str| fs::read_to_string(docs_dir.join(file)).unwrap();
+        assert!(read("2023/CS/fresh-autumn/A101.mdx").contains("Edited body"));
+        assert!(read("2023/CS/fresh-autumn/index.mdx").contains("Alpha"));
+        assert!(read("2023/CS/index.mdx").contains("fresh-autumn"));
+        assert!(read("2023/CS/meta.json").contains("fresh-autumn"));
+        for untouched in [
+            "2023/CS/fresh-spring/B102.mdx",
+            "2023/CS/fresh-spring/index.mdx",
+            "2023/index.mdx",
+        ] {
+            assert_eq!(read(untouched), "stale", "{}", untouched);
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_search_page() {
+        let root = setup_workspace("test_generate_search_page", &["MATH1001", "COMP1001"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![
+            test_plan(
+                "2023",
+                "CS",
+                vec![
+                    test_course("MATH1001", "Calculus", Some("第一学年秋季")),
+                    test_course("COMP1001", "Programming", Some("第一学年秋季")),
+                ],
+            ),
+            test_plan(
+                "2023",
+                "EE",
+                vec![test_course("MATH1001", "Calculus", Some("第一学年秋季"))],
+            ),
+        ];
+        let mut config = Config::default();
+        config.generator.search_page = true;
+
+        generate_course_pages(
+            &plans,
+            &no_shared_categories(),
+            &HashMap::new(),
+            &root.join("repos"),
+            &docs_dir,
+            &HashSet::new(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let search = fs::read_to_string(docs_dir.join("search.mdx")).unwrap();
+        assert_eq!(search.matches("[Calculus]").count(), 1);
+        assert!(search.ends_with(
+            "- [Calculus](/docs/2023/CS/fresh-autumn/MATH1001) `MATH1001`\n\
+             - [Programming](/docs/2023/CS/fresh-autumn/COMP1001) `COMP1001`"
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_generate_search_page_partial_run() {
+        let root = setup_workspace("test_generate_search_partial", &["MATH1001", "GEN101"]);
+        let docs_dir = root.join("docs");
+        let plans = vec![test_plan(
+            "2023",
+            "CS",
+            vec![
+                test_course("MATH1001", "Calculus", Some("第一学年秋季")),
+                test_course("GEN101", "Writing", None),
+            ],
+        )];
+        let mut config = Config::default();
+        config.generator.search_page = true;
+
+        for changed in [None, Some(HashSet::from(["MATH1001".to_string()]))] {
+            config.generator.changed_repo_ids = changed;
+            generate_course_pages(
+                &plans,
+                &no_shared_categories(),
+                &HashMap::new(),
+                &root.join("repos"),
+                &docs_dir,
+                &HashSet::new(),
+                &config,
+            )
+            .await
+            .unwrap();
+
+            // The unchanged semester-less course stays in the catalog
+            let search = fs::read_to_string(docs_dir.join("search.mdx")).unwrap();
+            assert!(search.contains("- [Writing](/docs/2023/CS/GEN101) `GEN101`"));
+            assert!(search.contains("- [Calculus](/docs/2023/CS/fresh-autumn/MATH1001)"));
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_render_toc_with_several_headings() {
+        let body = "Intro\n\n## 课程简介\n\n### Exam Tips!\n\n```\n## not a heading\n```\n\n## [资源](https://example.com)\n\n## 课程简介\n";
+
+        assert_eq!(
+            render_toc(body, 3).as_deref(),
+            Some(
+                "- [课程简介](#课程简介)\n  - [Exam Tips!](#exam-tips)\n- [资源](#资源)\n- [课程简介](#课程简介-1)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_toc_skips_few_headings() {
+        let body = "## 课程简介\n\nText\n\n#### Too deep\n";
+        assert_eq!(render_toc(body, 3), None);
+        assert_eq!(render_toc("No headings", 0), None);
+    }
 }
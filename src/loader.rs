@@ -4,12 +4,14 @@
 //! and enrich it with grade details from grades_summary.json. By loading all data
 //! upfront, we avoid the N+1 query problem that plagued the Python implementation.
 
+use crate::constants::{get_semester_folder, split_semester_tokens, SemesterMapping};
 use crate::error::{FumaError, Result};
-use crate::models::{Course, GradeDetail, Plan, SharedCategory, TomlPlan};
+use crate::models::{Course, GradeDetail, Plan, SharedCategory, TomlCourse, TomlPlan};
+use rayon::prelude::*;
 use serde::Deserialize;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 #[derive(Debug, Deserialize)]
@@ -41,10 +43,59 @@ pub fn load_grades_summary(data_dir: &Path) -> GradesSummary {
         return HashMap::new();
     }
 
-    match fs::read_to_string(&path) {
+    let summary: GradesSummary = match fs::read_to_string(&path) {
         Ok(content) => serde_json::from_str(&content).unwrap_or_else(|_| HashMap::new()),
         Err(_) => HashMap::new(),
+    };
+
+    summary
+        .into_iter()
+        .map(|(repo_id, variants)| {
+            let variants = variants
+                .into_iter()
+                .map(|(key, details)| (normalize_variant_key(&key), details))
+                .collect();
+            (repo_id, variants)
+        })
+        .collect()
+}
+
+/// Normalize a grades_summary variant key such as `2023_Computer Science`.
+///
+/// Trims the key and collapses each run of whitespace (including the
+/// full-width space) and underscores into a single `_`, so that keys built
+/// from major names match regardless of how the summary spelled separators.
+fn normalize_variant_key(key: &str) -> String {
+    let mut normalized = String::with_capacity(key.len());
+    let mut pending_separator = false;
+
+    for ch in key.trim().chars() {
+        if ch.is_whitespace() || ch == '_' || ch == '＿' {
+            pending_separator = true;
+            continue;
+        }
+        if pending_separator && !normalized.is_empty() {
+            normalized.push('_');
+        }
+        pending_separator = false;
+        normalized.push(ch);
     }
+
+    normalized
+}
+
+/// Look up a grade variant by key, comparing normalized forms.
+fn get_variant<'a>(
+    entry: &'a HashMap<String, Vec<GradeDetail>>,
+    key: &str,
+) -> Option<&'a Vec<GradeDetail>> {
+    entry.get(key).or_else(|| {
+        let wanted = normalize_variant_key(key);
+        entry
+            .iter()
+            .find(|(k, _)| normalize_variant_key(k) == wanted)
+            .map(|(_, details)| details)
+    })
 }
 
 /// Load lookup_table.toml if present.
@@ -63,25 +114,98 @@ fn load_lookup_table(data_dir: &Path) -> LookupTable {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct TomlSemesters {
+    semesters: Vec<TomlSemester>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TomlSemester {
+    name: String,
+    folder: String,
+    title: String,
+}
+
+/// Load semesters.toml if present, replacing the built-in semester mapping.
+///
+/// Entries are kept in file order, which is also the order semesters are
+/// listed in. Returns None if the file doesn't exist, can't be parsed or
+/// lists no semesters, so the built-in mapping stays in use.
+pub fn load_semester_mapping(data_dir: &Path) -> Option<SemesterMapping> {
+    let path = data_dir.join("semesters.toml");
+    let content = fs::read_to_string(&path).ok()?;
+    let toml: TomlSemesters = match toml::from_str(&content) {
+        Ok(toml) => toml,
+        Err(e) => {
+            eprintln!("Warning: ignoring {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    if toml.semesters.is_empty() {
+        eprintln!(
+            "Warning: ignoring {}: it lists no semesters",
+            path.display()
+        );
+        return None;
+    }
+
+    Some(SemesterMapping::custom(
+        toml.semesters
+            .into_iter()
+            .map(|s| (s.name, s.folder, s.title))
+            .collect(),
+    ))
+}
+
+/// Course code in the form used for matching: trimmed and uppercased
+fn normalize_course_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
 /// Resolve repository ID for a course code by lookup table rules.
 ///
-/// Priority:
+/// Codes are compared after [`normalize_course_code`], so ` math101 `
+/// matches `MATH101`; the repo ID is returned as written in the table.
+/// The entry for the exact code is tried first, then wildcard entries such as
+/// `AUTO*` whose prefix the code starts with, longest prefix first. Within an
+/// entry:
 /// 1. Exact match by `plan_id`
 /// 2. `DEFAULT` fallback
-/// 3. Original `course_code` (identity mapping)
-fn resolve_repo_id(lookup_table: &LookupTable, course_code: &str, plan_id: &str) -> String {
-    lookup_table
-        .get(course_code)
-        .and_then(|mapping| {
+///
+/// Returns `None` when no entry applies; callers then use the original
+/// `course_code` (identity mapping).
+fn resolve_repo_id(lookup_table: &LookupTable, course_code: &str, plan_id: &str) -> Option<String> {
+    let code = normalize_course_code(course_code);
+    let exact = lookup_table.get(course_code).or_else(|| {
+        lookup_table
+            .iter()
+            .find(|(key, _)| normalize_course_code(key) == code)
+            .map(|(_, mapping)| mapping)
+    });
+
+    let mut wildcards: Vec<(String, &HashMap<String, String>)> = lookup_table
+        .iter()
+        .filter_map(|(key, mapping)| {
+            key.strip_suffix('*')
+                .map(normalize_course_code)
+                .filter(|prefix| code.starts_with(prefix.as_str()))
+                .map(|prefix| (prefix, mapping))
+        })
+        .collect();
+    wildcards.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0)));
+
+    exact
+        .into_iter()
+        .chain(wildcards.into_iter().map(|(_, mapping)| mapping))
+        .find_map(|mapping| {
             mapping
                 .get(plan_id)
                 .or_else(|| mapping.get("DEFAULT"))
                 .or_else(|| mapping.get("default"))
+                .map(|repo_id| repo_id.trim())
+                .filter(|repo_id| !repo_id.is_empty())
         })
-        .map(|repo_id| repo_id.trim())
-        .filter(|repo_id| !repo_id.is_empty())
         .map(str::to_string)
-        .unwrap_or_else(|| course_code.to_string())
 }
 
 /// Select grade details for a course based on hierarchical matching rules.
@@ -91,6 +215,9 @@ fn resolve_repo_id(lookup_table: &LookupTable, course_code: &str, plan_id: &str)
 /// 2. `{year}_default` (year-specific default)
 /// 3. `default` (global default)
 ///
+/// The repo ID is matched like course codes in [`resolve_repo_id`], ignoring
+/// surrounding whitespace and case.
+///
 /// Returns None if no matching grade details are found.
 fn select_grade_details(
     grades_summary: &GradesSummary,
@@ -99,7 +226,13 @@ fn select_grade_details(
     major_code: &str,
     major_name: &str,
 ) -> Option<Vec<GradeDetail>> {
-    let entry = grades_summary.get(repo_id)?;
+    let entry = grades_summary.get(repo_id).or_else(|| {
+        let wanted = normalize_course_code(repo_id);
+        grades_summary
+            .iter()
+            .find(|(key, _)| normalize_course_code(key) == wanted)
+            .map(|(_, entry)| entry)
+    })?;
 
     // Try year_major keys (both code and name)
     let year_major_keys = vec![
@@ -108,7 +241,7 @@ fn select_grade_details(
     ];
 
     for key in &year_major_keys {
-        if let Some(details) = entry.get(key) {
+        if let Some(details) = get_variant(entry, key) {
             if !details.is_empty() {
                 return Some(details.clone());
             }
@@ -117,7 +250,7 @@ fn select_grade_details(
 
     // Try year_default
     let year_default_key = format!("{}_default", year);
-    if let Some(details) = entry.get(&year_default_key) {
+    if let Some(details) = get_variant(entry, &year_default_key) {
         if !details.is_empty() {
             return Some(details.clone());
         }
@@ -133,6 +266,171 @@ fn select_grade_details(
     None
 }
 
+/// Course codes listed more than once in a plan, each reported once in
+/// order of first repetition
+fn duplicate_course_codes(courses: &[TomlCourse]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+
+    for course in courses {
+        if !seen.insert(course.course_code.as_str()) && !duplicates.contains(&course.course_code) {
+            duplicates.push(course.course_code.clone());
+        }
+    }
+
+    duplicates
+}
+
+/// Values of a `recommended_year_semester` field that name no known
+/// semester, e.g. a typo like `第三学年秋李`. Bare numbers are accepted since
+/// their meaning depends on the configured numbering.
+fn unknown_semesters<'a>(semesters: &SemesterMapping, recommended: &'a str) -> Vec<&'a str> {
+    split_semester_tokens(recommended)
+        .filter(|token| {
+            get_semester_folder(semesters, token).is_none()
+                && !token.chars().all(|c| c.is_ascii_digit())
+        })
+        .collect()
+}
+
+/// Replace the courses of every plan with an `extends` field by its base
+/// plan's (resolved) courses, with the plan's own courses applied on top: a
+/// course replaces the base course with the same code in place, and other
+/// courses are appended.
+fn resolve_plan_inheritance(plans: &mut [TomlPlan]) -> Result<()> {
+    let index: HashMap<String, usize> = plans
+        .iter()
+        .enumerate()
+        .map(|(i, plan)| (plan.info.plan_id.clone(), i))
+        .collect();
+
+    let mut resolved = HashMap::new();
+    for i in 0..plans.len() {
+        inherited_courses(i, plans, &index, &mut resolved, &mut Vec::new())?;
+    }
+    for (i, courses) in resolved {
+        plans[i].courses = courses;
+    }
+
+    Ok(())
+}
+
+/// Effective courses of `plans[i]`, memoized in `resolved` for plans that
+/// extend another. `chain` holds the plans whose resolution is in progress.
+fn inherited_courses(
+    i: usize,
+    plans: &[TomlPlan],
+    index: &HashMap<String, usize>,
+    resolved: &mut HashMap<usize, Vec<TomlCourse>>,
+    chain: &mut Vec<usize>,
+) -> Result<Vec<TomlCourse>> {
+    if let Some(courses) = resolved.get(&i) {
+        return Ok(courses.clone());
+    }
+    let plan = &plans[i];
+    let Some(ref base_id) = plan.info.extends else {
+        return Ok(plan.courses.clone());
+    };
+
+    let error = |reason: &str| FumaError::PlanExtends {
+        plan_id: plan.info.plan_id.clone(),
+        base: base_id.clone(),
+        reason: reason.to_string(),
+    };
+    let &base = index
+        .get(base_id)
+        .ok_or_else(|| error("no plan has this plan_ID"))?;
+    if base == i || chain.contains(&base) {
+        return Err(error("plans extend each other in a cycle"));
+    }
+
+    chain.push(i);
+    let mut courses = inherited_courses(base, plans, index, resolved, chain)?;
+    chain.pop();
+
+    for course in &plan.courses {
+        match courses
+            .iter()
+            .position(|c| c.course_code == course.course_code)
+        {
+            Some(pos) => courses[pos] = course.clone(),
+            None => courses.push(course.clone()),
+        }
+    }
+
+    resolved.insert(i, courses.clone());
+    Ok(courses)
+}
+
+/// How far a course's grade percentages may be from 100 before warning
+const GRADE_TOTAL_TOLERANCE: f64 = 0.5;
+
+/// Total of `details`' percentages when it isn't 100 (within tolerance).
+///
+/// Returns `None` when the total adds up, and also when any percent is
+/// missing or not a number, since the total is then unknown.
+fn grade_total_mismatch(details: &[GradeDetail]) -> Option<f64> {
+    if details.is_empty() {
+        return None;
+    }
+
+    let mut total = 0.0;
+    for detail in details {
+        total += detail.percent_text()?.parse::<f64>().ok()?;
+    }
+
+    ((total - 100.0).abs() > GRADE_TOTAL_TOLERANCE).then_some(total)
+}
+
+/// Result of [`load_all_plans`]: the plans plus lookup details to audit.
+#[derive(Debug, Default)]
+pub struct LoadedPlans {
+    pub plans: Vec<Plan>,
+    /// Course codes with no lookup_table.toml entry, used as their own repo
+    /// ID. A missing entry here looks the same as a missing repo later on.
+    pub unmapped_course_codes: BTreeSet<String>,
+    /// Duplicate course codes and unrecognized semesters, one message each
+    pub plan_warnings: Vec<String>,
+    /// Courses whose grade percentages don't add up to 100
+    pub bad_percentages: Vec<String>,
+}
+
+/// Read and parse one plan file.
+fn parse_plan_file(path: &Path) -> Result<TomlPlan> {
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|source| FumaError::PlanParse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Duplicate course codes and unrecognized semesters in a plan as written,
+/// before inheritance.
+fn plan_warnings(toml_plan: &TomlPlan, semesters: &SemesterMapping) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let duplicates = duplicate_course_codes(&toml_plan.courses);
+    if !duplicates.is_empty() {
+        warnings.push(format!(
+            "plan {} {} ({}) lists course codes more than once: {}",
+            toml_plan.info.year,
+            toml_plan.info.major_name,
+            toml_plan.info.major_code,
+            duplicates.join(", ")
+        ));
+    }
+    for course in &toml_plan.courses {
+        let recommended = course.recommended_year_semester.as_deref();
+        let unknown = recommended.map(|r| unknown_semesters(semesters, r));
+        for semester in unknown.unwrap_or_default() {
+            warnings.push(format!(
+                "{} in plan {} has unrecognized recommended_year_semester {:?}",
+                course.course_code, toml_plan.info.plan_id, semester
+            ));
+        }
+    }
+    warnings
+}
+
 /// Load all training plans from TOML files with grade details enrichment.
 ///
 /// This function loads all plan data in a single pass, avoiding the N+1 query problem
@@ -141,11 +439,12 @@ fn select_grade_details(
 ///
 /// # Arguments
 /// * `data_dir` - Path to the hoa-majors data directory containing plans/ subdirectory
+/// * `semesters` - Semester mapping used to spot unrecognized semesters
 ///
 /// # Returns
-/// * `Ok(Vec<Plan>)` - All loaded and enriched training plans
+/// * `Ok(LoadedPlans)` - All loaded and enriched training plans
 /// * `Err(FumaError)` - If the plans directory is missing or files can't be read
-pub fn load_all_plans(data_dir: &Path) -> Result<Vec<Plan>> {
+pub fn load_all_plans(data_dir: &Path, semesters: &SemesterMapping) -> Result<LoadedPlans> {
     let plans_dir = data_dir.join("plans");
 
     if !plans_dir.exists() {
@@ -157,27 +456,53 @@ pub fn load_all_plans(data_dir: &Path) -> Result<Vec<Plan>> {
     // Load course_code -> repo_id lookup table once for all plans
     let lookup_table = load_lookup_table(data_dir);
 
-    let mut plans = Vec::new();
-
-    for entry in WalkDir::new(&plans_dir)
+    // Plan files are independent until inheritance, so read and parse them
+    // in parallel. Every result is kept in path order so that the reported
+    // error is the first bad file, not whichever thread failed first.
+    let paths: Vec<PathBuf> = WalkDir::new(&plans_dir)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.path().extension().is_some_and(|ext| ext == "toml"))
-    {
-        let content = fs::read_to_string(entry.path())?;
-        let toml_plan: TomlPlan = toml::from_str(&content)?;
+        .map(|e| e.into_path())
+        .collect();
+    let parsed: Vec<Result<TomlPlan>> =
+        paths.par_iter().map(|path| parse_plan_file(path)).collect();
+    let mut toml_plans = parsed.into_iter().collect::<Result<Vec<_>>>()?;
+
+    // Warn only once parsing is done, so the output order is stable
+    let mut warnings = Vec::new();
+    for toml_plan in &toml_plans {
+        for warning in plan_warnings(toml_plan, semesters) {
+            eprintln!("Warning: {}", warning);
+            warnings.push(warning);
+        }
+    }
+
+    // Second pass, once every plan_ID is known
+    resolve_plan_inheritance(&mut toml_plans)?;
+
+    let mut plans = Vec::new();
+    let mut unmapped_course_codes = BTreeSet::new();
+    let mut bad_percentages = Vec::new();
 
+    for toml_plan in toml_plans {
         // Enrich courses with grade_details from grades_summary.json
         let courses = toml_plan
             .courses
             .into_iter()
-            .map(|c| {
+            .map(|mut c| {
                 let repo_id =
-                    resolve_repo_id(&lookup_table, &c.course_code, &toml_plan.info.plan_id);
+                    resolve_repo_id(&lookup_table, &c.course_code, &toml_plan.info.plan_id)
+                        .unwrap_or_else(|| {
+                            let code = c.course_code.trim().to_string();
+                            unmapped_course_codes.insert(code.clone());
+                            code
+                        });
 
                 // Select grade details if not already in TOML.
                 // NOTE: We look up grades_summary by repository ID, not by course_code.
-                let grade_details = c.grade_details.or_else(|| {
+                let grade_details = c.grade_details.take().or_else(|| {
                     select_grade_details(
                         &grades_summary,
                         &repo_id,
@@ -187,18 +512,24 @@ pub fn load_all_plans(data_dir: &Path) -> Result<Vec<Plan>> {
                     )
                 });
 
-                Course {
-                    repo_id,
-                    name: c.course_name,
-                    credit: c.credit,
-                    assessment_method: c.assessment_method,
-                    course_nature: c.course_nature,
-                    recommended_semester: c.recommended_year_semester,
-                    hours: c.hours,
-                    grade_details,
-                }
+                Course::from_toml(c, repo_id, grade_details)
             })
-            .collect();
+            .collect::<Vec<_>>();
+
+        for course in &courses {
+            let total = course
+                .grade_details
+                .as_deref()
+                .and_then(grade_total_mismatch);
+            if let Some(total) = total {
+                let message = format!(
+                    "grade percentages of {} in plan {} sum to {}%",
+                    course.repo_id, toml_plan.info.plan_id, total
+                );
+                eprintln!("Warning: {}", message);
+                bad_percentages.push(message);
+            }
+        }
 
         plans.push(Plan {
             year: toml_plan.info.year,
@@ -211,7 +542,12 @@ pub fn load_all_plans(data_dir: &Path) -> Result<Vec<Plan>> {
     // Sort plans by year and major_code for deterministic processing
     plans.sort_by(|a, b| a.year.cmp(&b.year).then(a.major_code.cmp(&b.major_code)));
 
-    Ok(plans)
+    Ok(LoadedPlans {
+        plans,
+        unmapped_course_codes,
+        plan_warnings: warnings,
+        bad_percentages,
+    })
 }
 
 /// Config for shared categories and which repo IDs are index pages (no CourseInfo).
@@ -220,6 +556,25 @@ pub struct SharedCategoriesConfig {
     pub no_course_info_repo_ids: HashSet<String>,
 }
 
+/// Remove repeated repo IDs while preserving first-occurrence order.
+///
+/// Returns the deduplicated list and the IDs that were dropped as duplicates.
+fn dedup_repo_ids(repo_ids: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let mut seen = HashSet::new();
+    let mut unique = Vec::new();
+    let mut duplicates = Vec::new();
+
+    for repo_id in repo_ids {
+        if seen.insert(repo_id.clone()) {
+            unique.push(repo_id);
+        } else {
+            duplicates.push(repo_id);
+        }
+    }
+
+    (unique, duplicates)
+}
+
 /// Load shared_categories.toml if present.
 ///
 /// Returns default (empty categories, empty no_course_info set) if file doesn't exist or can't be parsed.
@@ -257,10 +612,20 @@ pub fn load_shared_categories(data_dir: &Path) -> SharedCategoriesConfig {
         categories: toml
             .categories
             .into_iter()
-            .map(|c| SharedCategory {
-                id: c.id,
-                title: c.title,
-                repo_ids: c.repo_ids,
+            .map(|c| {
+                let (repo_ids, duplicates) = dedup_repo_ids(c.repo_ids);
+                if !duplicates.is_empty() {
+                    eprintln!(
+                        "Warning: shared category '{}' lists duplicate repo_ids: {}",
+                        c.id,
+                        duplicates.join(", ")
+                    );
+                }
+                SharedCategory {
+                    id: c.id,
+                    title: c.title,
+                    repo_ids,
+                }
             })
             .collect(),
         no_course_info_repo_ids: toml.no_course_info_repo_ids.into_iter().collect(),
@@ -288,6 +653,117 @@ pub fn load_repos_list(repo_root: &Path) -> Result<HashSet<String>> {
         .collect())
 }
 
+/// Drift between `repos_list.txt` and the repos the data actually references.
+#[derive(Debug, Default, PartialEq)]
+pub struct ReposListReport {
+    /// Listed in repos_list.txt but not used by any plan or category
+    pub listed_unused: Vec<String>,
+    /// Used by a plan or category but missing from repos_list.txt
+    pub referenced_unlisted: Vec<String>,
+}
+
+/// Compare `repos_set` against every repo ID referenced by the plans and the
+/// shared categories. Both lists are sorted.
+pub fn reconcile_repos_list(
+    repos_set: &HashSet<String>,
+    plans: &[Plan],
+    shared_categories: &SharedCategoriesConfig,
+) -> ReposListReport {
+    let referenced: HashSet<&String> = plans
+        .iter()
+        .flat_map(|plan| plan.courses.iter().map(|c| &c.repo_id))
+        .chain(
+            shared_categories
+                .categories
+                .iter()
+                .flat_map(|cat| cat.repo_ids.iter()),
+        )
+        .collect();
+
+    let mut listed_unused: Vec<String> = repos_set
+        .iter()
+        .filter(|repo_id| !referenced.contains(repo_id))
+        .cloned()
+        .collect();
+    let mut referenced_unlisted: Vec<String> = referenced
+        .into_iter()
+        .filter(|repo_id| !repos_set.contains(*repo_id))
+        .cloned()
+        .collect();
+    listed_unused.sort();
+    referenced_unlisted.sort();
+
+    ReposListReport {
+        listed_unused,
+        referenced_unlisted,
+    }
+}
+
+/// Repo IDs with a cached README under `repos_dir` that no plan or shared
+/// category references, sorted. Works for both cache layouts.
+pub fn find_orphaned_readmes(
+    repos_dir: &Path,
+    plans: &[Plan],
+    shared_categories: &SharedCategoriesConfig,
+) -> Vec<String> {
+    let referenced: HashSet<&str> = plans
+        .iter()
+        .flat_map(|plan| plan.courses.iter().map(|c| c.repo_id.as_str()))
+        .chain(
+            shared_categories
+                .categories
+                .iter()
+                .flat_map(|cat| cat.repo_ids.iter().map(String::as_str)),
+        )
+        .collect();
+
+    let mut orphans: Vec<String> = WalkDir::new(repos_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "mdx"))
+        .filter_map(|e| Some(e.path().file_stem()?.to_str()?.to_string()))
+        .filter(|repo_id| !referenced.contains(repo_id.as_str()))
+        .collect();
+    orphans.sort();
+    orphans.dedup();
+    orphans
+}
+
+/// Check the inputs of a run before doing any work.
+///
+/// `plans/` under `data_dir` and `repos_dir` itself are required; every
+/// missing one is listed in a single [`FumaError::MissingInputs`]. Absent
+/// optional files only print a warning. `repos_list.txt` is looked up next to
+/// `repos_dir`, in the repository root.
+pub fn preflight(data_dir: &Path, repos_dir: &Path) -> Result<()> {
+    let mut optional = [
+        "grades_summary.json",
+        "lookup_table.toml",
+        "shared_categories.toml",
+    ]
+    .map(|name| data_dir.join(name))
+    .to_vec();
+    optional.push(
+        repos_dir
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("repos_list.txt"),
+    );
+    for path in optional.iter().filter(|p| !p.exists()) {
+        eprintln!("Warning: optional input {} not found", path.display());
+    }
+
+    let missing: Vec<_> = [data_dir.join("plans"), repos_dir.to_path_buf()]
+        .into_iter()
+        .filter(|dir| !dir.is_dir())
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(FumaError::MissingInputs(missing))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -453,7 +929,7 @@ mod tests {
         lookup_table.insert("COURSE1".to_string(), mapping);
 
         let repo_id = resolve_repo_id(&lookup_table, "COURSE1", "PLAN_A");
-        assert_eq!(repo_id, "REPO_A");
+        assert_eq!(repo_id.as_deref(), Some("REPO_A"));
     }
 
     #[test]
@@ -464,15 +940,97 @@ mod tests {
         lookup_table.insert("COURSE1".to_string(), mapping);
 
         let repo_id = resolve_repo_id(&lookup_table, "COURSE1", "PLAN_B");
-        assert_eq!(repo_id, "REPO_DEFAULT");
+        assert_eq!(repo_id.as_deref(), Some("REPO_DEFAULT"));
+    }
+
+    #[test]
+    fn test_resolve_repo_id_wildcard() {
+        let lookup_table: LookupTable = toml::from_str(
+            r#"
+"AUTO*" = { DEFAULT = "AUTO-FAMILY" }
+"AUTO3*" = { DEFAULT = "AUTO3-FAMILY", PLAN_B = "AUTO3-B" }
+AUTO3001 = { DEFAULT = "AUTO3001" }
+"#,
+        )
+        .unwrap();
+
+        let resolve = |code, plan| resolve_repo_id(&lookup_table, code, plan);
+        assert_eq!(
+            resolve("AUTO1001", "PLAN_A").as_deref(),
+            Some("AUTO-FAMILY")
+        );
+        // The longest matching prefix wins, with its plan-specific entry first
+        assert_eq!(
+            resolve("AUTO3002", "PLAN_A").as_deref(),
+            Some("AUTO3-FAMILY")
+        );
+        assert_eq!(resolve("AUTO3002", "PLAN_B").as_deref(), Some("AUTO3-B"));
+        assert_eq!(resolve("COMP1001", "PLAN_A"), None);
+    }
+
+    #[test]
+    fn test_resolve_repo_id_exact_over_wildcard() {
+        let lookup_table: LookupTable = toml::from_str(
+            r#"
+"AUTO*" = { DEFAULT = "AUTO-FAMILY", PLAN_A = "AUTO-A" }
+AUTO1001 = { DEFAULT = "AUTO1001-REPO" }
+AUTO1002 = { PLAN_C = "AUTO1002-C" }
+"#,
+        )
+        .unwrap();
+
+        let resolve = |code, plan| resolve_repo_id(&lookup_table, code, plan);
+        assert_eq!(
+            resolve("AUTO1001", "PLAN_A").as_deref(),
+            Some("AUTO1001-REPO")
+        );
+        // An exact entry without a match for the plan falls through to wildcards
+        assert_eq!(resolve("AUTO1002", "PLAN_C").as_deref(), Some("AUTO1002-C"));
+        assert_eq!(resolve("AUTO1002", "PLAN_A").as_deref(), Some("AUTO-A"));
+    }
+
+    #[test]
+    fn test_resolve_repo_id_normalizes_course_code() {
+        let lookup_table: LookupTable = toml::from_str(
+            r#"
+MATH101 = { DEFAULT = "Math101-Repo" }
+"auto*" = { DEFAULT = "AUTO-FAMILY" }
+"#,
+        )
+        .unwrap();
+
+        let resolve = |code| resolve_repo_id(&lookup_table, code, "PLAN_A");
+        // Whitespace-padded code; the repo ID keeps the table's spelling
+        assert_eq!(resolve(" MATH101 ").as_deref(), Some("Math101-Repo"));
+        // Case mismatch, for exact and wildcard keys
+        assert_eq!(resolve("math101").as_deref(), Some("Math101-Repo"));
+        assert_eq!(resolve("AUTO1001").as_deref(), Some("AUTO-FAMILY"));
+    }
+
+    #[test]
+    fn test_select_grade_details_normalizes_repo_id() {
+        let mut grades_summary = HashMap::new();
+        grades_summary.insert(
+            "MATH101".to_string(),
+            HashMap::from([(
+                "default".to_string(),
+                vec![create_test_grade_detail("Exam", "100%")],
+            )]),
+        );
+
+        for repo_id in [" MATH101 ", "math101"] {
+            let details = select_grade_details(&grades_summary, repo_id, "2023", "CS", "计算机");
+            assert_eq!(details.unwrap()[0].name, "Exam");
+        }
     }
 
     #[test]
     fn test_resolve_repo_id_identity_fallback() {
         let lookup_table: LookupTable = HashMap::new();
 
+        // No entry: load_all_plans uses the course code itself
         let repo_id = resolve_repo_id(&lookup_table, "COURSE1", "PLAN_A");
-        assert_eq!(repo_id, "COURSE1");
+        assert_eq!(repo_id, None);
     }
 
     #[test]
@@ -498,7 +1056,7 @@ mod tests {
         writeln!(file, "MATH101").unwrap();
         writeln!(file, "PHYS201").unwrap();
         writeln!(file, "  CHEM301  ").unwrap(); // with whitespace
-        writeln!(file, "").unwrap(); // empty line
+        writeln!(file).unwrap(); // empty line
         writeln!(file, "CS401").unwrap();
 
         let result = load_repos_list(&temp_dir).unwrap();
@@ -628,4 +1186,588 @@ PLAN_A = "REPO2A"
 
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
+
+    #[test]
+    fn test_dedup_repo_ids_preserves_order() {
+        let (unique, duplicates) =
+            dedup_repo_ids(vec!["B".to_string(), "A".to_string(), "B".to_string()]);
+        assert_eq!(unique, vec!["B".to_string(), "A".to_string()]);
+        assert_eq!(duplicates, vec!["B".to_string()]);
+    }
+
+    #[test]
+    fn test_load_shared_categories_dedups_repo_ids() {
+        use std::env;
+        let temp_dir = env::temp_dir().join("test_shared_categories_dedup");
+        let _ = std::fs::create_dir_all(&temp_dir);
+
+        fs::write(
+            temp_dir.join("shared_categories.toml"),
+            r#"
+[[categories]]
+id = "general"
+title = "General"
+repo_ids = ["GEN101", "GEN102", "GEN101"]
+"#,
+        )
+        .unwrap();
+
+        let config = load_shared_categories(&temp_dir);
+
+        assert_eq!(config.categories.len(), 1);
+        assert_eq!(
+            config.categories[0].repo_ids,
+            vec!["GEN101".to_string(), "GEN102".to_string()]
+        );
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_normalize_variant_key() {
+        assert_eq!(
+            normalize_variant_key(" 2023_Computer  Science "),
+            "2023_Computer_Science"
+        );
+        assert_eq!(
+            normalize_variant_key("2023__自动化　专业"),
+            "2023_自动化_专业"
+        );
+        assert_eq!(normalize_variant_key("default"), "default");
+    }
+
+    #[test]
+    fn test_select_grade_details_space_vs_underscore() {
+        let mut grades_summary = HashMap::new();
+        let mut course_entry = HashMap::new();
+
+        course_entry.insert(
+            "2023_Computer_Science".to_string(),
+            vec![create_test_grade_detail("Project", "80%")],
+        );
+        course_entry.insert(
+            "default".to_string(),
+            vec![create_test_grade_detail("Exam", "50%")],
+        );
+
+        grades_summary.insert("PROG202".to_string(), course_entry);
+
+        let result =
+            select_grade_details(&grades_summary, "PROG202", "2023", "CS", "Computer Science");
+
+        assert_eq!(result.unwrap()[0].name, "Project");
+    }
+
+    #[test]
+    fn test_load_grades_summary_normalizes_keys() {
+        use std::env;
+        let temp_dir = env::temp_dir().join("test_grades_normalized_keys");
+        let _ = std::fs::create_dir_all(&temp_dir);
+
+        let grades_data = serde_json::json!({
+            "MATH101": {
+                " 2023_Computer  Science ": [
+                    {"name": "Exam", "percent": "70%"}
+                ]
+            }
+        });
+        fs::write(
+            temp_dir.join("grades_summary.json"),
+            grades_data.to_string(),
+        )
+        .unwrap();
+
+        let result = load_grades_summary(&temp_dir);
+        assert!(result["MATH101"].contains_key("2023_Computer_Science"));
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_reconcile_repos_list() {
+        let course = |repo_id: &str| Course {
+            repo_id: repo_id.to_string(),
+            name: repo_id.to_string(),
+            english_name: None,
+            credit: None,
+            assessment_method: None,
+            course_nature: None,
+            recommended_semester: None,
+            hours: None,
+            grade_details: None,
+            extra: HashMap::new(),
+        };
+        let plans = vec![Plan {
+            year: "2023".to_string(),
+            major_code: "CS".to_string(),
+            major_name: "Computer Science".to_string(),
+            courses: vec![course("COMP1001"), course("MATH1001")],
+        }];
+        let shared = SharedCategoriesConfig {
+            categories: vec![SharedCategory {
+                id: "general".to_string(),
+                title: "通识".to_string(),
+                repo_ids: vec!["GEN1001".to_string()],
+            }],
+            no_course_info_repo_ids: HashSet::new(),
+        };
+        let repos_set: HashSet<String> = ["COMP1001", "GEN1001", "OLD9999"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(
+            reconcile_repos_list(&repos_set, &plans, &shared),
+            ReposListReport {
+                listed_unused: vec!["OLD9999".to_string()],
+                referenced_unlisted: vec!["MATH1001".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_preflight_complete_setup() {
+        use std::env;
+        let root = env::temp_dir().join("test_preflight_complete");
+        let _ = fs::remove_dir_all(&root);
+        let data_dir = root.join("hoa-major-data");
+        fs::create_dir_all(data_dir.join("plans")).unwrap();
+        fs::create_dir_all(root.join("repos")).unwrap();
+        for name in [
+            "grades_summary.json",
+            "lookup_table.toml",
+            "shared_categories.toml",
+        ] {
+            fs::write(data_dir.join(name), "").unwrap();
+        }
+        fs::write(root.join("repos_list.txt"), "").unwrap();
+
+        assert!(preflight(&data_dir, &root.join("repos")).is_ok());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_preflight_missing_plans() {
+        use std::env;
+        let root = env::temp_dir().join("test_preflight_missing_plans");
+        let _ = fs::remove_dir_all(&root);
+        let data_dir = root.join("hoa-major-data");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(root.join("repos")).unwrap();
+
+        let err = preflight(&data_dir, &root.join("repos")).unwrap_err();
+        assert!(
+            matches!(&err, FumaError::MissingInputs(paths) if paths == &vec![data_dir.join("plans")])
+        );
+        assert!(err.to_string().contains("plans"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_load_all_plans_reports_broken_plan_path() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_all_plans_broken");
+        let _ = fs::remove_dir_all(&data_dir);
+        let plans_dir = data_dir.join("plans");
+        fs::create_dir_all(&plans_dir).unwrap();
+        fs::write(
+            plans_dir.join("2023_CS.toml"),
+            "courses = []\n[info]\nyear = \"2023\"\nmajor_code = \"CS\"\nmajor_name = \"计算机\"\nplan_ID = \"2023CS\"\n",
+        )
+        .unwrap();
+        fs::write(plans_dir.join("2023_EE.toml"), "[info\nyear = 2023\n").unwrap();
+
+        let err = load_all_plans(&data_dir, &SemesterMapping::default()).unwrap_err();
+        assert!(
+            matches!(&err, FumaError::PlanParse { path, .. } if path == &plans_dir.join("2023_EE.toml"))
+        );
+        assert!(err.to_string().contains("2023_EE.toml"));
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_grade_total_mismatch_exact_hundred() {
+        let details = vec![
+            create_test_grade_detail("Exam", "70%"),
+            create_test_grade_detail("Homework", " 30 % "),
+        ];
+        assert_eq!(grade_total_mismatch(&details), None);
+
+        let details = vec![
+            create_test_grade_detail("Exam", "７０％"),
+            create_test_grade_detail("Homework", "30％"),
+        ];
+        assert_eq!(grade_total_mismatch(&details), None);
+    }
+
+    #[test]
+    fn test_grade_total_mismatch_off_by_ten() {
+        let details = vec![
+            create_test_grade_detail("Exam", "60%"),
+            create_test_grade_detail("Homework", "30%"),
+        ];
+        assert_eq!(grade_total_mismatch(&details), Some(90.0));
+
+        let details = vec![
+            create_test_grade_detail("Exam", "80%"),
+            create_test_grade_detail("Homework", "30%"),
+        ];
+        assert_eq!(grade_total_mismatch(&details), Some(110.0));
+    }
+
+    #[test]
+    fn test_grade_total_mismatch_missing_percents() {
+        let details = vec![
+            create_test_grade_detail("Exam", "70%"),
+            GradeDetail {
+                name: "Homework".to_string(),
+                percent: None,
+            },
+        ];
+        assert_eq!(grade_total_mismatch(&details), None);
+
+        let details = vec![
+            create_test_grade_detail("Exam", "70%"),
+            create_test_grade_detail("Homework", "见教学大纲"),
+        ];
+        assert_eq!(grade_total_mismatch(&details), None);
+        assert_eq!(grade_total_mismatch(&[]), None);
+    }
+
+    #[test]
+    fn test_duplicate_course_codes() {
+        let plan: TomlPlan = toml::from_str(
+            r#"
+[info]
+year = "2023"
+major_code = "CS"
+major_name = "计算机科学与技术"
+plan_ID = "2023CS"
+
+[[courses]]
+course_code = "COMP1001"
+course_name = "程序设计"
+
+[[courses]]
+course_code = "MATH1001"
+course_name = "高等数学"
+
+[[courses]]
+course_code = "COMP1001"
+course_name = "程序设计"
+
+[[courses]]
+course_code = "COMP1001"
+course_name = "程序设计（重复）"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(duplicate_course_codes(&plan.courses), vec!["COMP1001"]);
+        assert!(duplicate_course_codes(&plan.courses[..2]).is_empty());
+    }
+
+    /// Write a plan TOML with the given extra `[info]` lines and courses
+    fn write_plan(plans_dir: &Path, plan_id: &str, info: &str, courses: &[(&str, &str)]) {
+        let mut toml = format!(
+            "[info]\nyear = \"2023\"\nmajor_code = \"{0}\"\nmajor_name = \"{0}\"\nplan_ID = \"{0}\"\n{1}\n",
+            plan_id, info
+        );
+        for (code, name) in courses {
+            toml.push_str(&format!(
+                "[[courses]]\ncourse_code = \"{}\"\ncourse_name = \"{}\"\n",
+                code, name
+            ));
+        }
+        fs::write(plans_dir.join(format!("{}.toml", plan_id)), toml).unwrap();
+    }
+
+    fn course_names(plan: &Plan) -> Vec<(&str, &str)> {
+        plan.courses
+            .iter()
+            .map(|c| (c.repo_id.as_str(), c.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_load_all_plans_extends_base_plan() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_all_plans_extends");
+        let _ = fs::remove_dir_all(&data_dir);
+        let plans_dir = data_dir.join("plans");
+        fs::create_dir_all(&plans_dir).unwrap();
+        write_plan(
+            &plans_dir,
+            "BASE",
+            "",
+            &[("COMP1001", "程序设计"), ("MATH1001", "高等数学")],
+        );
+        write_plan(
+            &plans_dir,
+            "CS",
+            "extends = \"BASE\"",
+            &[("MATH1001", "高等数学 A"), ("COMP2001", "数据结构")],
+        );
+        // Inheritance chains resolve through the intermediate plan
+        write_plan(&plans_dir, "CS2", "extends = \"CS\"", &[]);
+
+        let plans = load_all_plans(&data_dir, &SemesterMapping::default())
+            .unwrap()
+            .plans;
+        let by_code: HashMap<&str, &Plan> =
+            plans.iter().map(|p| (p.major_code.as_str(), p)).collect();
+
+        assert_eq!(
+            course_names(by_code["BASE"]),
+            vec![("COMP1001", "程序设计"), ("MATH1001", "高等数学")]
+        );
+        let expected = vec![
+            ("COMP1001", "程序设计"),
+            ("MATH1001", "高等数学 A"),
+            ("COMP2001", "数据结构"),
+        ];
+        assert_eq!(course_names(by_code["CS"]), expected);
+        assert_eq!(course_names(by_code["CS2"]), expected);
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_load_all_plans_missing_base_plan() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_all_plans_missing_base");
+        let _ = fs::remove_dir_all(&data_dir);
+        let plans_dir = data_dir.join("plans");
+        fs::create_dir_all(&plans_dir).unwrap();
+        write_plan(
+            &plans_dir,
+            "CS",
+            "extends = \"NOPE\"",
+            &[("COMP1001", "程序设计")],
+        );
+
+        let err = load_all_plans(&data_dir, &SemesterMapping::default()).unwrap_err();
+        assert!(matches!(
+            &err,
+            FumaError::PlanExtends { plan_id, base, .. } if plan_id == "CS" && base == "NOPE"
+        ));
+
+        // A plan extending itself is a cycle rather than a missing base
+        write_plan(&plans_dir, "CS", "extends = \"CS\"", &[]);
+        let err = load_all_plans(&data_dir, &SemesterMapping::default()).unwrap_err();
+        assert!(err.to_string().contains("cycle"));
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_unknown_semesters() {
+        let semesters = SemesterMapping::default();
+        assert!(unknown_semesters(&semesters, "第三学年秋季，第四学年秋季").is_empty());
+        assert!(unknown_semesters(&semesters, "3").is_empty());
+        assert!(unknown_semesters(&semesters, "").is_empty());
+        assert_eq!(
+            unknown_semesters(&semesters, "第一学年秋季, 第三学年秋李"),
+            vec!["第三学年秋李"]
+        );
+    }
+
+    #[test]
+    fn test_load_all_plans_collects_unmapped_course_codes() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_all_plans_unmapped");
+        let _ = fs::remove_dir_all(&data_dir);
+        let plans_dir = data_dir.join("plans");
+        fs::create_dir_all(&plans_dir).unwrap();
+        fs::write(
+            data_dir.join("lookup_table.toml"),
+            "[COMP1001]\nDEFAULT = \"COMP1001-REPO\"\n\n[MATH1001]\nOTHER = \"MATH-OTHER\"\n",
+        )
+        .unwrap();
+        write_plan(
+            &plans_dir,
+            "CS",
+            "",
+            &[
+                ("COMP1001", "程序设计"),
+                ("MATH1001", "高等数学"),
+                ("PHYS1001", "大学物理"),
+            ],
+        );
+
+        let loaded = load_all_plans(&data_dir, &SemesterMapping::default()).unwrap();
+        let repo_ids: Vec<&str> = loaded.plans[0]
+            .courses
+            .iter()
+            .map(|c| c.repo_id.as_str())
+            .collect();
+        assert_eq!(repo_ids, vec!["COMP1001-REPO", "MATH1001", "PHYS1001"]);
+        assert_eq!(
+            loaded.unmapped_course_codes.into_iter().collect::<Vec<_>>(),
+            vec!["MATH1001", "PHYS1001"]
+        );
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_load_all_plans_parallel_matches_serial() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_all_plans_parallel");
+        let _ = fs::remove_dir_all(&data_dir);
+        let plans_dir = data_dir.join("plans");
+        fs::create_dir_all(&plans_dir).unwrap();
+        for plan_id in ["EE", "CS", "ME", "AUTO", "MATH", "BIO"] {
+            let code = format!("{}1001", plan_id);
+            let name = format!("{} 导论", plan_id);
+            write_plan(
+                &plans_dir,
+                plan_id,
+                "",
+                &[(&code, &name), ("PE1001", "体育")],
+            );
+        }
+
+        // Serial reference: parse one file at a time, then sort the same way
+        let mut expected: Vec<(String, Vec<String>)> = WalkDir::new(&plans_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .map(|e| parse_plan_file(e.path()).unwrap())
+            .map(|plan| {
+                let names = plan.courses.into_iter().map(|c| c.course_name).collect();
+                (plan.info.major_code, names)
+            })
+            .collect();
+        expected.sort();
+
+        let plans = load_all_plans(&data_dir, &SemesterMapping::default())
+            .unwrap()
+            .plans;
+        let actual: Vec<(String, Vec<String>)> = plans
+            .into_iter()
+            .map(|plan| {
+                let names = plan.courses.into_iter().map(|c| c.name).collect();
+                (plan.major_code, names)
+            })
+            .collect();
+        assert_eq!(actual, expected);
+        assert_eq!(actual[0].0, "AUTO");
+        assert_eq!(actual[5].0, "ME");
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_load_all_plans_reports_first_bad_file() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_all_plans_first_error");
+        let _ = fs::remove_dir_all(&data_dir);
+        let plans_dir = data_dir.join("plans");
+        fs::create_dir_all(&plans_dir).unwrap();
+        for name in ["d_bad", "b_bad", "c_bad", "a_ok"] {
+            let content = if name.ends_with("ok") {
+                "courses = []\n[info]\nyear = \"2023\"\nmajor_code = \"CS\"\nmajor_name = \"CS\"\nplan_ID = \"2023CS\"\n"
+            } else {
+                "[info\n"
+            };
+            fs::write(plans_dir.join(format!("{}.toml", name)), content).unwrap();
+        }
+
+        for _ in 0..5 {
+            match load_all_plans(&data_dir, &SemesterMapping::default()) {
+                Err(FumaError::PlanParse { path, .. }) => {
+                    assert_eq!(path, plans_dir.join("b_bad.toml"))
+                }
+                other => panic!("expected a parse error, got {:?}", other.map(|l| l.plans)),
+            }
+        }
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_load_semester_mapping() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_semester_mapping");
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(&data_dir).unwrap();
+        assert!(load_semester_mapping(&data_dir).is_none());
+
+        fs::write(
+            data_dir.join("semesters.toml"),
+            r#"
+[[semesters]]
+name = "第一学期"
+folder = "term-1"
+title = "Term 1"
+
+[[semesters]]
+name = "第二学期"
+folder = "term-2"
+title = "Term 2"
+"#,
+        )
+        .unwrap();
+
+        let mapping = load_semester_mapping(&data_dir).unwrap();
+        assert_eq!(mapping.entries().count(), 2);
+        assert_eq!(
+            get_semester_folder(&mapping, "第二学期"),
+            Some(("term-2", "Term 2"))
+        );
+        assert_eq!(get_semester_folder(&mapping, "第一学年秋季"), None);
+
+        fs::write(data_dir.join("semesters.toml"), "semesters = 1").unwrap();
+        assert!(load_semester_mapping(&data_dir).is_none());
+        fs::write(data_dir.join("semesters.toml"), "semesters = []").unwrap();
+        assert!(load_semester_mapping(&data_dir).is_none());
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_load_all_plans_integer_semester() {
+        use std::env;
+        let data_dir = env::temp_dir().join("test_load_all_plans_integer_semester");
+        let _ = fs::remove_dir_all(&data_dir);
+        let plans_dir = data_dir.join("plans");
+        fs::create_dir_all(&plans_dir).unwrap();
+        fs::write(
+            plans_dir.join("CS.toml"),
+            r#"
+[info]
+year = "2023"
+major_code = "CS"
+major_name = "计算机"
+plan_ID = "CS"
+
+[[courses]]
+course_code = "COMP1001"
+course_name = "程序设计"
+recommended_year_semester = 3
+
+[[courses]]
+course_code = "MATH1001"
+course_name = "高等数学"
+recommended_year_semester = "第一学年秋季"
+"#,
+        )
+        .unwrap();
+
+        let plans = load_all_plans(&data_dir, &SemesterMapping::default())
+            .unwrap()
+            .plans;
+        let semesters: Vec<Option<&str>> = plans[0]
+            .courses
+            .iter()
+            .map(|c| c.recommended_semester.as_deref())
+            .collect();
+        assert_eq!(semesters, [Some("3"), Some("第一学年秋季")]);
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
 }
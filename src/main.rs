@@ -3,19 +3,40 @@
 //! This binary replaces the Python-based page generation system with a high-performance
 //! Rust implementation that avoids the N+1 query problem by loading all data upfront.
 
+mod config;
 mod constants;
+mod diff;
 mod error;
 mod fetcher;
 mod formatter;
 mod generator;
 mod loader;
 mod models;
+mod pipeline;
+mod report;
 mod tree;
 
 use error::Result;
 use std::path::Path;
+use std::sync::Arc;
 use std::{env, fs};
 
+/// Return the value following `flag` on the command line, if any
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Load fuma.toml, exiting with an error if it exists but is invalid
+fn load_config_or_exit(repo_root: &Path) -> config::Config {
+    config::load_config(repo_root).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    })
+}
+
 /// Main entry point for the Fuma course page generator.
 ///
 /// This program:
@@ -30,25 +51,136 @@ async fn main() -> Result<()> {
     // Check for --fetch flag
     let args: Vec<String> = env::args().collect();
     let should_fetch = args.contains(&"--fetch".to_string());
+    let max_concurrency_per_host = match flag_value(&args, "--max-concurrency-per-host") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(n) if n > 0 => Some(n),
+            _ => {
+                eprintln!("Error: --max-concurrency-per-host expects a positive integer");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let fetch_attempts = match flag_value(&args, "--fetch-attempts") {
+        Some(value) => match value.parse::<u32>() {
+            Ok(n) if n > 0 => Some(n),
+            _ => {
+                eprintln!("Error: --fetch-attempts expects a positive integer");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Compare two generated docs trees and exit: --diff-docs <old> <new>
+    if let Some(i) = args.iter().position(|arg| arg == "--diff-docs") {
+        let (Some(old_dir), Some(new_dir)) = (args.get(i + 1), args.get(i + 2)) else {
+            eprintln!("Error: --diff-docs expects <old_dir> <new_dir>");
+            std::process::exit(1);
+        };
+        let docs_diff = diff::diff_docs(Path::new(old_dir), Path::new(new_dir))?;
+        for (label, paths) in [
+            ("+", &docs_diff.added),
+            ("-", &docs_diff.removed),
+            ("~", &docs_diff.changed),
+        ] {
+            for path in paths {
+                println!("{} {}", label, path.display());
+            }
+        }
+        if docs_diff.is_empty() {
+            println!("No differences");
+            return Ok(());
+        }
+        println!(
+            "{} added, {} removed, {} changed",
+            docs_diff.added.len(),
+            docs_diff.removed.len(),
+            docs_diff.changed.len()
+        );
+        return Ok(());
+    }
 
     let repo_root = Path::new(".").to_path_buf();
 
+    // List unformatted MDX files without writing, for CI: --check-format
+    if args.contains(&"--check-format".to_string()) {
+        let config = load_config_or_exit(&repo_root);
+        let unformatted =
+            formatter::check_all_mdx_files(&repo_root.join("content/docs"), &config.format)?;
+        for path in &unformatted {
+            println!("{}", path.display());
+        }
+        if !unformatted.is_empty() {
+            eprintln!("{} MDX files need formatting", unformatted.len());
+            std::process::exit(1);
+        }
+        println!("All MDX files are formatted");
+        return Ok(());
+    }
+
     println!("Repository root: {}", repo_root.display());
 
     let repos_dir = repo_root.join("repos");
+    let mut config = load_config_or_exit(&repo_root);
+    config.generator.index_only = args.contains(&"--index-only".to_string());
+    if let Some(changed) = flag_value(&args, "--changed") {
+        config.generator.changed_repo_ids = Some(
+            changed
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+        );
+    }
+    if let Some(path) = flag_value(&args, "--report") {
+        config.report.path = Some(path.into());
+    }
+    if args.contains(&"--sharded-repos".to_string()) {
+        config.cache.layout = config::ReposLayout::Sharded;
+    }
 
-    // Fetch repos from GitHub if --fetch flag is provided
-    if should_fetch {
-        println!("\n=== Fetching repos from GitHub ===");
+    // Repo data comes from GitHub, or a local --source-dir; it's needed by
+    // --fetch and by --refetch-corrupt
+    let refetch_corrupt = args.contains(&"--refetch-corrupt".to_string());
+    let source = if should_fetch || refetch_corrupt {
+        let source = match flag_value(&args, "--source-dir") {
+            Some(dir) => fetcher::Source::LocalDir(dir.into()),
+            None => {
+                let Some(token) = fetcher::resolve_github_token() else {
+                    eprintln!("Error: No GitHub token found!");
+                    eprintln!(
+                        "Please set PERSONAL_ACCESS_TOKEN, GITHUB_TOKEN, or login via `gh auth login`"
+                    );
+                    std::process::exit(1);
+                };
+
+                let mut github = fetcher::GitHubFetcher::new(token)?;
+                if let Some(per_host) = max_concurrency_per_host {
+                    github = github.with_max_concurrency_per_host(per_host);
+                }
+                if let Some(attempts) = fetch_attempts {
+                    github = github.with_max_attempts(attempts);
+                }
+                if let Some(ref base_url) = config.fetch.base_url {
+                    github = github.with_base_url(base_url);
+                }
+                github = github.with_raw_content(config.fetch.raw_content);
+                fetcher::Source::GitHub(github)
+            }
+        };
+        Some(Arc::new(source))
+    } else {
+        None
+    };
+    if refetch_corrupt {
+        config.cache.refetch_source = source.clone();
+    }
 
-        let token = fetcher::resolve_github_token();
-        if token.is_none() {
-            eprintln!("Error: No GitHub token found!");
-            eprintln!(
-                "Please set PERSONAL_ACCESS_TOKEN, GITHUB_TOKEN, or login via `gh auth login`"
-            );
-            std::process::exit(1);
-        }
+    // Fetch repos if --fetch flag is provided
+    if let Some(source) = source.filter(|_| should_fetch) {
+        println!("\n=== Fetching repos from {} ===", source.describe());
 
         // Load repos list
         let repos_list_path = repo_root.join("repos_list.txt");
@@ -67,91 +199,49 @@ async fn main() -> Result<()> {
         println!("Found {} repositories in repos_list.txt", repos_list.len());
 
         // Fetch repos (20 concurrent requests)
+        let fetch_options = fetcher::FetchOptions {
+            fetch_tags: config.generator.tag_txt_tags,
+            layout: config.cache.layout,
+            readme_paths: config.fetch.readme_paths.clone(),
+            readme_branch: config.fetch.readme_branch.clone(),
+            force: args.contains(&"--force-fetch".to_string()),
+        };
         fetcher::fetch_all_repos(
-            token.unwrap(),
-            "HITSZ-OpenAuto",
+            source,
+            constants::GITHUB_ORG,
             &repos_list,
             &repos_dir,
             20,
+            &fetch_options,
+            Some(&mut |done, total, repo| println!("[{}/{}] {}", done, total, repo)),
         )
         .await?;
 
         println!("✓ Repos fetched successfully\n");
     }
 
-    // Check if repos directory exists
-    if !repos_dir.exists() {
-        eprintln!("\nError: 'repos' directory not found!");
-        eprintln!("This tool requires the repos directory to be populated first.");
-        eprintln!("Please run with --fetch flag or ensure repos have been fetched.");
-        eprintln!("\nExpected directory: {}", repos_dir.display());
-        std::process::exit(1);
-    }
-
-    // Load repos list (optional filter)
-    let repos_set = loader::load_repos_list(&repo_root)?;
-    if repos_set.is_empty() {
-        println!("No repos_list.txt found - will process all available courses");
-    } else {
-        println!(
-            "Loaded {} repositories from repos_list.txt",
-            repos_set.len()
-        );
-    }
-
-    // Load all training plans from TOML files
-    let data_dir = repo_root.join("hoa-major-data");
-    let plans = loader::load_all_plans(&data_dir)?;
-    println!("Loaded {} training plans", plans.len());
-
-    let shared_categories_config = loader::load_shared_categories(&data_dir);
-    if !shared_categories_config.categories.is_empty() {
-        println!("Loaded {} shared categories", shared_categories_config.categories.len());
-    }
-
-    let grades_summary = loader::load_grades_summary(&data_dir);
-
-    // Filter courses by repos_set (if repos_list.txt exists)
-    let filtered_plans: Vec<_> = if repos_set.is_empty() {
-        plans
-    } else {
-        plans
-            .into_iter()
-            .map(|mut plan| {
-                plan.courses.retain(|c| repos_set.contains(&c.repo_id));
-                plan
-            })
-            .collect()
+    let summary = match pipeline::run_pipeline(&repo_root, &config).await {
+        Err(e @ error::FumaError::MissingInputs(_)) => {
+            eprintln!("\nError: {}", e);
+            if !repos_dir.exists() {
+                eprintln!("Please run with --fetch flag or ensure repos have been fetched.");
+            }
+            std::process::exit(1);
+        }
+        result => result?,
     };
-
-    let total_courses: usize = filtered_plans.iter().map(|p| p.courses.len()).sum();
-    println!("Total courses to process: {}", total_courses);
-
-    // Generate course pages
-    let docs_dir = repo_root.join("content/docs");
-    if !docs_dir.exists() {
-        println!("Creating output directory: {}", docs_dir.display());
-        fs::create_dir_all(&docs_dir)?;
+    println!(
+        "Generated {} courses from {} plans, formatted {} MDX files",
+        summary.courses,
+        summary.plans,
+        summary.formatted.len()
+    );
+
+    if let Some(ref path) = config.report.path {
+        summary.report.write(path)?;
+        println!("Wrote report to {}", path.display());
     }
 
-    println!("Generating course pages...");
-    generator::generate_course_pages(
-        &filtered_plans,
-        &shared_categories_config.categories,
-        &shared_categories_config.no_course_info_repo_ids,
-        &grades_summary,
-        &repos_dir,
-        &docs_dir,
-        &repos_set,
-    )
-    .await?;
-    println!("Course pages generated successfully");
-
-    // Format MDX files
-    println!("Formatting MDX files...");
-    let modified_count = formatter::format_all_mdx_files(&docs_dir)?;
-    println!("Formatted {} MDX files", modified_count);
-
     println!("\n✓ Done! All pages generated and formatted.");
 
     Ok(())
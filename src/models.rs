@@ -1,8 +1,12 @@
+use crate::config::ZeroCredit;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Deserialize)]
 pub struct TomlPlan {
     pub info: PlanInfo,
+    /// May be left out by a plan that only inherits via `extends`
+    #[serde(default)]
     pub courses: Vec<TomlCourse>,
 }
 
@@ -13,18 +17,82 @@ pub struct PlanInfo {
     pub major_name: String,
     #[serde(rename = "plan_ID")]
     pub plan_id: String,
+    /// `plan_ID` of a plan whose courses this plan inherits
+    #[serde(default)]
+    pub extends: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct TomlCourse {
     pub course_code: String,
     pub course_name: String,
+    pub english_name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_credit")]
     pub credit: Option<f64>,
     pub assessment_method: Option<String>,
     pub course_nature: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_semester")]
     pub recommended_year_semester: Option<String>,
     pub hours: Option<HourDistribution>,
     pub grade_details: Option<Vec<GradeDetail>>,
+    /// Fields not modeled above, e.g. `department`, kept as written
+    #[serde(flatten)]
+    pub extra: HashMap<String, toml::Value>,
+}
+
+/// Accept `credit = 3`, `credit = "3.0"` or `credit = "3学分"`.
+///
+/// Anything else becomes `None` with a warning instead of failing the plan.
+fn deserialize_credit<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawCredit {
+        Number(f64),
+        Text(String),
+        Other(serde::de::IgnoredAny),
+    }
+
+    let credit = match RawCredit::deserialize(deserializer)? {
+        RawCredit::Number(credit) => Some(credit),
+        RawCredit::Text(text) => {
+            let text = text.trim();
+            let number_len = text
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(text.len());
+            let credit = text[..number_len].parse().ok();
+            if credit.is_none() {
+                eprintln!("Warning: ignoring unparseable credit {:?}", text);
+            }
+            credit
+        }
+        RawCredit::Other(_) => {
+            eprintln!("Warning: ignoring credit that is neither a number nor a string");
+            None
+        }
+    };
+    Ok(credit)
+}
+
+/// Accept `recommended_year_semester = 3` as well as the usual string, so
+/// numbered semesters work whether or not the source quotes them.
+fn deserialize_semester<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawSemester {
+        Number(i64),
+        Text(String),
+    }
+
+    Ok(
+        Option::<RawSemester>::deserialize(deserializer)?.map(|semester| match semester {
+            RawSemester::Number(n) => n.to_string(),
+            RawSemester::Text(text) => text,
+        }),
+    )
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -33,6 +101,25 @@ pub struct GradeDetail {
     pub percent: Option<String>,
 }
 
+impl GradeDetail {
+    /// The percent as plain ASCII number text, ready to parse.
+    ///
+    /// Fullwidth characters such as `％` and `７０` are folded to ASCII first,
+    /// then whitespace and the `%` sign are stripped.
+    pub fn percent_text(&self) -> Option<String> {
+        let percent: String = self
+            .percent
+            .as_deref()?
+            .chars()
+            .map(|c| match c {
+                '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+                _ => c,
+            })
+            .collect();
+        Some(percent.trim().trim_end_matches('%').trim().to_string())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct HourDistribution {
     pub theory: Option<u32>,
@@ -43,6 +130,23 @@ pub struct HourDistribution {
     pub tutoring: Option<u32>,
 }
 
+impl HourDistribution {
+    /// Total teaching hours across all categories, counting unset ones as zero
+    pub fn total(&self) -> u32 {
+        [
+            self.theory,
+            self.lab,
+            self.practice,
+            self.exercise,
+            self.computer,
+            self.tutoring,
+        ]
+        .into_iter()
+        .flatten()
+        .sum()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Plan {
     pub year: String,
@@ -62,12 +166,40 @@ pub struct SharedCategory {
 pub struct Course {
     pub repo_id: String,
     pub name: String,
+    pub english_name: Option<String>,
     pub credit: Option<f64>,
     pub assessment_method: Option<String>,
     pub course_nature: Option<String>,
     pub recommended_semester: Option<String>,
     pub hours: Option<HourDistribution>,
     pub grade_details: Option<Vec<GradeDetail>>,
+    /// Unmodeled fields from the plan TOML entry
+    pub extra: HashMap<String, toml::Value>,
+}
+
+impl Course {
+    /// Build a course from its plan TOML entry.
+    ///
+    /// `repo_id` and `grade_details` are resolved by the caller (lookup table
+    /// and grades summary); the TOML's own `grade_details` is ignored.
+    pub fn from_toml(
+        toml: TomlCourse,
+        repo_id: String,
+        grade_details: Option<Vec<GradeDetail>>,
+    ) -> Self {
+        Self {
+            repo_id,
+            name: toml.course_name,
+            english_name: toml.english_name,
+            credit: toml.credit,
+            assessment_method: toml.assessment_method,
+            course_nature: toml.course_nature,
+            recommended_semester: toml.recommended_year_semester,
+            hours: toml.hours,
+            grade_details,
+            extra: toml.extra,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -77,6 +209,10 @@ pub struct WorktreeData(pub std::collections::HashMap<String, FileMetadata>);
 pub struct FileMetadata {
     pub size: Option<u64>,
     pub time: Option<i64>,
+    /// Explicit download URL, e.g. a GitHub release asset, used instead of
+    /// the computed `raw/main` link
+    #[serde(default)]
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -85,6 +221,8 @@ pub struct FileNode {
     pub node_type: NodeType,
     pub children: Vec<FileNode>,
     pub url: Option<String>,
+    /// Alternate download URLs on failover hosts, in preference order
+    pub fallback_urls: Vec<String>,
     pub size: Option<u64>,
     pub date: Option<String>,
 }
@@ -95,24 +233,67 @@ pub enum NodeType {
     File,
 }
 
+/// Page frontmatter. Free-text fields are skipped by serde and written by
+/// [`Frontmatter::to_yaml`] as double-quoted scalars.
 #[derive(Debug, Serialize)]
 pub struct Frontmatter {
+    #[serde(skip)]
     pub title: String,
+    #[serde(skip)]
+    pub english_title: Option<String>,
+    #[serde(skip)]
     pub description: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub course: CourseMetadata,
 }
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CourseMetadata {
-    pub credit: f64,
+    #[serde(skip_serializing_if = "CreditValue::is_omitted")]
+    pub credit: CreditValue,
     pub assessment_method: String,
     pub course_nature: String,
     pub hour_distribution: HourDistributionMeta,
     pub grading_scheme: Vec<GradingItem>,
+    /// Unmodeled plan fields chosen by `course_extra_fields`
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, toml::Value>,
 }
 
-#[derive(Debug, Serialize)]
+/// The `credit` written to frontmatter.
+///
+/// Whole values are written as integers (`credit: 3`), fractional ones as
+/// they are (`credit: 1.5`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CreditValue {
+    Value(f64),
+    /// Written as `credit: null`
+    Null,
+    /// The `credit` key is left out
+    Omitted,
+}
+
+impl CreditValue {
+    fn is_omitted(&self) -> bool {
+        *self == CreditValue::Omitted
+    }
+}
+
+impl Serialize for CreditValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            CreditValue::Value(credit) if credit.fract() == 0.0 && credit.abs() < 1e15 => {
+                serializer.serialize_i64(*credit as i64)
+            }
+            CreditValue::Value(credit) => serializer.serialize_f64(*credit),
+            CreditValue::Null | CreditValue::Omitted => serializer.serialize_none(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct HourDistributionMeta {
     pub theory: u32,
     pub lab: u32,
@@ -120,6 +301,8 @@ pub struct HourDistributionMeta {
     pub exercise: u32,
     pub computer: u32,
     pub tutoring: u32,
+    /// Sum of the categories above
+    pub total: u32,
 }
 
 #[derive(Debug, Serialize)]
@@ -128,12 +311,148 @@ pub struct GradingItem {
     pub percent: u32,
 }
 
+/// Quote a string as a YAML double-quoted scalar.
+///
+/// JSON string syntax is a subset of YAML's double-quoted style, so
+/// serde_json's escaping yields a scalar any YAML parser reads back verbatim.
+pub fn yaml_quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+impl From<&HourDistribution> for HourDistributionMeta {
+    /// Fill unset hour categories with zero
+    fn from(h: &HourDistribution) -> Self {
+        Self {
+            theory: h.theory.unwrap_or(0),
+            lab: h.lab.unwrap_or(0),
+            practice: h.practice.unwrap_or(0),
+            exercise: h.exercise.unwrap_or(0),
+            computer: h.computer.unwrap_or(0),
+            tutoring: h.tutoring.unwrap_or(0),
+            total: h.total(),
+        }
+    }
+}
+
+/// Builder for [`Frontmatter`] that zero-fills any course field left unset.
+#[derive(Debug, Default)]
+pub struct FrontmatterBuilder {
+    title: String,
+    english_title: Option<String>,
+    description: String,
+    tags: Vec<String>,
+    credit: Option<f64>,
+    zero_credit: ZeroCredit,
+    assessment_method: Option<String>,
+    course_nature: Option<String>,
+    hours: Option<HourDistribution>,
+    grading_scheme: Vec<GradingItem>,
+    extra: BTreeMap<String, toml::Value>,
+}
+
+impl FrontmatterBuilder {
+    pub fn english_title(mut self, english_title: Option<String>) -> Self {
+        self.english_title = english_title;
+        self
+    }
+
+    pub fn description(mut self, description: Option<String>) -> Self {
+        self.description = description.unwrap_or_default();
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn credit(mut self, credit: Option<f64>) -> Self {
+        self.credit = credit;
+        self
+    }
+
+    /// How a zero or unknown credit is written; defaults to `0`
+    pub fn zero_credit(mut self, zero_credit: ZeroCredit) -> Self {
+        self.zero_credit = zero_credit;
+        self
+    }
+
+    pub fn assessment_method(mut self, assessment_method: Option<String>) -> Self {
+        self.assessment_method = assessment_method;
+        self
+    }
+
+    pub fn course_nature(mut self, course_nature: Option<String>) -> Self {
+        self.course_nature = course_nature;
+        self
+    }
+
+    pub fn hours(mut self, hours: Option<HourDistribution>) -> Self {
+        self.hours = hours;
+        self
+    }
+
+    pub fn grading_scheme(mut self, grading_scheme: Vec<GradingItem>) -> Self {
+        self.grading_scheme = grading_scheme;
+        self
+    }
+
+    pub fn extra(mut self, extra: BTreeMap<String, toml::Value>) -> Self {
+        self.extra = extra;
+        self
+    }
+
+    pub fn build(self) -> Frontmatter {
+        Frontmatter {
+            title: self.title,
+            english_title: self.english_title,
+            description: self.description,
+            tags: self.tags,
+            course: CourseMetadata {
+                credit: match (self.credit.filter(|c| *c != 0.0), self.zero_credit) {
+                    (Some(credit), _) => CreditValue::Value(credit),
+                    (None, ZeroCredit::Zero) => CreditValue::Value(0.0),
+                    (None, ZeroCredit::Null) => CreditValue::Null,
+                    (None, ZeroCredit::Omit) => CreditValue::Omitted,
+                },
+                assessment_method: self.assessment_method.unwrap_or_default(),
+                course_nature: self.course_nature.unwrap_or_default(),
+                hour_distribution: self
+                    .hours
+                    .as_ref()
+                    .map(HourDistributionMeta::from)
+                    .unwrap_or_default(),
+                grading_scheme: self.grading_scheme,
+                extra: self.extra,
+            },
+        }
+    }
+}
+
 impl Frontmatter {
+    /// Start building frontmatter for a page with the given title
+    pub fn builder(title: impl Into<String>) -> FrontmatterBuilder {
+        FrontmatterBuilder {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
     /// Convert frontmatter to YAML string
+    ///
+    /// Titles and descriptions are always double-quoted so that values such
+    /// as `course: intro`, `# notes` or `- draft` can't be misread by
+    /// downstream frontmatter parsers.
     pub fn to_yaml(&self) -> String {
-        // Use serde_yaml to serialize, but customize for better formatting
+        let mut header = format!("title: {}\n", yaml_quote(&self.title));
+        if let Some(ref english_title) = self.english_title {
+            header.push_str(&format!("englishTitle: {}\n", yaml_quote(english_title)));
+        }
+        header.push_str(&format!("description: {}\n", yaml_quote(&self.description)));
+
+        // Use serde_yaml for the structured remainder
         match serde_yaml::to_string(self) {
-            Ok(yaml) => format!("---\n{}---", yaml),
+            Ok(yaml) => format!("---\n{}{}---", header, yaml),
             Err(_) => {
                 // Fallback to empty frontmatter
                 "---\ntitle: ''\ndescription: ''\n---".to_string()
@@ -142,6 +461,26 @@ impl Frontmatter {
     }
 }
 
+/// Parse a generated `---`-delimited frontmatter block back as YAML and check
+/// that `title` round-trips to `expected_title`.
+pub fn validate_frontmatter_yaml(block: &str, expected_title: &str) -> Result<(), String> {
+    let body = block
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.strip_suffix("---"))
+        .ok_or_else(|| "frontmatter is not delimited by `---` lines".to_string())?;
+
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(body).map_err(|e| format!("invalid YAML: {}", e))?;
+    match value.get("title").and_then(serde_yaml::Value::as_str) {
+        Some(title) if title == expected_title => Ok(()),
+        Some(title) => Err(format!(
+            "title parsed as {:?}, expected {:?}",
+            title, expected_title
+        )),
+        None => Err("missing string `title`".to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,9 +489,11 @@ mod tests {
     fn test_frontmatter_to_yaml_basic() {
         let frontmatter = Frontmatter {
             title: "Test Course".to_string(),
+            english_title: None,
             description: "A test description".to_string(),
+            tags: vec![],
             course: CourseMetadata {
-                credit: 3,
+                credit: CreditValue::Value(3.0),
                 assessment_method: "Exam".to_string(),
                 course_nature: "Required".to_string(),
                 hour_distribution: HourDistributionMeta {
@@ -162,6 +503,7 @@ mod tests {
                     exercise: 0,
                     computer: 0,
                     tutoring: 0,
+                    total: 48,
                 },
                 grading_scheme: vec![
                     GradingItem {
@@ -173,6 +515,7 @@ mod tests {
                         percent: 30,
                     },
                 ],
+                extra: BTreeMap::new(),
             },
         };
 
@@ -180,8 +523,8 @@ mod tests {
 
         assert!(yaml.starts_with("---\n"));
         assert!(yaml.ends_with("---"));
-        assert!(yaml.contains("title: Test Course"));
-        assert!(yaml.contains("description: A test description"));
+        assert!(yaml.contains("title: \"Test Course\""));
+        assert!(yaml.contains("description: \"A test description\""));
         assert!(yaml.contains("credit: 3"));
         assert!(yaml.contains("assessmentMethod: Exam"));
         assert!(yaml.contains("courseNature: Required"));
@@ -191,9 +534,11 @@ mod tests {
     fn test_frontmatter_to_yaml_with_grading_scheme() {
         let frontmatter = Frontmatter {
             title: "Advanced Math".to_string(),
+            english_title: None,
             description: "".to_string(),
+            tags: vec![],
             course: CourseMetadata {
-                credit: 4,
+                credit: CreditValue::Value(4.0),
                 assessment_method: "Mixed".to_string(),
                 course_nature: "Elective".to_string(),
                 hour_distribution: HourDistributionMeta {
@@ -203,6 +548,7 @@ mod tests {
                     exercise: 0,
                     computer: 0,
                     tutoring: 0,
+                    total: 48,
                 },
                 grading_scheme: vec![
                     GradingItem {
@@ -218,6 +564,7 @@ mod tests {
                         percent: 20,
                     },
                 ],
+                extra: BTreeMap::new(),
             },
         };
 
@@ -236,9 +583,11 @@ mod tests {
     fn test_frontmatter_to_yaml_empty_grading_scheme() {
         let frontmatter = Frontmatter {
             title: "Simple Course".to_string(),
+            english_title: None,
             description: "No grading details".to_string(),
+            tags: vec![],
             course: CourseMetadata {
-                credit: 2,
+                credit: CreditValue::Value(2.0),
                 assessment_method: "Pass/Fail".to_string(),
                 course_nature: "Optional".to_string(),
                 hour_distribution: HourDistributionMeta {
@@ -248,14 +597,16 @@ mod tests {
                     exercise: 0,
                     computer: 0,
                     tutoring: 0,
+                    total: 24,
                 },
                 grading_scheme: vec![],
+                extra: BTreeMap::new(),
             },
         };
 
         let yaml = frontmatter.to_yaml();
 
-        assert!(yaml.contains("title: Simple Course"));
+        assert!(yaml.contains("title: \"Simple Course\""));
         assert!(yaml.contains("gradingScheme: []"));
     }
 
@@ -263,9 +614,11 @@ mod tests {
     fn test_hour_distribution_meta_all_fields() {
         let frontmatter = Frontmatter {
             title: "Complex Course".to_string(),
+            english_title: None,
             description: "".to_string(),
+            tags: vec![],
             course: CourseMetadata {
-                credit: 5,
+                credit: CreditValue::Value(5.0),
                 assessment_method: "Comprehensive".to_string(),
                 course_nature: "Core".to_string(),
                 hour_distribution: HourDistributionMeta {
@@ -275,8 +628,10 @@ mod tests {
                     exercise: 4,
                     computer: 8,
                     tutoring: 2,
+                    total: 70,
                 },
                 grading_scheme: vec![],
+                extra: BTreeMap::new(),
             },
         };
 
@@ -312,6 +667,7 @@ mod tests {
             exercise: 0,
             computer: 0,
             tutoring: 0,
+            total: 0,
         };
 
         let yaml = serde_yaml::to_string(&hours).unwrap();
@@ -320,4 +676,324 @@ mod tests {
         assert!(yaml.contains("theory: 0"));
         assert!(yaml.contains("lab: 0"));
     }
+
+    #[test]
+    fn test_toml_course_english_name() {
+        let with_name: TomlCourse = toml::from_str(
+            r#"
+course_code = "COMP1001"
+course_name = "程序设计"
+english_name = "Programming"
+"#,
+        )
+        .unwrap();
+        assert_eq!(with_name.english_name.as_deref(), Some("Programming"));
+
+        let without_name: TomlCourse = toml::from_str(
+            r#"
+course_code = "COMP1001"
+course_name = "程序设计"
+"#,
+        )
+        .unwrap();
+        assert_eq!(without_name.english_name, None);
+    }
+
+    #[test]
+    fn test_frontmatter_english_title() {
+        let mut frontmatter = Frontmatter {
+            title: "程序设计".to_string(),
+            english_title: Some("Programming".to_string()),
+            description: "".to_string(),
+            tags: vec![],
+            course: CourseMetadata {
+                credit: CreditValue::Value(3.0),
+                assessment_method: "".to_string(),
+                course_nature: "".to_string(),
+                hour_distribution: HourDistributionMeta {
+                    theory: 0,
+                    lab: 0,
+                    practice: 0,
+                    exercise: 0,
+                    computer: 0,
+                    tutoring: 0,
+                    total: 0,
+                },
+                grading_scheme: vec![],
+                extra: BTreeMap::new(),
+            },
+        };
+
+        assert!(frontmatter
+            .to_yaml()
+            .contains("englishTitle: \"Programming\""));
+
+        frontmatter.english_title = None;
+        assert!(!frontmatter.to_yaml().contains("englishTitle"));
+    }
+
+    #[test]
+    fn test_frontmatter_builder_full_course() {
+        let yaml = Frontmatter::builder("Signals")
+            .credit(Some(4.0))
+            .assessment_method(Some("考试".to_string()))
+            .course_nature(Some("必修".to_string()))
+            .hours(Some(HourDistribution {
+                theory: Some(48),
+                lab: Some(16),
+                practice: None,
+                exercise: None,
+                computer: None,
+                tutoring: None,
+            }))
+            .grading_scheme(vec![GradingItem {
+                name: "Final".to_string(),
+                percent: 60,
+            }])
+            .build()
+            .to_yaml();
+
+        assert!(yaml.contains("title: \"Signals\""));
+        assert!(yaml.contains("credit: 4"));
+        assert!(yaml.contains("assessmentMethod: 考试"));
+        assert!(yaml.contains("courseNature: 必修"));
+        assert!(yaml.contains("theory: 48"));
+        assert!(yaml.contains("lab: 16"));
+        assert!(yaml.contains("practice: 0"));
+        assert!(yaml.contains("name: Final"));
+    }
+
+    #[test]
+    fn test_frontmatter_builder_bare_category_page() {
+        let yaml = Frontmatter::builder("Physical Education").build().to_yaml();
+
+        assert!(yaml.contains("title: \"Physical Education\""));
+        assert!(yaml.contains("description: \"\""));
+        assert!(yaml.contains("credit: 0"));
+        assert!(yaml.contains("assessmentMethod: ''"));
+        assert!(yaml.contains("theory: 0"));
+        assert!(yaml.contains("tutoring: 0"));
+        assert!(yaml.contains("gradingScheme: []"));
+    }
+
+    /// Parse the YAML between the `---` fences back into a value
+    fn reparse(yaml: &str) -> serde_yaml::Value {
+        let body = yaml
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.strip_suffix("---"))
+            .unwrap();
+        serde_yaml::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_frontmatter_titles_round_trip() {
+        for title in [
+            "course: intro",
+            "# not a comment",
+            "- leading dash",
+            "数据结构 🚀",
+            "say \"hi\" \\ bye",
+        ] {
+            let yaml = Frontmatter::builder(title)
+                .english_title(Some(title.to_string()))
+                .build()
+                .to_yaml();
+            let value = reparse(&yaml);
+
+            assert_eq!(value["title"].as_str(), Some(title), "yaml: {}", yaml);
+            assert_eq!(value["englishTitle"].as_str(), Some(title));
+            assert_eq!(value["description"].as_str(), Some(""));
+        }
+    }
+
+    #[test]
+    fn test_validate_frontmatter_yaml_round_trip() {
+        for title in [
+            "数据结构: 进阶",
+            "The \"Real\" Analysis",
+            "It's 'quoted'",
+            "Line one\nLine two",
+            "# not a comment",
+            "- not a list",
+        ] {
+            let yaml = Frontmatter::builder(title).build().to_yaml();
+            assert_eq!(validate_frontmatter_yaml(&yaml, title), Ok(()), "{}", title);
+        }
+    }
+
+    #[test]
+    fn test_validate_frontmatter_yaml_rejects_broken() {
+        // Unquoted values like these are what the check is meant to catch
+        assert!(validate_frontmatter_yaml("---\ntitle: a: b\n---", "a: b").is_err());
+        assert!(validate_frontmatter_yaml("---\ntitle: # x\n---", "# x").is_err());
+        assert!(validate_frontmatter_yaml("---\ntitle: \"x\"\n---", "y").is_err());
+        assert!(validate_frontmatter_yaml("title: \"x\"\n", "x").is_err());
+    }
+
+    #[test]
+    fn test_course_from_toml() {
+        let toml_course: TomlCourse = toml::from_str(
+            r#"
+course_code = "COMP1001"
+course_name = "程序设计"
+english_name = "Programming"
+credit = 3.5
+assessment_method = "考试"
+course_nature = "必修"
+recommended_year_semester = "第一学年秋季"
+hours = { theory = 48, lab = 16 }
+grade_details = [{ name = "Ignored", percent = "100%" }]
+"#,
+        )
+        .unwrap();
+        let grades = vec![GradeDetail {
+            name: "期末".to_string(),
+            percent: Some("60%".to_string()),
+        }];
+
+        let course = Course::from_toml(toml_course, "COMP1001-repo".to_string(), Some(grades));
+        assert_eq!(course.repo_id, "COMP1001-repo");
+        assert_eq!(course.name, "程序设计");
+        assert_eq!(course.english_name.as_deref(), Some("Programming"));
+        assert_eq!(course.credit, Some(3.5));
+        assert_eq!(course.assessment_method.as_deref(), Some("考试"));
+        assert_eq!(course.course_nature.as_deref(), Some("必修"));
+        assert_eq!(course.recommended_semester.as_deref(), Some("第一学年秋季"));
+        let hours = course.hours.unwrap();
+        assert_eq!(
+            (hours.theory, hours.lab, hours.practice),
+            (Some(48), Some(16), None)
+        );
+        let details = course.grade_details.unwrap();
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0].name, "期末");
+    }
+
+    #[test]
+    fn test_course_from_toml_minimal() {
+        let toml_course: TomlCourse =
+            toml::from_str("course_code = \"X\"\ncourse_name = \"Minimal\"\n").unwrap();
+
+        let course = Course::from_toml(toml_course, "X".to_string(), None);
+        assert_eq!(course.name, "Minimal");
+        assert!(course.english_name.is_none());
+        assert!(course.credit.is_none());
+        assert!(course.assessment_method.is_none());
+        assert!(course.course_nature.is_none());
+        assert!(course.recommended_semester.is_none());
+        assert!(course.hours.is_none());
+        assert!(course.grade_details.is_none());
+    }
+
+    #[test]
+    fn test_frontmatter_zero_credit_modes() {
+        let yaml = |credit: Option<f64>, mode: ZeroCredit| {
+            Frontmatter::builder("Seminar")
+                .credit(credit)
+                .zero_credit(mode)
+                .build()
+                .to_yaml()
+        };
+
+        for credit in [Some(0.0), None] {
+            assert!(yaml(credit, ZeroCredit::Zero).contains("  credit: 0"));
+            assert!(yaml(credit, ZeroCredit::Null).contains("  credit: null\n"));
+            assert!(!yaml(credit, ZeroCredit::Omit).contains("credit"));
+        }
+        assert!(yaml(Some(2.0), ZeroCredit::Omit).contains("  credit: 2"));
+    }
+
+    #[test]
+    fn test_toml_course_credit_formats() {
+        let credit = |value: &str| {
+            let toml = format!(
+                "course_code = \"COMP1001\"\ncourse_name = \"程序设计\"\ncredit = {}",
+                value
+            );
+            toml::from_str::<TomlCourse>(&toml).unwrap().credit
+        };
+
+        assert_eq!(credit("3.0"), Some(3.0));
+        assert_eq!(credit("2"), Some(2.0));
+        assert_eq!(credit("\"3.0\""), Some(3.0));
+        assert_eq!(credit("\"3学分\""), Some(3.0));
+        assert_eq!(credit("\"garbage\""), None);
+        assert_eq!(credit("true"), None);
+    }
+
+    #[test]
+    fn test_toml_course_keeps_unknown_fields() {
+        let toml = r#"
+course_code = "COMP1001"
+course_name = "程序设计"
+credit = "3学分"
+department = "计算机科学与技术学院"
+weekly_hours = 4
+"#;
+        let course: TomlCourse = toml::from_str(toml).unwrap();
+        assert_eq!(course.credit, Some(3.0));
+        assert_eq!(course.extra.len(), 2);
+        assert_eq!(
+            course.extra["department"].as_str(),
+            Some("计算机科学与技术学院")
+        );
+        assert_eq!(course.extra["weekly_hours"].as_integer(), Some(4));
+
+        let course = Course::from_toml(course, "COMP1001".to_string(), None);
+        assert_eq!(
+            course.extra["department"].as_str(),
+            Some("计算机科学与技术学院")
+        );
+        assert!(!course.extra.contains_key("course_code"));
+    }
+
+    #[test]
+    fn test_toml_course_credit_missing() {
+        let toml = "course_code = \"COMP1001\"\ncourse_name = \"程序设计\"";
+        assert_eq!(toml::from_str::<TomlCourse>(toml).unwrap().credit, None);
+    }
+
+    #[test]
+    fn test_frontmatter_fractional_credit() {
+        let yaml = |credit| {
+            Frontmatter::builder("PE")
+                .credit(Some(credit))
+                .build()
+                .to_yaml()
+        };
+        assert!(yaml(1.5).contains("  credit: 1.5\n"));
+        assert!(yaml(0.5).contains("  credit: 0.5\n"));
+        assert!(yaml(3.0).contains("  credit: 3\n"));
+    }
+
+    #[test]
+    fn test_hour_distribution_total() {
+        let hours = |theory, lab, practice, exercise, computer, tutoring| HourDistribution {
+            theory,
+            lab,
+            practice,
+            exercise,
+            computer,
+            tutoring,
+        };
+        let cases = [
+            (hours(Some(48), None, None, None, None, None), 48),
+            (hours(Some(32), Some(16), None, None, None, None), 48),
+            (
+                hours(Some(32), Some(16), Some(8), Some(4), Some(8), Some(2)),
+                70,
+            ),
+            (hours(None, None, None, None, None, None), 0),
+        ];
+        for (distribution, total) in cases {
+            assert_eq!(distribution.total(), total);
+            assert_eq!(HourDistributionMeta::from(&distribution).total, total);
+        }
+
+        let yaml = Frontmatter::builder("Circuits")
+            .hours(Some(hours(Some(32), Some(16), None, None, None, None)))
+            .build()
+            .to_yaml();
+        assert!(yaml.contains("    tutoring: 0\n    total: 48\n"));
+    }
 }
@@ -0,0 +1,262 @@
+//! The load → generate → format run, shared by the binary and the e2e test.
+//!
+//! Fetching stays in `main`; this starts from an already populated `repos/`
+//! directory under the repository root.
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::report::GenerationReport;
+use crate::{formatter, generator, loader};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What a pipeline run did.
+#[derive(Debug, Default)]
+pub struct PipelineSummary {
+    /// Training plans loaded
+    pub plans: usize,
+    /// Plan courses that had pages generated, after the repos_list filter
+    pub courses: usize,
+    /// MDX files rewritten by the formatter
+    pub formatted: Vec<PathBuf>,
+    /// Issues found along the way, including repos_list drift
+    pub report: GenerationReport,
+}
+
+/// Generate and format the docs tree for the repository at `repo_root`.
+///
+/// Reads `hoa-major-data/`, `repos/` and `repos_list.txt` from `repo_root`
+/// and writes to `content/docs/`.
+pub async fn run_pipeline(repo_root: &Path, config: &Config) -> Result<PipelineSummary> {
+    let repos_dir = repo_root.join("repos");
+    let data_dir = repo_root.join("hoa-major-data");
+    loader::preflight(&data_dir, &repos_dir)?;
+
+    // Load repos list (optional filter)
+    let repos_set = loader::load_repos_list(repo_root)?;
+    if repos_set.is_empty() {
+        println!("No repos_list.txt found - will process all available courses");
+    } else {
+        println!(
+            "Loaded {} repositories from repos_list.txt",
+            repos_set.len()
+        );
+    }
+
+    // A custom semesters.toml replaces the built-in semester mapping
+    let mut config = config.clone();
+    if let Some(mapping) = loader::load_semester_mapping(&data_dir) {
+        println!(
+            "Loaded {} semesters from semesters.toml",
+            mapping.entries().count()
+        );
+        config.generator.semesters = mapping;
+    }
+    let config = &config;
+
+    // Load all training plans from TOML files
+    let loaded = loader::load_all_plans(&data_dir, &config.generator.semesters)?;
+    let plans = loaded.plans;
+    println!("Loaded {} training plans", plans.len());
+    if !loaded.unmapped_course_codes.is_empty() {
+        println!(
+            "{} course codes have no lookup_table.toml entry and are used as repo IDs",
+            loaded.unmapped_course_codes.len()
+        );
+    }
+
+    let shared_categories_config = loader::load_shared_categories(&data_dir);
+    if !shared_categories_config.categories.is_empty() {
+        println!(
+            "Loaded {} shared categories",
+            shared_categories_config.categories.len()
+        );
+    }
+
+    let grades_summary = loader::load_grades_summary(&data_dir);
+
+    let drift = if repos_set.is_empty() {
+        loader::ReposListReport::default()
+    } else {
+        loader::reconcile_repos_list(&repos_set, &plans, &shared_categories_config)
+    };
+    if !drift.listed_unused.is_empty() {
+        println!(
+            "Listed in repos_list.txt but unused: {}",
+            drift.listed_unused.join(", ")
+        );
+    }
+    if !drift.referenced_unlisted.is_empty() {
+        println!(
+            "Referenced but missing from repos_list.txt: {}",
+            drift.referenced_unlisted.join(", ")
+        );
+    }
+
+    let orphaned_readmes =
+        loader::find_orphaned_readmes(&repos_dir, &plans, &shared_categories_config);
+    if !orphaned_readmes.is_empty() {
+        println!(
+            "Cached READMEs not used by any plan or category: {}",
+            orphaned_readmes.join(", ")
+        );
+    }
+
+    let plan_count = plans.len();
+
+    // Filter courses by repos_set (if repos_list.txt exists)
+    let filtered_plans: Vec<_> = if repos_set.is_empty() {
+        plans
+    } else {
+        plans
+            .into_iter()
+            .map(|mut plan| {
+                plan.courses.retain(|c| repos_set.contains(&c.repo_id));
+                plan
+            })
+            .collect()
+    };
+
+    let total_courses: usize = filtered_plans.iter().map(|p| p.courses.len()).sum();
+    println!("Total courses to process: {}", total_courses);
+
+    // Generate course pages
+    let docs_dir = repo_root.join("content/docs");
+    if !docs_dir.exists() {
+        println!("Creating output directory: {}", docs_dir.display());
+        fs::create_dir_all(&docs_dir)?;
+    }
+
+    println!("Generating course pages...");
+    let mut report = generator::generate_course_pages(
+        &filtered_plans,
+        &shared_categories_config,
+        &grades_summary,
+        &repos_dir,
+        &docs_dir,
+        &repos_set,
+        config,
+    )
+    .await?;
+    println!("Course pages generated successfully");
+
+    // Format MDX files
+    println!("Formatting MDX files...");
+    let formatted = if config.generator.index_only {
+        // Course pages were left as they are; only format what was rewritten
+        let course_pages: HashSet<&str> = filtered_plans
+            .iter()
+            .flat_map(|plan| plan.courses.iter().map(|c| c.repo_id.as_str()))
+            .chain(
+                shared_categories_config
+                    .categories
+                    .iter()
+                    .flat_map(|cat| cat.repo_ids.iter().map(String::as_str)),
+            )
+            .collect();
+        formatter::format_mdx_files_where(&docs_dir, &config.format, &mut report, |path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_none_or(|stem| !course_pages.contains(stem))
+        })?
+    } else {
+        formatter::format_all_mdx_files(&docs_dir, &config.format, &mut report)?
+    };
+    println!("Formatted {} MDX files", formatted.len());
+
+    report.listed_unused = drift.listed_unused;
+    report.referenced_unlisted = drift.referenced_unlisted;
+    report.unmapped_course_codes = loaded.unmapped_course_codes;
+    report.plan_warnings = loaded.plan_warnings;
+    report.bad_percentages = loaded.bad_percentages;
+    report.orphaned_readmes = orphaned_readmes;
+
+    Ok(PipelineSummary {
+        plans: plan_count,
+        courses: total_courses,
+        formatted,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff_docs;
+    use walkdir::WalkDir;
+
+    /// Copy the files under `from` into `to`, replacing what was there
+    fn copy_tree(from: &Path, to: &Path) {
+        let _ = fs::remove_dir_all(to);
+        for entry in WalkDir::new(from).into_iter().filter_map(|e| e.ok()) {
+            let target = to.join(entry.path().strip_prefix(from).unwrap());
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(&target).unwrap();
+            } else {
+                fs::copy(entry.path(), &target).unwrap();
+            }
+        }
+    }
+
+    /// Runs the fixture repository and compares `content/docs` against the
+    /// golden tree. Set `UPDATE_GOLDEN=1` to rewrite the golden tree after an
+    /// intended output change.
+    #[tokio::test]
+    async fn test_pipeline_matches_golden_docs() {
+        let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pipeline");
+        let root = std::env::temp_dir().join("test_pipeline_golden");
+        copy_tree(&fixture.join("input"), &root);
+
+        let summary = run_pipeline(&root, &Config::default()).await.unwrap();
+        assert_eq!(summary.plans, 1);
+        assert_eq!(summary.courses, 2);
+        assert_eq!(summary.report.listed_unused, vec!["OLD9999".to_string()]);
+        assert!(summary.report.missing_readmes.is_empty());
+
+        let docs_dir = root.join("content/docs");
+        let golden_dir = fixture.join("expected");
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            copy_tree(&docs_dir, &golden_dir);
+        }
+        let diff = diff_docs(&golden_dir, &docs_dir).unwrap();
+        assert!(diff.is_empty(), "docs differ from golden tree: {:?}", diff);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_index_only_formats_only_indexes() {
+        let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pipeline");
+        let root = std::env::temp_dir().join("test_pipeline_index_only");
+        copy_tree(&fixture.join("input"), &root);
+        run_pipeline(&root, &Config::default()).await.unwrap();
+
+        let docs_dir = root.join("content/docs");
+        let page = docs_dir.join("2023/CS/fresh-autumn/COMP1001.mdx");
+        let unformatted = format!("{}\n<br>\n", fs::read_to_string(&page).unwrap());
+        fs::write(&page, &unformatted).unwrap();
+
+        let mut config = Config::default();
+        config.generator.index_only = true;
+        let summary = run_pipeline(&root, &config).await.unwrap();
+        assert_eq!(fs::read_to_string(&page).unwrap(), unformatted);
+        assert!(summary.formatted.iter().all(|path| path != &page));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_requires_plans() {
+        let root = std::env::temp_dir().join("test_pipeline_no_plans");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("repos")).unwrap();
+
+        assert!(matches!(
+            run_pipeline(&root, &Config::default()).await,
+            Err(crate::error::FumaError::MissingInputs(_))
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,165 @@
+//! Machine-readable summary of the issues found during a run.
+//!
+//! Each pipeline stage adds what it notices to a [`GenerationReport`]; at the
+//! end of the run the report is written as JSON so CI can annotate a PR.
+
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Issues collected across a run, grouped by category.
+#[derive(Debug, Default, Serialize)]
+pub struct GenerationReport {
+    /// Repo IDs referenced by a plan or category with no cached README
+    pub missing_readmes: BTreeSet<String>,
+    /// Listed in repos_list.txt but not used by any plan or category
+    pub listed_unused: Vec<String>,
+    /// Used by a plan or category but missing from repos_list.txt
+    pub referenced_unlisted: Vec<String>,
+    /// Problems found in formatted pages, as `path: message`
+    pub format_warnings: Vec<String>,
+    /// Plan course codes with no lookup_table.toml entry
+    pub unmapped_course_codes: BTreeSet<String>,
+    /// Duplicate course codes and unrecognized semesters in plan files
+    pub plan_warnings: Vec<String>,
+    /// Courses whose grade percentages don't add up to 100
+    pub bad_percentages: Vec<String>,
+    /// Cached READMEs that no plan or category references
+    pub orphaned_readmes: Vec<String>,
+    /// Shared category entries left without a card, as `category/repo_id`
+    pub dangling_cards: BTreeSet<String>,
+}
+
+/// On-disk shape of `report.json`: per-category counts plus the entries.
+#[derive(Serialize)]
+struct ReportFile<'a> {
+    counts: BTreeMap<&'static str, usize>,
+    #[serde(flatten)]
+    report: &'a GenerationReport,
+}
+
+impl GenerationReport {
+    /// Number of entries in each category
+    pub fn counts(&self) -> BTreeMap<&'static str, usize> {
+        BTreeMap::from([
+            ("missing_readmes", self.missing_readmes.len()),
+            ("listed_unused", self.listed_unused.len()),
+            ("referenced_unlisted", self.referenced_unlisted.len()),
+            ("format_warnings", self.format_warnings.len()),
+            ("unmapped_course_codes", self.unmapped_course_codes.len()),
+            ("plan_warnings", self.plan_warnings.len()),
+            ("bad_percentages", self.bad_percentages.len()),
+            ("orphaned_readmes", self.orphaned_readmes.len()),
+            ("dangling_cards", self.dangling_cards.len()),
+        ])
+    }
+
+    /// Write the report as pretty-printed JSON to `path`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = ReportFile {
+            counts: self.counts(),
+            report: self,
+        };
+        fs::write(path, serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::pipeline::run_pipeline;
+
+    #[tokio::test]
+    async fn test_report_json_from_run() {
+        let root = std::env::temp_dir().join("test_generation_report");
+        let _ = fs::remove_dir_all(&root);
+        let data_dir = root.join("hoa-major-data");
+        let repos_dir = root.join("repos");
+        fs::create_dir_all(data_dir.join("plans")).unwrap();
+        fs::create_dir_all(&repos_dir).unwrap();
+        fs::write(repos_dir.join("COMP1001.mdx"), "# COMP1001\n\nBody\n").unwrap();
+        fs::write(repos_dir.join("STALE01.mdx"), "# STALE01\n\nBody\n").unwrap();
+        fs::write(
+            root.join("repos_list.txt"),
+            "COMP1001\nMATH1001\nGEN101\nOLD9999\n",
+        )
+        .unwrap();
+        fs::write(
+            data_dir.join("plans/2023_CS.toml"),
+            r#"
+[info]
+year = "2023"
+major_code = "CS"
+major_name = "Computer Science"
+plan_ID = "2023CS"
+
+[[courses]]
+course_code = "COMP1001"
+course_name = "Programming"
+recommended_year_semester = "第一学年秋季"
+
+[[courses]]
+course_code = "COMP1001"
+course_name = "Programming"
+
+[[courses]]
+course_code = "MATH1001"
+course_name = "Calculus"
+recommended_year_semester = "第三学年秋李"
+grade_details = [
+  { name = "Final", percent = "60%" },
+  { name = "Homework", percent = "30%" },
+]
+"#,
+        )
+        .unwrap();
+        fs::write(
+            data_dir.join("shared_categories.toml"),
+            r#"
+[[categories]]
+id = "general"
+title = "General"
+repo_ids = ["GEN101"]
+"#,
+        )
+        .unwrap();
+
+        let summary = run_pipeline(&root, &Config::default()).await.unwrap();
+        let path = root.join("out/report.json");
+        summary.report.write(&path).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            json["missing_readmes"],
+            serde_json::json!(["GEN101", "MATH1001"])
+        );
+        assert_eq!(json["listed_unused"], serde_json::json!(["OLD9999"]));
+        assert_eq!(json["orphaned_readmes"], serde_json::json!(["STALE01"]));
+        assert_eq!(
+            json["dangling_cards"],
+            serde_json::json!(["general/GEN101"])
+        );
+        assert_eq!(
+            json["bad_percentages"],
+            serde_json::json!(["grade percentages of MATH1001 in plan 2023CS sum to 90%"])
+        );
+        assert_eq!(json["counts"]["missing_readmes"], 2);
+        assert_eq!(json["counts"]["listed_unused"], 1);
+        assert_eq!(json["counts"]["referenced_unlisted"], 0);
+        assert_eq!(json["counts"]["format_warnings"], 0);
+        assert_eq!(json["counts"]["plan_warnings"], 2);
+        assert_eq!(json["counts"]["bad_percentages"], 1);
+        assert_eq!(json["counts"]["orphaned_readmes"], 1);
+        assert_eq!(json["counts"]["dangling_cards"], 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,18 +1,50 @@
-use crate::constants::should_include_file;
+use crate::config::{TreeConfig, TreeSort};
+use crate::constants::{should_include_file, DEFAULT_DATE_FORMAT, DEFAULT_DOWNLOAD_HOST};
 use crate::models::{FileNode, NodeType, WorktreeData};
+use chrono::FixedOffset;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
-/// Format Unix timestamp to YYYY-MM-DD format
-fn format_timestamp(unix_ts: i64) -> String {
-    use std::time::UNIX_EPOCH;
-    let duration = std::time::Duration::from_secs(unix_ts as u64);
-    let datetime = UNIX_EPOCH + duration;
-    let datetime = chrono::DateTime::<chrono::Utc>::from(datetime);
-    datetime.format("%Y-%m-%d").to_string()
+/// Latest file date we believe, 2100-01-01; anything later is bad worktree data
+const MAX_PLAUSIBLE_TIMESTAMP: i64 = 4_102_444_800;
+
+/// Whether a worktree timestamp is worth showing. Zero or negative values
+/// would render as 1970 or earlier, so they are treated as missing too.
+fn is_plausible_timestamp(unix_ts: i64) -> bool {
+    (1..=MAX_PLAUSIBLE_TIMESTAMP).contains(&unix_ts)
+}
+
+/// Format Unix timestamp in the `offset` time zone with a chrono format
+/// string, falling back to YYYY-MM-DD when the format string is invalid.
+/// Returns `None` for implausible timestamps.
+fn format_timestamp(unix_ts: i64, format: &str, offset: FixedOffset) -> Option<String> {
+    use chrono::format::{Item, StrftimeItems};
+    if !is_plausible_timestamp(unix_ts) {
+        return None;
+    }
+    let datetime = chrono::DateTime::from_timestamp(unix_ts, 0)?.with_timezone(&offset);
+    let format = if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        DEFAULT_DATE_FORMAT
+    } else {
+        format
+    };
+    Some(datetime.format(format).to_string())
 }
 
-/// Generate download URL for a file in the repository
-fn generate_download_url(repo: &str, path: &str) -> String {
+/// Describe a Unix timestamp relative to `reference`, e.g. "3 天前"
+fn format_relative_timestamp(unix_ts: i64, reference: i64) -> String {
+    let days = (reference - unix_ts).max(0) / 86_400;
+    match days {
+        0 => "今天".to_string(),
+        1..=29 => format!("{} 天前", days),
+        30..=364 => format!("{} 个月前", days / 30),
+        _ => format!("{} 年前", days / 365),
+    }
+}
+
+/// Generate download URL for a file on `branch` of `org/repo` via the given
+/// proxy host
+fn generate_download_url(host: &str, org: &str, repo: &str, branch: &str, path: &str) -> String {
     // Only encode parts, not the path separators
     let parts: Vec<String> = path
         .split('/')
@@ -20,20 +52,32 @@ fn generate_download_url(repo: &str, path: &str) -> String {
         .collect();
     let encoded_path = parts.join("/");
     format!(
-        "https://gh.hoa.moe/github.com/HITSZ-OpenAuto/{}/raw/main/{}",
-        repo, encoded_path
+        "{}/{}/{}/raw/{}/{}",
+        host.trim_end_matches('/'),
+        org,
+        repo,
+        branch,
+        encoded_path
     )
 }
 
-/// Build nested file tree from flat worktree data
-pub fn build_file_tree(flat_data: &WorktreeData, repo_name: &str) -> Vec<FileNode> {
+/// Build nested file tree from flat worktree data, linking files on `branch`
+pub fn build_file_tree(
+    flat_data: &WorktreeData,
+    repo_name: &str,
+    branch: &str,
+    config: &TreeConfig,
+) -> Vec<FileNode> {
     #[derive(Debug)]
     struct TreeBuilder {
         children: HashMap<String, TreeBuilder>,
         is_file: bool,
         url: Option<String>,
+        fallback_urls: Vec<String>,
         size: Option<u64>,
         date: Option<String>,
+        /// Plausible modification time, for date sorting
+        time: Option<i64>,
     }
 
     impl TreeBuilder {
@@ -42,26 +86,31 @@ pub fn build_file_tree(flat_data: &WorktreeData, repo_name: &str) -> Vec<FileNod
                 children: HashMap::new(),
                 is_file: false,
                 url: None,
+                fallback_urls: Vec::new(),
                 size: None,
                 date: None,
+                time: None,
             }
         }
 
-        fn into_node(self, name: String) -> FileNode {
-            let mut children: Vec<FileNode> = self
+        /// Convert to a node with sorted children, along with the newest time
+        /// in it (a folder's newest file) for the parent to sort by
+        fn into_node(self, name: String, sort: TreeSort) -> (FileNode, Option<i64>) {
+            let mut children: Vec<(FileNode, Option<i64>)> = self
                 .children
                 .into_iter()
-                .map(|(child_name, builder)| builder.into_node(child_name))
+                .map(|(child_name, builder)| builder.into_node(child_name, sort))
                 .collect();
+            children.sort_by(|a, b| compare_nodes(a, b, sort));
 
-            // Sort: folders first, then by name
-            children.sort_by(|a, b| match (&a.node_type, &b.node_type) {
-                (NodeType::Folder, NodeType::File) => std::cmp::Ordering::Less,
-                (NodeType::File, NodeType::Folder) => std::cmp::Ordering::Greater,
-                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-            });
+            let time = if self.is_file {
+                self.time
+            } else {
+                children.iter().filter_map(|(_, time)| *time).max()
+            };
+            let children = children.into_iter().map(|(node, _)| node).collect();
 
-            FileNode {
+            let node = FileNode {
                 name,
                 node_type: if self.is_file {
                     NodeType::File
@@ -70,17 +119,38 @@ pub fn build_file_tree(flat_data: &WorktreeData, repo_name: &str) -> Vec<FileNod
                 },
                 children,
                 url: self.url,
+                fallback_urls: self.fallback_urls,
                 size: self.size,
                 date: self.date,
-            }
+            };
+            (node, time)
         }
     }
 
+    let (primary_host, fallback_hosts) = match config.download_hosts.split_first() {
+        Some((primary, rest)) => (primary.as_str(), rest),
+        None => (DEFAULT_DOWNLOAD_HOST, &[][..]),
+    };
+
+    let hidden_files = config.hidden_files.get(repo_name);
+    let utc_offset = config.utc_offset;
+    let date_reference = config.relative_dates.then(|| {
+        config
+            .date_reference
+            .unwrap_or_else(|| chrono::Utc::now().timestamp())
+    });
+
     let mut root = TreeBuilder::new();
 
     // Build tree from flat paths
     for (path, meta) in flat_data.0.iter() {
-        if !should_include_file(path) {
+        // worktree.json generated on Windows uses `\` separators
+        let path = &path.replace('\\', "/");
+        if !should_include_file(path, &config.exclude) {
+            continue;
+        }
+
+        if hidden_files.is_some_and(|hidden| hidden.iter().any(|h| h == path)) {
             continue;
         }
 
@@ -96,27 +166,141 @@ pub fn build_file_tree(flat_data: &WorktreeData, repo_name: &str) -> Vec<FileNod
 
             if is_last {
                 current.is_file = true;
-                current.url = Some(generate_download_url(repo_name, path));
+                if let Some(ref url) = meta.url {
+                    // Release assets live on GitHub itself, so proxy hosts don't apply
+                    current.url = Some(url.clone());
+                } else {
+                    current.url = Some(generate_download_url(
+                        primary_host,
+                        &config.download_org,
+                        repo_name,
+                        branch,
+                        path,
+                    ));
+                }
+                if config.emit_fallback_urls && meta.url.is_none() {
+                    current.fallback_urls = fallback_hosts
+                        .iter()
+                        .map(|host| {
+                            generate_download_url(
+                                host,
+                                &config.download_org,
+                                repo_name,
+                                branch,
+                                path,
+                            )
+                        })
+                        .collect();
+                }
                 current.size = meta.size;
-                current.date = meta.time.map(format_timestamp);
+                current.time = meta.time.filter(|&ts| is_plausible_timestamp(ts));
+                current.date = meta.time.and_then(|ts| match date_reference {
+                    Some(reference) if is_plausible_timestamp(ts) => {
+                        Some(format_relative_timestamp(ts, reference))
+                    }
+                    Some(_) => None,
+                    None => format_timestamp(ts, &config.date_format, utc_offset),
+                });
             }
         }
     }
 
     // Convert to sorted node list
-    let mut result: Vec<FileNode> = root
-        .children
+    let mut nodes = root.into_node(String::new(), config.sort).0.children;
+    if config.collapse_folders {
+        nodes = collapse_folder_chains(nodes);
+    }
+    if let Some(max_depth) = config.max_depth {
+        truncate_depth(&mut nodes, max_depth);
+    }
+    nodes
+}
+
+/// Number of entries below `nodes`, counting folders and files
+fn count_entries(nodes: &[FileNode]) -> usize {
+    nodes
+        .iter()
+        .map(|node| 1 + count_entries(&node.children))
+        .sum()
+}
+
+/// Replace the contents of folders deeper than `max_depth` (0 being the top
+/// level) with one placeholder entry saying how many entries were hidden
+fn truncate_depth(nodes: &mut [FileNode], max_depth: usize) {
+    for node in nodes.iter_mut().filter(|n| n.node_type == NodeType::Folder) {
+        if max_depth > 0 {
+            truncate_depth(&mut node.children, max_depth - 1);
+            continue;
+        }
+        let hidden = count_entries(&node.children);
+        if hidden > 0 {
+            node.children = vec![FileNode {
+                name: format!("... ({} more items)", hidden),
+                node_type: NodeType::File,
+                children: Vec::new(),
+                url: None,
+                fallback_urls: Vec::new(),
+                size: None,
+                date: None,
+            }];
+        }
+    }
+}
+
+/// Merge each folder whose only child is a folder into that child, naming
+/// the result by the joined path, e.g. `slides/2023/week1`
+fn collapse_folder_chains(nodes: Vec<FileNode>) -> Vec<FileNode> {
+    nodes
         .into_iter()
-        .map(|(name, builder)| builder.into_node(name))
-        .collect();
+        .map(|mut node| {
+            while node.node_type == NodeType::Folder
+                && node.children.len() == 1
+                && node.children[0].node_type == NodeType::Folder
+            {
+                let child = node.children.remove(0);
+                node.name = format!("{}/{}", node.name, child.name);
+                node.children = child.children;
+            }
+            node.children = collapse_folder_chains(node.children);
+            node
+        })
+        .collect()
+}
 
-    result.sort_by(|a, b| match (&a.node_type, &b.node_type) {
-        (NodeType::Folder, NodeType::File) => std::cmp::Ordering::Less,
-        (NodeType::File, NodeType::Folder) => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-    });
+/// Order sibling nodes: folders first, then by `sort`, with names breaking
+/// ties. In date modes undated entries go last.
+fn compare_nodes(
+    (a, a_time): &(FileNode, Option<i64>),
+    (b, b_time): &(FileNode, Option<i64>),
+    sort: TreeSort,
+) -> Ordering {
+    let by_kind = match (&a.node_type, &b.node_type) {
+        (NodeType::Folder, NodeType::File) => Ordering::Less,
+        (NodeType::File, NodeType::Folder) => Ordering::Greater,
+        _ => Ordering::Equal,
+    };
+    let by_date = match (sort, a_time, b_time) {
+        (TreeSort::AlphaFoldersFirst, _, _) => Ordering::Equal,
+        (_, Some(_), None) => Ordering::Less,
+        (_, None, Some(_)) => Ordering::Greater,
+        (TreeSort::DateDescending, _, _) => b_time.cmp(a_time),
+        (TreeSort::DateAscending, _, _) => a_time.cmp(b_time),
+    };
+    by_kind
+        .then(by_date)
+        .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+}
 
-    result
+/// Render a JSX string prop. Values with a double quote or backslash are
+/// written as a JS string expression (`name={"a \"b\""}`), since JSX
+/// attribute strings have no escape syntax.
+fn jsx_prop(name: &str, value: &str) -> String {
+    if value.contains(['"', '\\']) {
+        let literal = serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string());
+        format!("{}={{{}}}", name, literal)
+    } else {
+        format!("{}=\"{}\"", name, value)
+    }
 }
 
 /// Convert file tree to JSX string for Fumadocs Files component
@@ -127,17 +311,24 @@ pub fn tree_to_jsx(nodes: &[FileNode], indent_level: usize) -> String {
     for node in nodes {
         match node.node_type {
             NodeType::Folder => {
-                result.push(format!("{}<Folder name=\"{}\">", indent, node.name));
+                result.push(format!(
+                    "{}<Folder {}>",
+                    indent,
+                    jsx_prop("name", &node.name)
+                ));
                 result.push(tree_to_jsx(&node.children, indent_level + 1));
                 result.push(format!("{}</Folder>", indent));
             }
             NodeType::File => {
-                let mut props = vec![format!("name=\"{}\"", node.name)];
+                let mut props = vec![jsx_prop("name", &node.name)];
                 if let Some(ref url) = node.url {
-                    props.push(format!("url=\"{}\"", url));
+                    props.push(jsx_prop("url", url));
+                }
+                if !node.fallback_urls.is_empty() {
+                    props.push(jsx_prop("data-fallback", &node.fallback_urls.join(" ")));
                 }
                 if let Some(ref date) = node.date {
-                    props.push(format!("date=\"{}\"", date));
+                    props.push(jsx_prop("date", date));
                 }
                 // Skip size if it's 0 or None
                 if let Some(size) = node.size {
@@ -156,6 +347,9 @@ pub fn tree_to_jsx(nodes: &[FileNode], indent_level: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    const UTC: FixedOffset = FixedOffset::east_opt(0).unwrap();
+    use crate::constants::{DEFAULT_BRANCH, GITHUB_ORG};
     use crate::models::FileMetadata;
 
     #[test]
@@ -166,6 +360,7 @@ mod tests {
             FileMetadata {
                 size: Some(100),
                 time: Some(1640000000),
+                url: None,
             },
         );
         data.insert(
@@ -173,11 +368,17 @@ mod tests {
             FileMetadata {
                 size: Some(200),
                 time: Some(1640000000),
+                url: None,
             },
         );
 
         let worktree = WorktreeData(data);
-        let tree = build_file_tree(&worktree, "test-repo");
+        let tree = build_file_tree(
+            &worktree,
+            "test-repo",
+            DEFAULT_BRANCH,
+            &TreeConfig::default(),
+        );
 
         assert_eq!(tree.len(), 2); // file1.txt and folder
         assert!(tree.iter().any(|n| n.name == "file1.txt"));
@@ -192,6 +393,7 @@ mod tests {
             FileMetadata {
                 size: Some(1024),
                 time: Some(1640000000),
+                url: None,
             },
         );
         data.insert(
@@ -199,6 +401,7 @@ mod tests {
             FileMetadata {
                 size: Some(2048),
                 time: Some(1640000000),
+                url: None,
             },
         );
         data.insert(
@@ -206,11 +409,17 @@ mod tests {
             FileMetadata {
                 size: Some(512),
                 time: Some(1640000000),
+                url: None,
             },
         );
 
         let worktree = WorktreeData(data);
-        let tree = build_file_tree(&worktree, "test-repo");
+        let tree = build_file_tree(
+            &worktree,
+            "test-repo",
+            DEFAULT_BRANCH,
+            &TreeConfig::default(),
+        );
 
         assert_eq!(tree.len(), 1); // Only docs folder at root
         let docs_folder = &tree[0];
@@ -227,6 +436,7 @@ mod tests {
             FileMetadata {
                 size: Some(100),
                 time: None,
+                url: None,
             },
         );
         data.insert(
@@ -234,6 +444,7 @@ mod tests {
             FileMetadata {
                 size: Some(100),
                 time: None,
+                url: None,
             },
         );
         data.insert(
@@ -241,11 +452,17 @@ mod tests {
             FileMetadata {
                 size: Some(100),
                 time: None,
+                url: None,
             },
         );
 
         let worktree = WorktreeData(data);
-        let tree = build_file_tree(&worktree, "test-repo");
+        let tree = build_file_tree(
+            &worktree,
+            "test-repo",
+            DEFAULT_BRANCH,
+            &TreeConfig::default(),
+        );
 
         // Folders should come before files
         assert_eq!(tree[0].name, "a_folder");
@@ -262,6 +479,7 @@ mod tests {
             FileMetadata {
                 size: Some(100),
                 time: None,
+                url: None,
             },
         );
         data.insert(
@@ -269,6 +487,7 @@ mod tests {
             FileMetadata {
                 size: Some(100),
                 time: None,
+                url: None,
             },
         );
         data.insert(
@@ -276,42 +495,438 @@ mod tests {
             FileMetadata {
                 size: Some(100),
                 time: None,
+                url: None,
             },
         );
 
         let worktree = WorktreeData(data);
-        let tree = build_file_tree(&worktree, "test-repo");
+        let tree = build_file_tree(
+            &worktree,
+            "test-repo",
+            DEFAULT_BRANCH,
+            &TreeConfig::default(),
+        );
 
         // Only valid.txt should remain
         assert_eq!(tree.len(), 1);
         assert_eq!(tree[0].name, "valid.txt");
     }
 
+    #[test]
+    fn test_explicit_url_overrides_computed() {
+        let release_url =
+            "https://github.com/HITSZ-OpenAuto/TEST101/releases/download/v1/videos.zip";
+        let mut data = HashMap::new();
+        data.insert(
+            "videos.zip".to_string(),
+            FileMetadata {
+                size: Some(1 << 30),
+                time: None,
+                url: Some(release_url.to_string()),
+            },
+        );
+        data.insert(
+            "notes.pdf".to_string(),
+            FileMetadata {
+                size: Some(100),
+                time: None,
+                url: None,
+            },
+        );
+        let config = TreeConfig {
+            download_hosts: vec![
+                DEFAULT_DOWNLOAD_HOST.to_string(),
+                "https://mirror.example.com/github.com".to_string(),
+            ],
+            emit_fallback_urls: true,
+            ..TreeConfig::default()
+        };
+
+        let tree = build_file_tree(&WorktreeData(data), "TEST101", DEFAULT_BRANCH, &config);
+        let jsx = tree_to_jsx(&tree, 0);
+
+        assert!(jsx.contains(&format!(
+            "<File name=\"videos.zip\" url=\"{}\" size=",
+            release_url
+        )));
+        assert!(jsx.contains(
+            "<File name=\"notes.pdf\" url=\"https://gh.hoa.moe/github.com/HITSZ-OpenAuto/TEST101/raw/main/notes.pdf\" data-fallback="
+        ));
+    }
+
     #[test]
     fn test_generate_download_url() {
-        let url = generate_download_url("TEST101", "slides/lecture1.pdf");
+        let url = generate_download_url(
+            DEFAULT_DOWNLOAD_HOST,
+            GITHUB_ORG,
+            "TEST101",
+            DEFAULT_BRANCH,
+            "slides/lecture1.pdf",
+        );
         assert_eq!(
             url,
             "https://gh.hoa.moe/github.com/HITSZ-OpenAuto/TEST101/raw/main/slides/lecture1.pdf"
         );
     }
 
+    #[test]
+    fn test_build_file_tree_links_repo_branch() {
+        let worktree = WorktreeData(HashMap::from([(
+            "slides/lecture1.pdf".to_string(),
+            FileMetadata {
+                size: Some(100),
+                time: None,
+                url: None,
+            },
+        )]));
+        let tree = build_file_tree(&worktree, "TEST101", "master", &TreeConfig::default());
+        let jsx = tree_to_jsx(&tree, 0);
+        assert!(jsx.contains("HITSZ-OpenAuto/TEST101/raw/master/slides/lecture1.pdf"));
+    }
+
+    #[test]
+    fn test_build_file_tree_sort_by_date() {
+        let file = |time| FileMetadata {
+            size: Some(1),
+            time,
+            url: None,
+        };
+        let worktree = WorktreeData(HashMap::from([
+            ("old.pdf".to_string(), file(Some(1_600_000_000))),
+            ("new.pdf".to_string(), file(Some(1_700_000_000))),
+            ("a-undated.pdf".to_string(), file(None)),
+            ("z-zero.pdf".to_string(), file(Some(0))),
+            ("mid.pdf".to_string(), file(Some(1_650_000_000))),
+            ("early/notes.pdf".to_string(), file(Some(1_500_000_000))),
+            ("recent/notes.pdf".to_string(), file(Some(1_750_000_000))),
+        ]));
+        let names = |sort| {
+            let config = TreeConfig {
+                sort,
+                ..Default::default()
+            };
+            build_file_tree(&worktree, "TEST101", DEFAULT_BRANCH, &config)
+                .into_iter()
+                .map(|node| node.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(TreeSort::DateDescending),
+            [
+                "recent",
+                "early",
+                "new.pdf",
+                "mid.pdf",
+                "old.pdf",
+                "a-undated.pdf",
+                "z-zero.pdf"
+            ]
+        );
+        assert_eq!(
+            names(TreeSort::DateAscending),
+            [
+                "early",
+                "recent",
+                "old.pdf",
+                "mid.pdf",
+                "new.pdf",
+                "a-undated.pdf",
+                "z-zero.pdf"
+            ]
+        );
+        assert_eq!(
+            names(TreeSort::AlphaFoldersFirst),
+            [
+                "early",
+                "recent",
+                "a-undated.pdf",
+                "mid.pdf",
+                "new.pdf",
+                "old.pdf",
+                "z-zero.pdf"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_file_tree_collapse_folders() {
+        let file = || FileMetadata {
+            size: Some(1),
+            time: None,
+            url: None,
+        };
+        let worktree = WorktreeData(HashMap::from([
+            ("slides/2023/week1/intro.pdf".to_string(), file()),
+            ("exams/2022/final.pdf".to_string(), file()),
+            ("exams/2023/final.pdf".to_string(), file()),
+            ("labs/lab1/handout.pdf".to_string(), file()),
+            ("labs/readme.txt".to_string(), file()),
+        ]));
+        let config = TreeConfig {
+            collapse_folders: true,
+            ..Default::default()
+        };
+        let tree = build_file_tree(&worktree, "TEST101", DEFAULT_BRANCH, &config);
+        let names: Vec<&str> = tree.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["exams", "labs", "slides/2023/week1"]);
+
+        // Branching folder and folder with files keep their children
+        assert_eq!(tree[0].children.len(), 2);
+        assert_eq!(tree[0].children[0].name, "2022");
+        assert_eq!(tree[1].children[0].name, "lab1");
+        assert_eq!(tree[2].children[0].name, "intro.pdf");
+
+        let jsx = tree_to_jsx(&tree, 0);
+        assert!(jsx.contains("<Folder name=\"slides/2023/week1\">"));
+
+        let tree = build_file_tree(&worktree, "TEST101", DEFAULT_BRANCH, &TreeConfig::default());
+        assert_eq!(tree[2].name, "slides");
+    }
+
+    #[test]
+    fn test_build_file_tree_max_depth() {
+        let file = || FileMetadata {
+            size: Some(1),
+            time: None,
+            url: None,
+        };
+        let worktree = WorktreeData(HashMap::from([
+            ("top.pdf".to_string(), file()),
+            ("a/one.pdf".to_string(), file()),
+            ("a/b/two.pdf".to_string(), file()),
+            ("a/b/c/three.pdf".to_string(), file()),
+            ("a/b/c/four.pdf".to_string(), file()),
+        ]));
+        let jsx = |max_depth| {
+            let config = TreeConfig {
+                max_depth: Some(max_depth),
+                ..Default::default()
+            };
+            tree_to_jsx(
+                &build_file_tree(&worktree, "TEST101", DEFAULT_BRANCH, &config),
+                0,
+            )
+        };
+
+        // Top level only: a/ hides one.pdf, b/, two.pdf, c/ and its two files
+        let top = jsx(0);
+        assert!(
+            top.contains("<Folder name=\"a\">\n  <File name=\"... (6 more items)\" />\n</Folder>")
+        );
+        assert!(top.contains("<File name=\"top.pdf\""));
+        assert!(!top.contains("one.pdf"));
+
+        let two = jsx(1);
+        assert!(two.contains("<File name=\"one.pdf\""));
+        assert!(two.contains("<Folder name=\"b\">\n    <File name=\"... (4 more items)\" />"));
+
+        let all = jsx(3);
+        assert!(all.contains("three.pdf"));
+        assert!(!all.contains("more items"));
+    }
+
+    #[test]
+    fn test_build_file_tree_backslash_paths() {
+        let worktree = WorktreeData(HashMap::from([
+            (
+                "slides\\第一章 intro.pdf".to_string(),
+                FileMetadata {
+                    size: Some(100),
+                    time: None,
+                    url: None,
+                },
+            ),
+            (
+                ".github\\workflows\\ci.yml".to_string(),
+                FileMetadata {
+                    size: Some(1),
+                    time: None,
+                    url: None,
+                },
+            ),
+        ]));
+        let tree = build_file_tree(&worktree, "TEST101", DEFAULT_BRANCH, &TreeConfig::default());
+
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].name, "slides");
+        assert_eq!(tree[0].node_type, NodeType::Folder);
+        let file = &tree[0].children[0];
+        assert_eq!(file.name, "第一章 intro.pdf");
+        assert_eq!(
+            file.url.as_deref(),
+            Some("https://gh.hoa.moe/github.com/HITSZ-OpenAuto/TEST101/raw/main/slides/%E7%AC%AC%E4%B8%80%E7%AB%A0%20intro.pdf")
+        );
+    }
+
+    #[test]
+    fn test_build_file_tree_custom_org_and_host() {
+        let worktree = WorktreeData(HashMap::from([(
+            "slides/lecture1.pdf".to_string(),
+            FileMetadata {
+                size: Some(100),
+                time: None,
+                url: None,
+            },
+        )]));
+        let config = TreeConfig {
+            download_hosts: vec!["https://mirror.example.com/github.com/".to_string()],
+            download_org: "Example-Org".to_string(),
+            ..Default::default()
+        };
+        let tree = build_file_tree(&worktree, "TEST101", "dev", &config);
+        assert_eq!(
+            tree[0].children[0].url.as_deref(),
+            Some("https://mirror.example.com/github.com/Example-Org/TEST101/raw/dev/slides/lecture1.pdf")
+        );
+    }
+
     #[test]
     fn test_generate_download_url_with_spaces() {
-        let url = generate_download_url("COURSE", "folder/file name.pdf");
+        let url = generate_download_url(
+            DEFAULT_DOWNLOAD_HOST,
+            GITHUB_ORG,
+            "COURSE",
+            DEFAULT_BRANCH,
+            "folder/file name.pdf",
+        );
         assert!(url.contains("file%20name.pdf"));
     }
 
     #[test]
     fn test_generate_download_url_with_chinese() {
-        let url = generate_download_url("COURSE", "作业/题目.pdf");
+        let url = generate_download_url(
+            DEFAULT_DOWNLOAD_HOST,
+            GITHUB_ORG,
+            "COURSE",
+            DEFAULT_BRANCH,
+            "作业/题目.pdf",
+        );
         assert!(url.contains("%E4%BD%9C%E4%B8%9A")); // Encoded Chinese
     }
 
     #[test]
     fn test_format_timestamp() {
-        let formatted = format_timestamp(1640000000);
-        assert_eq!(formatted, "2021-12-20");
+        let formatted = format_timestamp(1640000000, DEFAULT_DATE_FORMAT, UTC);
+        assert_eq!(formatted.as_deref(), Some("2021-12-20"));
+    }
+
+    #[test]
+    fn test_format_timestamp_custom_format() {
+        assert_eq!(
+            format_timestamp(1640000000, "%Y年%m月%d日", UTC).as_deref(),
+            Some("2021年12月20日")
+        );
+        assert_eq!(
+            format_timestamp(1640000000, "%Q", UTC).as_deref(),
+            Some("2021-12-20")
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp_utc_offset() {
+        // 2021-12-20 17:00 UTC is already the 21st in China
+        let evening = 1640019600;
+        let china = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(
+            format_timestamp(evening, DEFAULT_DATE_FORMAT, UTC).as_deref(),
+            Some("2021-12-20")
+        );
+        assert_eq!(
+            format_timestamp(evening, DEFAULT_DATE_FORMAT, china).as_deref(),
+            Some("2021-12-21")
+        );
+        assert_eq!(
+            format_timestamp(evening, "%Y-%m-%d %H:%M", china).as_deref(),
+            Some("2021-12-21 01:00")
+        );
+    }
+
+    #[test]
+    fn test_build_file_tree_utc_offset() {
+        let worktree = WorktreeData(HashMap::from([(
+            "notes.pdf".to_string(),
+            FileMetadata {
+                size: Some(1),
+                time: Some(1640019600),
+                url: None,
+            },
+        )]));
+        let config = TreeConfig {
+            utc_offset: FixedOffset::east_opt(8 * 3600).unwrap(),
+            ..Default::default()
+        };
+        let tree = build_file_tree(&worktree, "TEST101", DEFAULT_BRANCH, &config);
+        assert_eq!(tree[0].date.as_deref(), Some("2021-12-21"));
+
+        let tree = build_file_tree(&worktree, "TEST101", DEFAULT_BRANCH, &TreeConfig::default());
+        assert_eq!(tree[0].date.as_deref(), Some("2021-12-20"));
+    }
+
+    #[test]
+    fn test_format_timestamp_implausible() {
+        assert_eq!(format_timestamp(-1, DEFAULT_DATE_FORMAT, UTC), None);
+        assert_eq!(format_timestamp(0, DEFAULT_DATE_FORMAT, UTC), None);
+        assert_eq!(format_timestamp(i64::MAX, DEFAULT_DATE_FORMAT, UTC), None);
+    }
+
+    #[test]
+    fn test_tree_omits_date_for_bad_timestamp() {
+        let mut data = HashMap::new();
+        for (path, time) in [("bad.pdf", -86_400), ("good.pdf", 1640000000)] {
+            data.insert(
+                path.to_string(),
+                FileMetadata {
+                    size: None,
+                    time: Some(time),
+                    url: None,
+                },
+            );
+        }
+
+        let tree = build_file_tree(
+            &WorktreeData(data),
+            "COURSE",
+            DEFAULT_BRANCH,
+            &TreeConfig::default(),
+        );
+        let jsx = tree_to_jsx(&tree, 0);
+
+        assert!(jsx.contains("<File name=\"bad.pdf\" url=\"https://gh.hoa.moe/github.com/HITSZ-OpenAuto/COURSE/raw/main/bad.pdf\" />"));
+        assert!(jsx.contains("date=\"2021-12-20\""));
+    }
+
+    #[test]
+    fn test_build_tree_relative_dates() {
+        let reference = 1640000000;
+        let mut data = HashMap::new();
+        for (path, age_days) in [("a.pdf", 0), ("b.pdf", 3), ("c.pdf", 65), ("d.pdf", 800)] {
+            data.insert(
+                path.to_string(),
+                FileMetadata {
+                    size: None,
+                    time: Some(reference - age_days * 86_400),
+                    url: None,
+                },
+            );
+        }
+        let config = TreeConfig {
+            relative_dates: true,
+            date_reference: Some(reference),
+            ..TreeConfig::default()
+        };
+
+        let tree = build_file_tree(&WorktreeData(data), "COURSE", DEFAULT_BRANCH, &config);
+        let dates: Vec<_> = tree.iter().map(|node| node.date.as_deref()).collect();
+        assert_eq!(
+            dates,
+            vec![
+                Some("今天"),
+                Some("3 天前"),
+                Some("2 个月前"),
+                Some("2 年前")
+            ]
+        );
     }
 
     #[test]
@@ -321,6 +936,7 @@ mod tests {
             node_type: NodeType::File,
             children: vec![],
             url: Some("https://example.com/test.pdf".to_string()),
+            fallback_urls: vec![],
             size: Some(1024),
             date: Some("2021-12-20".to_string()),
         }];
@@ -333,6 +949,41 @@ mod tests {
         assert!(jsx.contains("size={1024}"));
     }
 
+    #[test]
+    fn test_tree_to_jsx_escapes_quotes() {
+        let nodes = vec![FileNode {
+            name: "C:\\notes".to_string(),
+            node_type: NodeType::Folder,
+            children: vec![FileNode {
+                name: "Lecture \"Intro\".pdf".to_string(),
+                node_type: NodeType::File,
+                children: vec![],
+                url: Some("https://example.com/Lecture%20%22Intro%22.pdf".to_string()),
+                fallback_urls: vec![],
+                size: None,
+                date: Some("\"today\"".to_string()),
+            }],
+            url: None,
+            fallback_urls: vec![],
+            size: None,
+            date: None,
+        }];
+
+        let jsx = tree_to_jsx(&nodes, 0);
+        assert_eq!(
+            jsx,
+            "<Folder name={\"C:\\\\notes\"}>\n  <File name={\"Lecture \\\"Intro\\\".pdf\"} url=\"https://example.com/Lecture%20%22Intro%22.pdf\" date={\"\\\"today\\\"\"} />\n</Folder>"
+        );
+
+        // Each expression prop reads back as the original value
+        let name = jsx.split("<File name={").nth(1).unwrap();
+        let name = &name[..name.find("} url=").unwrap()];
+        assert_eq!(
+            serde_json::from_str::<String>(name).unwrap(),
+            "Lecture \"Intro\".pdf"
+        );
+    }
+
     #[test]
     fn test_tree_to_jsx_folder() {
         let nodes = vec![FileNode {
@@ -343,10 +994,12 @@ mod tests {
                 node_type: NodeType::File,
                 children: vec![],
                 url: Some("https://example.com/file.txt".to_string()),
+                fallback_urls: vec![],
                 size: Some(100),
                 date: None,
             }],
             url: None,
+            fallback_urls: vec![],
             size: None,
             date: None,
         }];
@@ -364,6 +1017,7 @@ mod tests {
             node_type: NodeType::File,
             children: vec![],
             url: Some("https://example.com/empty.txt".to_string()),
+            fallback_urls: vec![],
             size: Some(0),
             date: None,
         }];
@@ -386,14 +1040,17 @@ mod tests {
                     node_type: NodeType::File,
                     children: vec![],
                     url: Some("https://example.com/file.txt".to_string()),
+                    fallback_urls: vec![],
                     size: Some(100),
                     date: None,
                 }],
                 url: None,
+                fallback_urls: vec![],
                 size: None,
                 date: None,
             }],
             url: None,
+            fallback_urls: vec![],
             size: None,
             date: None,
         }];
@@ -411,4 +1068,111 @@ mod tests {
         let jsx = tree_to_jsx(&nodes, 1);
         assert_eq!(jsx, "");
     }
+
+    #[test]
+    fn test_build_tree_with_alternate_host() {
+        let mut data = HashMap::new();
+        data.insert(
+            "slides/week1.pdf".to_string(),
+            FileMetadata {
+                size: Some(100),
+                time: None,
+                url: None,
+            },
+        );
+        let config = TreeConfig {
+            download_hosts: vec![
+                "https://mirror.example.com/gh/".to_string(),
+                "https://gh.hoa.moe/github.com".to_string(),
+            ],
+            emit_fallback_urls: false,
+            ..Default::default()
+        };
+
+        let tree = build_file_tree(&WorktreeData(data), "COURSE", DEFAULT_BRANCH, &config);
+        let file = &tree[0].children[0];
+
+        assert_eq!(
+            file.url.as_deref(),
+            Some("https://mirror.example.com/gh/HITSZ-OpenAuto/COURSE/raw/main/slides/week1.pdf")
+        );
+        assert!(file.fallback_urls.is_empty());
+    }
+
+    #[test]
+    fn test_build_tree_emits_fallback_urls() {
+        let mut data = HashMap::new();
+        data.insert(
+            "notes.pdf".to_string(),
+            FileMetadata {
+                size: Some(100),
+                time: None,
+                url: None,
+            },
+        );
+        let config = TreeConfig {
+            download_hosts: vec![
+                "https://gh.hoa.moe/github.com".to_string(),
+                "https://mirror.example.com/gh".to_string(),
+            ],
+            emit_fallback_urls: true,
+            ..Default::default()
+        };
+
+        let tree = build_file_tree(&WorktreeData(data), "COURSE", DEFAULT_BRANCH, &config);
+        let jsx = tree_to_jsx(&tree, 1);
+
+        assert!(jsx.contains(
+            "url=\"https://gh.hoa.moe/github.com/HITSZ-OpenAuto/COURSE/raw/main/notes.pdf\""
+        ));
+        assert!(jsx.contains(
+            "data-fallback=\"https://mirror.example.com/gh/HITSZ-OpenAuto/COURSE/raw/main/notes.pdf\""
+        ));
+    }
+
+    #[test]
+    fn test_hidden_files_exact_path() {
+        let mut data = HashMap::new();
+        for path in [
+            "exam/answers.pdf",
+            "exam/answers.pdf.txt",
+            "other/exam/answers.pdf",
+        ] {
+            data.insert(
+                path.to_string(),
+                FileMetadata {
+                    size: Some(100),
+                    time: None,
+                    url: None,
+                },
+            );
+        }
+        let mut config = TreeConfig::default();
+        config
+            .hidden_files
+            .insert("COURSE".to_string(), vec!["exam/answers.pdf".to_string()]);
+
+        let tree = build_file_tree(&WorktreeData(data), "COURSE", DEFAULT_BRANCH, &config);
+        let jsx = tree_to_jsx(&tree, 1);
+
+        assert!(!jsx.contains("raw/main/exam/answers.pdf\""));
+        assert!(jsx.contains("raw/main/exam/answers.pdf.txt\""));
+        assert!(jsx.contains("raw/main/other/exam/answers.pdf\""));
+
+        // Hidden paths are scoped to their own repo
+        let other = build_file_tree(
+            &WorktreeData(HashMap::from([(
+                "exam/answers.pdf".to_string(),
+                FileMetadata {
+                    size: Some(100),
+                    time: None,
+                    url: None,
+                },
+            )])),
+            "OTHER",
+            DEFAULT_BRANCH,
+            &config,
+        );
+        assert_eq!(other.len(), 1);
+    }
 }